@@ -0,0 +1,210 @@
+use crate::motor_ctx::CVP;
+use std::time::{Duration, Instant};
+
+// a slave stops following once its master's measurement is this old, so that it never keeps
+// running on a master that went quiet.
+pub const MASTER_TIMEOUT: Duration = Duration::from_millis(250);
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum GearingSource {
+    #[default]
+    Position,
+    Velocity,
+}
+
+// the slave's setpoint is `ratio` times the master's measured position or velocity, plus `offset`.
+#[derive(Clone, Debug)]
+pub struct GearingInput {
+    // index of the master in the motor list, it may be on any backend.
+    pub master: usize,
+    pub source: GearingSource,
+    pub ratio: f64,
+    pub offset: f64,
+    // time constant of a first order low pass on the master's measurement, `None` follows it unfiltered.
+    pub filter: Option<Duration>,
+}
+
+impl Default for GearingInput {
+    fn default() -> Self {
+        Self {
+            master: 0,
+            source: GearingSource::Position,
+            ratio: 1.,
+            offset: 0.,
+            filter: None,
+        }
+    }
+}
+
+impl GearingInput {
+    pub fn is_valid(&self) -> bool {
+        self.ratio.is_finite()
+            && self.offset.is_finite()
+            && self.filter.is_none_or(|filter| !filter.is_zero())
+    }
+
+    // `hold` is where a velocity geared slave is kept, for a slave that follows positions.
+    pub fn setpoint(&self, master: &CVP, hold: f64) -> CVP {
+        match self.source {
+            // the master's velocity is fed forward so the slave does not lag behind
+            GearingSource::Position => CVP {
+                position: self.ratio * master.position + self.offset,
+                velocity: self.ratio * master.velocity,
+                current: 0.,
+            },
+            GearingSource::Velocity => CVP {
+                position: hold,
+                velocity: self.ratio * master.velocity + self.offset,
+                current: 0.,
+            },
+        }
+    }
+}
+
+pub struct Follower {
+    pub input: GearingInput,
+    // the filtered master measurement and when it was taken
+    filtered: Option<(CVP, Instant)>,
+    // the slave's position when it started following
+    hold: f64,
+}
+
+impl Follower {
+    pub fn new(input: GearingInput, hold: f64) -> Self {
+        Self {
+            input,
+            filtered: None,
+            hold,
+        }
+    }
+
+    // the slave's setpoint for a master measurement taken at `time`, `None` if it was already seen.
+    pub fn update(&mut self, measured: CVP, time: Instant) -> Option<CVP> {
+        let filtered = match (self.filtered, self.input.filter) {
+            (Some((_, last)), _) if time <= last => return None,
+            (Some((prev, last)), Some(filter)) => {
+                let alpha = 1. - (-(time - last).as_secs_f64() / filter.as_secs_f64()).exp();
+                let smooth = |prev: f64, measured: f64| prev + alpha * (measured - prev);
+                CVP {
+                    position: smooth(prev.position, measured.position),
+                    velocity: smooth(prev.velocity, measured.velocity),
+                    current: smooth(prev.current, measured.current),
+                }
+            }
+            _ => measured,
+        };
+
+        self.filtered = Some((filtered, time));
+        Some(self.input.setpoint(&filtered, self.hold))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-9;
+
+    fn cvp(position: f64, velocity: f64, current: f64) -> CVP {
+        CVP {
+            position,
+            velocity,
+            current,
+        }
+    }
+
+    fn gearing(source: GearingSource, filter: Option<Duration>) -> GearingInput {
+        GearingInput {
+            master: 1,
+            source,
+            ratio: 2.,
+            offset: 0.5,
+            filter,
+        }
+    }
+
+    #[test]
+    fn position_gearing_feeds_the_velocity_forward() {
+        let input = gearing(GearingSource::Position, None);
+
+        let setpoint = input.setpoint(&cvp(3., -1., 4.), 10.);
+        assert!((setpoint.position - 6.5).abs() < EPSILON);
+        assert!((setpoint.velocity + 2.).abs() < EPSILON);
+        assert_eq!(setpoint.current, 0.);
+    }
+
+    #[test]
+    fn velocity_gearing_holds_the_position() {
+        let input = gearing(GearingSource::Velocity, None);
+
+        let setpoint = input.setpoint(&cvp(3., -1., 4.), 10.);
+        assert_eq!(setpoint.position, 10.);
+        assert!((setpoint.velocity + 1.5).abs() < EPSILON);
+        assert_eq!(setpoint.current, 0.);
+    }
+
+    #[test]
+    fn rejects_invalid_gearing() {
+        assert!(gearing(GearingSource::Position, Some(Duration::from_millis(10))).is_valid());
+        assert!(!gearing(GearingSource::Position, Some(Duration::ZERO)).is_valid());
+
+        let mut input = gearing(GearingSource::Position, None);
+        input.ratio = f64::NAN;
+        assert!(!input.is_valid());
+
+        let mut input = gearing(GearingSource::Position, None);
+        input.offset = f64::INFINITY;
+        assert!(!input.is_valid());
+    }
+
+    #[test]
+    fn unfiltered_follows_each_measurement() {
+        let mut follower = Follower::new(gearing(GearingSource::Position, None), 0.);
+        let start = Instant::now();
+
+        let first = follower.update(cvp(1., 0., 0.), start).unwrap();
+        assert!((first.position - 2.5).abs() < EPSILON);
+
+        let second = follower
+            .update(cvp(2., 0., 0.), start + Duration::from_millis(1))
+            .unwrap();
+        assert!((second.position - 4.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn ignores_measurements_already_seen() {
+        let mut follower = Follower::new(gearing(GearingSource::Position, None), 0.);
+        let start = Instant::now();
+
+        assert!(follower.update(cvp(1., 0., 0.), start).is_some());
+        assert!(follower.update(cvp(2., 0., 0.), start).is_none());
+        assert!(
+            follower
+                .update(cvp(2., 0., 0.), start - Duration::from_millis(1))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn filter_settles_with_its_time_constant() {
+        let filter = Duration::from_millis(100);
+        let mut follower = Follower::new(gearing(GearingSource::Velocity, Some(filter)), 0.);
+        let start = Instant::now();
+
+        // the first measurement is taken as is
+        let first = follower.update(cvp(0., 0., 0.), start).unwrap();
+        assert!((first.velocity - 0.5).abs() < EPSILON);
+
+        // a step covers 1 - 1/e of the way in one time constant
+        let next = follower.update(cvp(0., 1., 0.), start + filter).unwrap();
+        let filtered = 1. - (-1f64).exp();
+        assert!((next.velocity - (2. * filtered + 0.5)).abs() < EPSILON);
+
+        // and the rest of the way in another
+        let last = follower
+            .update(cvp(0., 1., 0.), start + 2 * filter)
+            .unwrap();
+        let filtered = filtered + (1. - filtered) * filtered;
+        assert!((last.velocity - (2. * filtered + 0.5)).abs() < EPSILON);
+    }
+}
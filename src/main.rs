@@ -1,6 +1,8 @@
 mod controller;
+mod gearing;
 mod motor_backend;
 mod motor_ctx;
+mod trajectory;
 
 fn main() -> eframe::Result {
     eframe::run_native(
@@ -11,26 +13,91 @@ fn main() -> eframe::Result {
 }
 
 pub use motor_backend::ds402::{Ds402Cmd, Ds402Response};
-pub use motor_backend::fourier::{FourierCmd, FourierResponse};
+pub use motor_backend::fourier::{FourierAddr, FourierCmd, FourierResponse};
 pub use motor_backend::protobuf::{ProtobufCmd, ProtobufResponse};
 
 struct AppState {
     motors: Vec<MotorUiConfig>,
-    _fourier: std::thread::JoinHandle<std::io::Result<()>>,
-    fourier_tx: std::sync::mpsc::Sender<(Ipv4Addr, FourierCmd)>,
-    fourier_rx: std::sync::mpsc::Receiver<(Ipv4Addr, FourierResponse)>,
+    fourier: std::thread::JoinHandle<std::io::Result<()>>,
+    fourier_tx: std::sync::mpsc::Sender<(FourierAddr, FourierCmd)>,
+    fourier_rx: std::sync::mpsc::Receiver<(FourierAddr, FourierResponse)>,
 
     network_ifs: sysinfo::Networks,
     ecat: Option<ChosenEcatNetwork>,
 
     protobuf_tx: std::sync::mpsc::Sender<(String, ProtobufCmd)>,
     protobuf_rx: std::sync::mpsc::Receiver<(String, ProtobufResponse)>,
-    _protobuf: std::thread::JoinHandle<std::io::Result<()>>,
+    protobuf: std::thread::JoinHandle<std::io::Result<()>>,
+
+    estopped: bool,
+
+    watchdog_storage: String,
+    watchdog_timeout: Option<Duration>,
+
+    // json file with the pdo layout of each ethercat device, read when the interface is chosen.
+    pdo_layout_storage: String,
+    pdo_layout_error: Option<String>,
+    // pcap file the ethercat frames are captured to, empty to not capture them.
+    capture_storage: String,
+    // why the ethercat backend stopped on its own, or could not be stopped
+    ecat_error: Option<String>,
+
+    // esi file or directory of them, describing the object dictionaries of ethercat devices.
+    esi_storage: String,
+    esi: motor_backend::ds402::EsiLibrary,
+    esi_status: Option<String>,
+    // every subdevice on the bus and its state, by index
+    ecat_topology: motor_backend::ds402::Topology,
+
+    // how far in the future a group start is scheduled, so every backend receives it in time.
+    group_delay_storage: String,
+    group_delay: Duration,
+    // the ethercat sender, shared with the panic hook so that it can stop the drives.
+    panic_ds402_tx: SharedDs402Tx,
+    // when closing gives up on the backends that are still stopping their motors
+    shutdown_deadline: Option<std::time::Instant>,
+    // an ethercat backend asked to stop, and when it is given up on
+    stopping_ecat: Option<(ChosenEcatNetwork, std::time::Instant)>,
+    // the interface to start ethercat on once the previous backend has stopped
+    next_ecat: Option<String>,
+}
+
+type SharedDs402Tx =
+    std::sync::Arc<std::sync::Mutex<Option<std::sync::mpsc::Sender<(usize, Ds402Cmd)>>>>;
+
+const DEFAULT_GROUP_DELAY: Duration = Duration::from_millis(200);
+
+// how long closing the app waits for the backends to stop and disable their motors.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+// how long a panicking thread waits for the backends to send their stop commands.
+const PANIC_STOP_GRACE: Duration = Duration::from_millis(100);
+
+fn install_panic_stop(
+    fourier_tx: std::sync::mpsc::Sender<(FourierAddr, FourierCmd)>,
+    protobuf_tx: std::sync::mpsc::Sender<(String, ProtobufCmd)>,
+    ds402_tx: SharedDs402Tx,
+) {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = fourier_tx.send((FourierAddr::UNSPECIFIED, FourierCmd::EStop));
+        let _ = protobuf_tx.send((String::new(), ProtobufCmd::EStop));
+        // the lock may be held by the panicking thread
+        if let Ok(tx) = ds402_tx.try_lock() {
+            if let Some(tx) = tx.as_ref() {
+                let _ = tx.send((0, Ds402Cmd::EStop));
+            }
+        }
+
+        std::thread::sleep(PANIC_STOP_GRACE);
+        default_hook(info);
+    }));
 }
 
 struct ChosenEcatNetwork {
     network_itf: String,
-    driver: std::thread::JoinHandle<()>,
+    driver: std::thread::JoinHandle<Result<(), String>>,
     ds402_tx: std::sync::mpsc::Sender<(usize, Ds402Cmd)>,
     ds402_rx: std::sync::mpsc::Receiver<(usize, Ds402Response)>,
 }
@@ -49,22 +116,343 @@ impl AppState {
         let protobuf_handle =
             std::thread::spawn(|| motor_backend::protobuf::event_loop(thread_rx, thread_tx));
 
+        let panic_ds402_tx = SharedDs402Tx::default();
+        install_panic_stop(
+            fourier_tx.clone(),
+            protobuf_tx.clone(),
+            panic_ds402_tx.clone(),
+        );
+
         AppState {
             motors: vec![],
-            _fourier: fourier_handle,
+            fourier: fourier_handle,
             fourier_tx,
             fourier_rx,
             network_ifs: sysinfo::Networks::new(),
             ecat: None,
             protobuf_tx,
             protobuf_rx,
-            _protobuf: protobuf_handle,
+            protobuf: protobuf_handle,
+            estopped: false,
+            watchdog_storage: format!("{}ms", motor_backend::DEFAULT_WATCHDOG_TIMEOUT.as_millis()),
+            watchdog_timeout: Some(motor_backend::DEFAULT_WATCHDOG_TIMEOUT),
+            pdo_layout_storage: String::new(),
+            pdo_layout_error: None,
+            capture_storage: String::new(),
+            ecat_error: None,
+            esi_storage: String::new(),
+            esi: Default::default(),
+            esi_status: None,
+            ecat_topology: Default::default(),
+            group_delay_storage: String::new(),
+            group_delay: DEFAULT_GROUP_DELAY,
+            panic_ds402_tx,
+            shutdown_deadline: None,
+            stopping_ecat: None,
+            next_ecat: None,
+        }
+    }
+
+    fn heartbeat(&self) {
+        let _ = self
+            .fourier_tx
+            .send((FourierAddr::UNSPECIFIED, FourierCmd::Heartbeat));
+        let _ = self
+            .protobuf_tx
+            .send((String::new(), ProtobufCmd::Heartbeat));
+        if let Some(ecat) = &self.ecat {
+            let _ = ecat.ds402_tx.send((0, Ds402Cmd::Heartbeat));
+        }
+    }
+
+    // asks every backend to stop and disable its motors. the threads exit once they are done.
+    fn shutdown(&mut self) {
+        if self.shutdown_deadline.is_some() {
+            return;
+        }
+        self.shutdown_deadline = Some(std::time::Instant::now() + SHUTDOWN_TIMEOUT);
+
+        let _ = self
+            .fourier_tx
+            .send((FourierAddr::UNSPECIFIED, FourierCmd::Shutdown));
+        let _ = self
+            .protobuf_tx
+            .send((String::new(), ProtobufCmd::Shutdown));
+        if let Some(ecat) = &self.ecat {
+            let _ = ecat.ds402_tx.send((0, Ds402Cmd::Shutdown));
+        }
+    }
+
+    // whether the window can close, which is once every backend has stopped or the shutdown
+    // timed out.
+    fn shutdown_finished(&self) -> bool {
+        let Some(deadline) = self.shutdown_deadline else {
+            return false;
+        };
+
+        let ecat_finished = self
+            .ecat
+            .iter()
+            .chain(self.stopping_ecat.as_ref().map(|(ecat, _)| ecat))
+            .all(|ecat| ecat.driver.is_finished());
+        if self.fourier.is_finished() && self.protobuf.is_finished() && ecat_finished {
+            return true;
+        }
+
+        let timed_out = std::time::Instant::now() > deadline;
+        if timed_out {
+            println!("timed out waiting for the backends to shut down");
+        }
+        timed_out
+    }
+
+    // closing the window is held off until the backends have stopped their motors, the ui
+    // keeps running meanwhile.
+    fn handle_close(&mut self, ctx: &egui::Context) {
+        if self.shutdown_finished() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        } else if ctx.input(|i| i.viewport().close_requested()) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.shutdown();
+        }
+    }
+
+    fn set_watchdog(&self) {
+        let timeout = self.watchdog_timeout;
+        let _ = self
+            .fourier_tx
+            .send((FourierAddr::UNSPECIFIED, FourierCmd::SetWatchdog(timeout)));
+        let _ = self
+            .protobuf_tx
+            .send((String::new(), ProtobufCmd::SetWatchdog(timeout)));
+        if let Some(ecat) = &self.ecat {
+            let _ = ecat.ds402_tx.send((0, Ds402Cmd::SetWatchdog(timeout)));
+        }
+    }
+
+    // starts the ethercat backend on the interface, with the pdo layouts from the layout file.
+    fn start_ecat(&mut self, interface_name: &str) {
+        let pdo_layouts = match self.pdo_layout_storage.as_str() {
+            "" => Ok(motor_backend::ds402::PdoLayouts::default()),
+            path => motor_backend::ds402::PdoLayouts::load(path),
+        };
+        let pdo_layouts = match pdo_layouts {
+            Ok(pdo_layouts) => pdo_layouts,
+            Err(e) => {
+                self.pdo_layout_error = Some(e);
+                return;
+            }
+        };
+        self.pdo_layout_error = None;
+        self.ecat_error = None;
+
+        let (main_tx, thread_rx) = std::sync::mpsc::channel();
+        let (thread_tx, main_rx) = std::sync::mpsc::channel();
+
+        let ifname = interface_name.to_owned();
+        let capture = match self.capture_storage.trim() {
+            "" => None,
+            path => Some(path.to_owned()),
+        };
+        let handle = std::thread::spawn(|| {
+            motor_backend::ds402::event_loop(thread_rx, thread_tx, ifname, pdo_layouts, capture)
+                .map_err(|e| motor_backend::ds402::describe_error(&*e))
+        });
+
+        if self.estopped {
+            let _ = main_tx.send((0, Ds402Cmd::EStop));
+        }
+        let _ = main_tx.send((0, Ds402Cmd::SetWatchdog(self.watchdog_timeout)));
+
+        if let Ok(mut tx) = self.panic_ds402_tx.lock() {
+            *tx = Some(main_tx.clone());
+        }
+
+        self.ecat = Some(ChosenEcatNetwork {
+            ds402_tx: main_tx,
+            ds402_rx: main_rx,
+            network_itf: interface_name.to_owned(),
+            driver: handle,
+        });
+    }
+
+    // asks the ethercat backend to stop its drives, `poll_stopping_ecat` waits for it to exit.
+    // a backend that does not exit in time still stops its drives once its command channel is
+    // dropped.
+    fn stop_ecat(&mut self) {
+        let Some(ecat) = self.ecat.take() else {
+            return;
+        };
+        let _ = ecat.ds402_tx.send((0, Ds402Cmd::Shutdown));
+        self.stopping_ecat = Some((ecat, std::time::Instant::now() + SHUTDOWN_TIMEOUT));
+    }
+
+    // called every frame rather than blocking it, so the other backends keep getting their
+    // heartbeats. starts the next backend once the previous one is gone.
+    fn poll_stopping_ecat(&mut self) {
+        if let Some((ecat, deadline)) = &self.stopping_ecat {
+            // the backend drops its end of the response channel when it exits, whatever it
+            // still reports until then is of no interest anymore.
+            let exited = loop {
+                match ecat.ds402_rx.try_recv() {
+                    Ok(_) => (),
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => break true,
+                    Err(std::sync::mpsc::TryRecvError::Empty) => break false,
+                }
+            };
+            let timed_out = std::time::Instant::now() > *deadline;
+            if !exited && !timed_out {
+                return;
+            }
+
+            let Some((ecat, _)) = self.stopping_ecat.take() else {
+                return;
+            };
+            if !exited {
+                self.ecat_error = Some(format!(
+                    "the ethercat backend on {} did not shut down in time",
+                    ecat.network_itf
+                ));
+            }
+            self.forget_ecat(ecat, exited);
+        }
+
+        if let Some(interface_name) = self.next_ecat.take() {
+            self.start_ecat(&interface_name);
+        }
+    }
+
+    // clears everything learned from a backend that has stopped, or is about to. one that has
+    // `exited` is joined to find out why.
+    fn forget_ecat(&mut self, ecat: ChosenEcatNetwork, exited: bool) {
+        if let Ok(mut tx) = self.panic_ds402_tx.lock() {
+            *tx = None;
+        }
+
+        if exited {
+            let error = match ecat.driver.join() {
+                Ok(result) => result.err(),
+                Err(_) => Some("the ethercat backend panicked".to_owned()),
+            };
+            if let Some(e) = error {
+                self.ecat_error = Some(format!("ethercat on {}: {e}", ecat.network_itf));
+            }
+        }
+
+        self.ecat_topology = Default::default();
+        for motor in &mut self.motors {
+            if let MotorUiBackendConfig::Ds402(config) = &mut motor.backend {
+                config.added = false;
+                config.adding = false;
+                config.profile.forget();
+                config.process_data.clear();
+                config.power_state = None;
+                config.fault = None;
+                config.homing.forget();
+            }
+        }
+    }
+
+    // passes each master's latest measurement on to the motors following it.
+    fn update_followers(&mut self) {
+        let outputs = self
+            .motors
+            .iter()
+            .map(|motor| motor.last_output)
+            .collect::<Vec<_>>();
+        let ds402_tx = self.ecat.as_ref().map(|ecat| &ecat.ds402_tx);
+
+        for (id, motor) in self.motors.iter_mut().enumerate() {
+            let Some(follower) = motor.follower.as_mut() else {
+                continue;
+            };
+
+            if motor.ignore_motor_output || self.estopped {
+                motor.follower = None;
+                continue;
+            }
+
+            let master = follower.input.master;
+            let setpoint = match outputs.get(master).copied().flatten() {
+                Some((cvp, time)) if master != id && time.elapsed() < gearing::MASTER_TIMEOUT => {
+                    match follower.update(cvp, time) {
+                        Some(setpoint) => Some(setpoint),
+                        None => continue,
+                    }
+                }
+                // the master is gone or has gone quiet
+                _ => None,
+            };
+
+            if setpoint.is_none() {
+                motor.follower = None;
+            }
+            motor.send_follow(&self.fourier_tx, ds402_tx, &self.protobuf_tx, setpoint);
+        }
+    }
+
+    // pairs each ds402 motor with what its subdevice reported and its esi description, which
+    // follows along as the index is edited or other esi files are loaded.
+    fn match_esi(&mut self) {
+        for motor in &mut self.motors {
+            let MotorUiBackendConfig::Ds402(config) = &mut motor.backend else {
+                continue;
+            };
+
+            config.identity = config
+                .idx
+                .and_then(|idx| self.ecat_topology.identity(idx))
+                .cloned();
+            config.esi = config.identity.as_ref().and_then(|identity| {
+                self.esi
+                    .find(identity.vendor_id, identity.product_code, identity.revision)
+            });
+        }
+    }
+
+    // commands every connected motor to stop and refuses new waveforms until `rearm` is called.
+    fn estop(&mut self) {
+        self.estopped = true;
+
+        let _ = self
+            .fourier_tx
+            .send((FourierAddr::UNSPECIFIED, FourierCmd::EStop));
+        let _ = self.protobuf_tx.send((String::new(), ProtobufCmd::EStop));
+        if let Some(ecat) = &self.ecat {
+            let _ = ecat.ds402_tx.send((0, Ds402Cmd::EStop));
+        }
+
+        for motor in &mut self.motors {
+            motor.ignore_motor_output = true;
+        }
+    }
+
+    // a backend stopped its motors because it stopped hearing from the ui, which has to be
+    // re-armed like after an emergency stop.
+    fn watchdog_expired(&mut self) {
+        self.estopped = true;
+        for motor in &mut self.motors {
+            motor.ignore_motor_output = true;
+        }
+    }
+
+    fn rearm(&mut self) {
+        self.estopped = false;
+        for motor in &mut self.motors {
+            motor.estop_refused = false;
+        }
+
+        let _ = self
+            .fourier_tx
+            .send((FourierAddr::UNSPECIFIED, FourierCmd::Rearm));
+        let _ = self.protobuf_tx.send((String::new(), ProtobufCmd::Rearm));
+        if let Some(ecat) = &self.ecat {
+            let _ = ecat.ds402_tx.send((0, Ds402Cmd::Rearm));
         }
     }
 }
 
 use motor_ctx::ControlState;
-use std::net::Ipv4Addr;
 
 struct MotorUiConfig {
     backend: MotorUiBackendConfig,
@@ -77,6 +465,19 @@ struct MotorUiConfig {
     input_cache: MotorInputCache,
     output: Vec<(motor_ctx::CVP, std::time::Instant)>,
     ignore_motor_output: bool,
+    // the latest measurement, kept even while the output is ignored so that other motors can follow it
+    last_output: Option<(motor_ctx::CVP, std::time::Instant)>,
+    follower: Option<gearing::Follower>,
+
+    limits: motor_ctx::SafetyLimits,
+    limits_cache: SafetyLimitsCache,
+    limit_violation: Option<motor_ctx::LimitViolation>,
+    // the backend refused a waveform or procedure while the emergency stop was active
+    estop_refused: bool,
+
+    // motors in the same group can be started together
+    group_storage: String,
+    group: Option<u32>,
 }
 
 struct ControlStateCache(u8);
@@ -203,6 +604,97 @@ impl ControlState {
     }
 }
 
+#[derive(Default)]
+struct SafetyLimitsCache {
+    position: LimitCache,
+    velocity: LimitCache,
+    current: LimitCache,
+    acceleration: LimitCache,
+}
+
+#[derive(Default)]
+struct LimitCache {
+    enabled: bool,
+    min_storage: String,
+    max_storage: String,
+    action: motor_ctx::LimitAction,
+}
+
+impl LimitCache {
+    fn display(
+        &mut self,
+        name: &str,
+        symmetric: bool,
+        limit: &mut Option<motor_ctx::Limit>,
+        ui: &mut egui::Ui,
+    ) -> bool {
+        use motor_ctx::{Limit, LimitAction};
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            changed |= ui.checkbox(&mut self.enabled, name).changed();
+            if !symmetric {
+                ui.label("min:");
+                changed |= ui.text_edit_singleline(&mut self.min_storage).changed();
+            }
+            ui.label("max:");
+            changed |= ui.text_edit_singleline(&mut self.max_storage).changed();
+
+            changed |= ui
+                .radio_value(&mut self.action, LimitAction::Clamp, "clamp")
+                .changed();
+            changed |= ui
+                .radio_value(&mut self.action, LimitAction::RampToZero, "ramp to zero")
+                .changed();
+            changed |= ui
+                .radio_value(&mut self.action, LimitAction::Fault, "fault")
+                .changed();
+        });
+
+        if changed {
+            let max = self.max_storage.parse::<f64>().ok();
+            let (min, max) = if symmetric {
+                (max.map(|max| -max.abs()), max.map(f64::abs))
+            } else {
+                (self.min_storage.parse::<f64>().ok(), max)
+            };
+
+            *limit = match (self.enabled, min, max) {
+                (true, Some(min), Some(max)) if min <= max => Some(Limit {
+                    min,
+                    max,
+                    action: self.action,
+                }),
+                _ => None,
+            };
+        }
+
+        if let Some(Limit { min, max, action }) = limit {
+            ui.label(format!("current {name} limit: [{min}, {max}] ({action:?})"));
+        }
+        changed
+    }
+}
+
+impl SafetyLimitsCache {
+    fn display(&mut self, limits: &mut motor_ctx::SafetyLimits, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        changed |= self
+            .position
+            .display("position", false, &mut limits.position, ui);
+        changed |= self
+            .velocity
+            .display("velocity", true, &mut limits.velocity, ui);
+        changed |= self
+            .current
+            .display("current", true, &mut limits.current, ui);
+        changed |= self
+            .acceleration
+            .display("acceleration", true, &mut limits.acceleration, ui);
+        changed
+    }
+}
+
 impl MotorUiConfig {
     fn new() -> Self {
         Self {
@@ -220,14 +712,123 @@ impl MotorUiConfig {
 
             output: vec![],
             ignore_motor_output: false,
+            last_output: None,
+            follower: None,
+
+            limits: Default::default(),
+            limits_cache: Default::default(),
+            limit_violation: None,
+            estop_refused: false,
+
+            group_storage: String::new(),
+            group: None,
+        }
+    }
+
+    // sends the motor its waveform, to begin at `start`. motors that share a start begin on the
+    // same tick, whichever backend they are on.
+    fn send_waveform(
+        &mut self,
+        fourier_tx: &std::sync::mpsc::Sender<(FourierAddr, FourierCmd)>,
+        ds402_tx: Option<&std::sync::mpsc::Sender<(usize, Ds402Cmd)>>,
+        protobuf_tx: &std::sync::mpsc::Sender<(String, ProtobufCmd)>,
+        start: std::time::Instant,
+    ) {
+        let input = self.input.clone();
+        let sent = match &self.backend {
+            MotorUiBackendConfig::Fourier(config) => config
+                .ip_addr()
+                .map(|ip| fourier_tx.send((ip, FourierCmd::SetWaveForm(input, start)))),
+            MotorUiBackendConfig::Ds402(config) => match (ds402_tx, config.idx) {
+                (Some(tx), Some(idx)) => Some(tx.send((idx, Ds402Cmd::SetWaveForm(input, start)))),
+                _ => None,
+            },
+            MotorUiBackendConfig::Protobuf(config) => config.path.as_ref().map(|path| {
+                protobuf_tx.send((path.clone(), ProtobufCmd::SetWaveForm(input, start)))
+            }),
+            MotorUiBackendConfig::None => None,
+        };
+
+        if let Some(Ok(())) = sent {
+            self.output.clear();
+            self.ignore_motor_output = false;
+            self.limit_violation = None;
+            self.estop_refused = false;
+            self.follower = match &self.input {
+                MotorInput::Follow(gearing) => {
+                    let hold = self.last_output.map_or(0., |(cvp, _)| cvp.position);
+                    Some(gearing::Follower::new(gearing.clone(), hold))
+                }
+                _ => None,
+            };
+        }
+    }
+
+    // streams the next setpoint to a motor that follows another, or stops it with `None`.
+    fn send_follow(
+        &self,
+        fourier_tx: &std::sync::mpsc::Sender<(FourierAddr, FourierCmd)>,
+        ds402_tx: Option<&std::sync::mpsc::Sender<(usize, Ds402Cmd)>>,
+        protobuf_tx: &std::sync::mpsc::Sender<(String, ProtobufCmd)>,
+        setpoint: Option<motor_ctx::CVP>,
+    ) {
+        match &self.backend {
+            MotorUiBackendConfig::Fourier(config) => {
+                if let Some(ip) = config.ip_addr() {
+                    let cmd = setpoint.map_or(FourierCmd::StopWaveform, FourierCmd::Follow);
+                    let _ = fourier_tx.send((ip, cmd));
+                }
+            }
+            MotorUiBackendConfig::Ds402(config) => {
+                if let (Some(tx), Some(idx)) = (ds402_tx, config.idx) {
+                    let cmd = setpoint.map_or(Ds402Cmd::StopWaveform, Ds402Cmd::Follow);
+                    let _ = tx.send((idx, cmd));
+                }
+            }
+            MotorUiBackendConfig::Protobuf(config) => {
+                if let Some(path) = &config.path {
+                    let cmd = setpoint.map_or(ProtobufCmd::StopWaveform, ProtobufCmd::Follow);
+                    let _ = protobuf_tx.send((path.clone(), cmd));
+                }
+            }
+            MotorUiBackendConfig::None => (),
+        }
+    }
+
+    // hands edited limits to a motor that is already added, the others get them with `Add`.
+    fn send_limits(
+        &self,
+        fourier_tx: &std::sync::mpsc::Sender<(FourierAddr, FourierCmd)>,
+        ds402_tx: Option<&std::sync::mpsc::Sender<(usize, Ds402Cmd)>>,
+        protobuf_tx: &std::sync::mpsc::Sender<(String, ProtobufCmd)>,
+    ) {
+        let limits = self.limits;
+        match &self.backend {
+            MotorUiBackendConfig::Fourier(config) if config.added => {
+                if let Some(ip) = config.ip_addr() {
+                    let _ = fourier_tx.send((ip, FourierCmd::SetLimits(limits)));
+                }
+            }
+            MotorUiBackendConfig::Ds402(config) if config.added => {
+                if let (Some(tx), Some(idx)) = (ds402_tx, config.idx) {
+                    let _ = tx.send((idx, Ds402Cmd::SetLimits(limits)));
+                }
+            }
+            MotorUiBackendConfig::Protobuf(config) if config.added => {
+                if let Some(path) = &config.path {
+                    let _ = protobuf_tx.send((path.clone(), ProtobufCmd::SetLimits(limits)));
+                }
+            }
+            _ => (),
         }
     }
 
     fn display(
         &mut self,
-        fourier_tx: &std::sync::mpsc::Sender<(Ipv4Addr, FourierCmd)>,
+        fourier_tx: &std::sync::mpsc::Sender<(FourierAddr, FourierCmd)>,
         ds402_tx: Option<&std::sync::mpsc::Sender<(usize, Ds402Cmd)>>,
         protobuf_tx: &std::sync::mpsc::Sender<(String, ProtobufCmd)>,
+        estopped: bool,
         ui: &mut egui::Ui,
         ctx: &egui::Context,
         id: usize,
@@ -236,9 +837,16 @@ impl MotorUiConfig {
         changed |= self
             .control_state
             .display(&mut self.control_state_cache, ui);
-        changed |= self
-            .backend
-            .display(fourier_tx, ds402_tx, protobuf_tx, &self.control_state, ui);
+        changed |= self.backend.display(
+            fourier_tx,
+            ds402_tx,
+            protobuf_tx,
+            &self.control_state,
+            &self.limits,
+            self.gear_reduction,
+            estopped,
+            ui,
+        );
         ui.horizontal(|ui| {
             ui.label("gear reduction: ");
             if ui
@@ -256,18 +864,37 @@ impl MotorUiConfig {
             ui.label(format!("current gear reduction: {}", self.gear_reduction));
         });
 
+        let limits_changed = ui
+            .collapsing("safety limits", |ui| {
+                self.limits_cache.display(&mut self.limits, ui)
+            })
+            .body_returned
+            .unwrap_or(false);
+        if limits_changed {
+            self.send_limits(fourier_tx, ds402_tx, protobuf_tx);
+        }
+
+        if let Some(violation) = &self.limit_violation {
+            ui.colored_label(egui::Color32::RED, format!("limit tripped: {violation}"));
+        }
+        if self.estop_refused {
+            ui.colored_label(
+                egui::Color32::RED,
+                "command refused, the emergency stop is active",
+            );
+        }
+
         changed |= self.input.display_options(&mut self.input_cache, ui, ctx, id);
         let _changed = changed;
 
+        let mut send = false;
         ui.horizontal(|ui| {
             ui.vertical(|ui| match &mut self.backend {
                 MotorUiBackendConfig::Fourier(config) => {
                     if let Some(ip) = config.ip_addr() {
-                        if ui.button("send to motor").clicked() {
-                            self.output.clear();
-                            self.ignore_motor_output = false;
-                            let _ = fourier_tx.send((ip, FourierCmd::SetWaveForm(self.input.clone())));
-                        }
+                        send = ui
+                            .add_enabled(!estopped, egui::Button::new("send to motor"))
+                            .clicked();
 
                         if ui.button("stop").clicked() {
                             self.ignore_motor_output = true;
@@ -277,11 +904,9 @@ impl MotorUiConfig {
                 }
                 MotorUiBackendConfig::Ds402(config) => {
                     if let (Some(tx), Some(idx)) = (ds402_tx, config.idx) {
-                        if ui.button("send to motor").clicked() {
-                            self.output.clear();
-                            self.ignore_motor_output = false;
-                            let _ = tx.send((idx, Ds402Cmd::SetWaveForm(self.input.clone())));
-                        }
+                        send = ui
+                            .add_enabled(!estopped, egui::Button::new("send to motor"))
+                            .clicked();
 
                         if ui.button("stop").clicked() {
                             self.ignore_motor_output = true;
@@ -291,12 +916,9 @@ impl MotorUiConfig {
                 }
                 MotorUiBackendConfig::Protobuf(config) => {
                     if let Some(path) = config.path.as_mut() {
-                        if ui.button("send to motor").clicked() {
-                            self.output.clear();
-                            self.ignore_motor_output = false;
-                            let _ = protobuf_tx
-                                .send((path.clone(), ProtobufCmd::SetWaveForm(self.input.clone())));
-                        }
+                        send = ui
+                            .add_enabled(!estopped, egui::Button::new("send to motor"))
+                            .clicked();
 
                         if ui.button("stop").clicked() {
                             self.ignore_motor_output = true;
@@ -312,11 +934,34 @@ impl MotorUiConfig {
                 _ => (),
             });
 
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label("sync group:");
+                    if ui.text_edit_singleline(&mut self.group_storage).changed() {
+                        self.group = self.group_storage.parse().ok();
+                    }
+                });
+                match self.group {
+                    Some(group) => ui.label(format!("current group: {group}")),
+                    None => ui.label("not in a group"),
+                };
+            });
+
             if ui.button("reset start time").clicked() {
                 //self.start_instant = std::time::Instant::now();
                 self.output.clear();
             }
         });
+
+        if send {
+            self.send_waveform(
+                fourier_tx,
+                ds402_tx,
+                protobuf_tx,
+                std::time::Instant::now(),
+            );
+        }
+
         self.display_output_graph(ui, ctx, id)
     }
 
@@ -492,10 +1137,14 @@ enum MotorUiBackendConfig {
 impl MotorUiBackendConfig {
     fn display(
         &mut self,
-        fourier_tx: &std::sync::mpsc::Sender<(Ipv4Addr, FourierCmd)>,
+        fourier_tx: &std::sync::mpsc::Sender<(FourierAddr, FourierCmd)>,
         ds402_tx: Option<&std::sync::mpsc::Sender<(usize, Ds402Cmd)>>,
         protobuf_tx: &std::sync::mpsc::Sender<(String, ProtobufCmd)>,
         control_state: &ControlState,
+        limits: &motor_ctx::SafetyLimits,
+        // only the ds402 backend converts between the motor and the output itself
+        gear_reduction: f64,
+        estopped: bool,
         ui: &mut egui::Ui,
     ) -> bool {
         ui.horizontal(|ui| {
@@ -538,10 +1187,28 @@ impl MotorUiBackendConfig {
 
                 if let Some(addr) = &config.ip {
                     if config.added {
+                        // `added` is cleared once the backend reports that the motor was removed
                         if ui.button("remove").clicked() {
                             let _ = fourier_tx.send((*addr, FourierCmd::Remove));
-                            config.added = false;
                         }
+                        ui.horizontal(|ui| {
+                            if ui.button("read errors").clicked() {
+                                let _ = fourier_tx.send((*addr, FourierCmd::ReadErrors));
+                            }
+                            if ui.button("clear errors").clicked() {
+                                let _ = fourier_tx.send((*addr, FourierCmd::ClearErrors));
+                            }
+                            if let Some(errors) = &config.errors {
+                                let color = if errors.any() {
+                                    egui::Color32::RED
+                                } else {
+                                    ui.visuals().text_color()
+                                };
+                                ui.colored_label(color, errors.to_string());
+                            }
+                        });
+                        config.params.display(*addr, fourier_tx, ui);
+                        config.procedures.display(*addr, fourier_tx, estopped, ui);
                     } else {
                         if ui.button("add").clicked() {
                             let _ = fourier_tx.send((
@@ -553,10 +1220,12 @@ impl MotorUiBackendConfig {
                                         gear_reduction: 1.,
                                         controller: None,
                                         state: control_state.clone(),
+                                        limits: *limits,
                                     },
                                 ),
                             ));
                             config.added = true;
+                            config.add_error = None;
                         }
                     }
                 }
@@ -567,24 +1236,79 @@ impl MotorUiBackendConfig {
                     let changed = config.display(ui);
 
                     if let Some(idx) = config.idx {
+                        // a drive can fault while it is enabled before its motor is added
+                        let faulted =
+                            config.power_state == Some(motor_backend::ds402::PowerState::Fault);
+                        if ui
+                            .add_enabled(faulted, egui::Button::new("reset fault"))
+                            .clicked()
+                        {
+                            let _ = tx.send((idx, Ds402Cmd::ResetFault));
+                            config.reset_failed = false;
+                        }
+
+                        // profile and homing parameters are converted like the motor's setpoints
+                        let units = config
+                            .units
+                            .units()
+                            .ok()
+                            .map(|units| units.with_gear_reduction(gear_reduction));
                         if config.added {
                             if ui.button("remove").clicked() {
                                 let _ = tx.send((idx, Ds402Cmd::Remove));
-                                config.added = false;
                             }
                         } else {
-                            if ui.button("add").clicked() {
+                            // a position waveform is relative to home, which the drive has to
+                            // know first
+                            let needs_home = matches!(control_state, ControlState::Position { .. })
+                                && !config.homing.homed();
+                            if needs_home {
+                                ui.label("home the drive before testing position control");
+                            }
+                            let add = ui.add_enabled(
+                                !needs_home && units.is_some() && !config.adding,
+                                egui::Button::new(if config.adding { "adding..." } else { "add" }),
+                            );
+                            // the profile parameters go out first, the motor is only added once
+                            // the drive has every one of them
+                            let applied = units
+                                .filter(|_| add.clicked())
+                                .is_some_and(|units| config.profile.apply(idx, tx, &units));
+                            if applied {
+                                config.adding = true;
+                            }
+                            let written = config.adding && !config.profile.writing();
+                            if written {
+                                config.adding = false;
+                            }
+                            let units = units.filter(|_| written && !config.profile.failed());
+                            if let Some(units) = units {
                                 let _ = tx.send((
                                     idx,
-                                    Ds402Cmd::Add(crate::motor_ctx::MotorConfig {
-                                        gear_reduction: 1.,
-                                        controller: None,
-                                        state: control_state.clone(),
-                                    }),
+                                    Ds402Cmd::Add(
+                                        config.position_mode,
+                                        units,
+                                        crate::motor_ctx::MotorConfig {
+                                            gear_reduction,
+                                            controller: None,
+                                            state: control_state.clone(),
+                                            limits: *limits,
+                                        },
+                                    ),
                                 ));
                                 config.added = true;
+                                config.enable_timed_out = None;
                             }
                         }
+
+                        config.units.display(idx, tx, config.added, ui);
+                        config
+                            .profile
+                            .display(idx, tx, config.added, units.as_ref(), ui);
+                        if !config.added {
+                            config.homing.display(idx, tx, units.as_ref(), ui);
+                        }
+                        config.sdo.display(idx, tx, config.esi.as_deref(), ui);
                     }
                     changed
                 }
@@ -600,7 +1324,6 @@ impl MotorUiBackendConfig {
                     if config.added {
                         if ui.button("remove").clicked() {
                             let _ = protobuf_tx.send((path.clone(), ProtobufCmd::Remove));
-                            config.added = false;
                         }
                     } else {
                         if ui.button("add").clicked() {
@@ -616,6 +1339,7 @@ impl MotorUiBackendConfig {
                                                 gear_reduction: 1.,
                                                 controller: None,
                                                 state: control_state.clone(),
+                                                limits: *limits,
                                             },
                                             dev,
                                         ),
@@ -684,6 +1408,19 @@ struct MotorInputCache {
     imp_mag_storage: String,
     impulse: Option<ImpulseInput>,
     custom: Vec<(f64, Duration)>,
+
+    traj_target_storage: String,
+    traj_vel_storage: String,
+    traj_accel_storage: String,
+    traj_jerk_storage: String,
+    traj_current_storage: String,
+    trajectory: Option<trajectory::TrajectoryInput>,
+
+    gear_master_storage: String,
+    gear_ratio_storage: String,
+    gear_offset_storage: String,
+    gear_filter_storage: String,
+    gearing: Option<gearing::GearingInput>,
 }
 
 #[derive(Clone, Debug)]
@@ -693,6 +1430,33 @@ pub enum MotorInput {
     Step(StepInput),
     Impulse(ImpulseInput),
     Custom(Vec<(f64, Duration)>),
+    // a point to point move, meant for `ControlState::Position`
+    Trajectory(trajectory::TrajectoryInput),
+    // driven by another motor's measurement, which may be on a different backend
+    Follow(gearing::GearingInput),
+}
+
+// only applies edits that leave the input valid.
+fn edit_input<T: Clone>(
+    ui: &mut egui::Ui,
+    label: &str,
+    storage: &mut String,
+    input: &mut T,
+    is_valid: impl FnOnce(&T) -> bool,
+    apply: impl FnOnce(&mut T, &str) -> Option<()>,
+) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label(label);
+        if ui.text_edit_singleline(storage).changed() {
+            let mut edited = input.clone();
+            if apply(&mut edited, storage).is_some() && is_valid(&edited) {
+                *input = edited;
+                changed = true;
+            }
+        }
+    });
+    changed
 }
 
 macro_rules! calc_y_bounds {
@@ -792,6 +1556,34 @@ impl MotorInput {
                     changed = true;
                 }
             }
+
+            if ui
+                .add(egui::RadioButton::new(
+                    matches!(self, Self::Trajectory(_)),
+                    "trajectory",
+                ))
+                .clicked()
+            {
+                if !matches!(self, Self::Trajectory(_)) {
+                    let cached = core::mem::take(&mut cache.trajectory).unwrap_or_default();
+                    self.move_prev_input(Self::Trajectory(cached), cache);
+                    changed = true;
+                }
+            }
+
+            if ui
+                .add(egui::RadioButton::new(
+                    matches!(self, Self::Follow(_)),
+                    "follow",
+                ))
+                .clicked()
+            {
+                if !matches!(self, Self::Follow(_)) {
+                    let cached = core::mem::take(&mut cache.gearing).unwrap_or_default();
+                    self.move_prev_input(Self::Follow(cached), cache);
+                    changed = true;
+                }
+            }
         });
 
         match self {
@@ -904,6 +1696,144 @@ impl MotorInput {
                 }
                 ui.label(format!("current magnitude: {}", magnitude));
             }
+            Self::Trajectory(t) => {
+                changed |= edit_input(
+                    ui,
+                    "target position:",
+                    &mut cache.traj_target_storage,
+                    t,
+                    trajectory::TrajectoryInput::is_valid,
+                    |t, s| {
+                        t.target = s.parse().ok()?;
+                        Some(())
+                    },
+                );
+                changed |= edit_input(
+                    ui,
+                    "max velocity:",
+                    &mut cache.traj_vel_storage,
+                    t,
+                    trajectory::TrajectoryInput::is_valid,
+                    |t, s| {
+                        t.max_velocity = s.parse().ok()?;
+                        Some(())
+                    },
+                );
+                changed |= edit_input(
+                    ui,
+                    "max acceleration:",
+                    &mut cache.traj_accel_storage,
+                    t,
+                    trajectory::TrajectoryInput::is_valid,
+                    |t, s| {
+                        t.max_acceleration = s.parse().ok()?;
+                        Some(())
+                    },
+                );
+                changed |= edit_input(
+                    ui,
+                    "jerk (empty for trapezoidal):",
+                    &mut cache.traj_jerk_storage,
+                    t,
+                    trajectory::TrajectoryInput::is_valid,
+                    |t, s| {
+                        t.jerk = match s {
+                            "" => None,
+                            s => Some(s.parse().ok()?),
+                        };
+                        Some(())
+                    },
+                );
+                changed |= edit_input(
+                    ui,
+                    "current per acceleration:",
+                    &mut cache.traj_current_storage,
+                    t,
+                    trajectory::TrajectoryInput::is_valid,
+                    |t, s| {
+                        t.current_per_acceleration = s.parse().ok()?;
+                        Some(())
+                    },
+                );
+
+                let jerk = match t.jerk {
+                    Some(jerk) => jerk.to_string(),
+                    None => "trapezoidal".to_string(),
+                };
+                ui.label(format!(
+                    "current trajectory: to {} at up to {} with {} acceleration, jerk {jerk}, {} current per acceleration",
+                    t.target, t.max_velocity, t.max_acceleration, t.current_per_acceleration
+                ));
+            }
+            Self::Follow(g) => {
+                changed |= edit_input(
+                    ui,
+                    "master motor:",
+                    &mut cache.gear_master_storage,
+                    g,
+                    gearing::GearingInput::is_valid,
+                    |g, s| {
+                        g.master = s.parse().ok()?;
+                        Some(())
+                    },
+                );
+
+                ui.horizontal(|ui| {
+                    ui.label("follow the master's");
+                    for (source, name) in [
+                        (gearing::GearingSource::Position, "position"),
+                        (gearing::GearingSource::Velocity, "velocity"),
+                    ] {
+                        changed |= ui.radio_value(&mut g.source, source, name).changed();
+                    }
+                });
+
+                changed |= edit_input(
+                    ui,
+                    "ratio:",
+                    &mut cache.gear_ratio_storage,
+                    g,
+                    gearing::GearingInput::is_valid,
+                    |g, s| {
+                        g.ratio = s.parse().ok()?;
+                        Some(())
+                    },
+                );
+                changed |= edit_input(
+                    ui,
+                    "offset:",
+                    &mut cache.gear_offset_storage,
+                    g,
+                    gearing::GearingInput::is_valid,
+                    |g, s| {
+                        g.offset = s.parse().ok()?;
+                        Some(())
+                    },
+                );
+                changed |= edit_input(
+                    ui,
+                    "filter time constant (empty for none):",
+                    &mut cache.gear_filter_storage,
+                    g,
+                    gearing::GearingInput::is_valid,
+                    |g, s| {
+                        g.filter = match s {
+                            "" => None,
+                            s => Some(s.parse::<duration_string::DurationString>().ok()?.into()),
+                        };
+                        Some(())
+                    },
+                );
+
+                let source = match g.source {
+                    gearing::GearingSource::Position => "position",
+                    gearing::GearingSource::Velocity => "velocity",
+                };
+                ui.label(format!(
+                    "current gearing: {} x motor {}'s {source} + {}, filter {:?}",
+                    g.ratio, g.master, g.offset, g.filter
+                ));
+            }
             _ => (),
         }
         self.display_prelim_graph(ctx, id);
@@ -977,6 +1907,35 @@ impl MotorInput {
                             plot.line(Line::new("const", PlotPoints::Borrowed(&points)));
                         });
                 }
+                Self::Trajectory(t) => {
+                    // previewed from zero, the motor starts from its measured position
+                    let profile = t.plan(0.);
+                    let duration = profile.duration();
+
+                    const SAMPLES: u32 = 200;
+                    let samples = (0..=SAMPLES)
+                        .map(|i| {
+                            let elapsed = duration.mul_f64(i as f64 / SAMPLES as f64);
+                            (elapsed.as_secs_f64(), profile.sample(elapsed))
+                        })
+                        .collect::<Vec<_>>();
+
+                    let position = samples
+                        .iter()
+                        .map(|(t, cvp)| PlotPoint::new(*t, cvp.position))
+                        .collect::<Vec<_>>();
+                    let velocity = samples
+                        .iter()
+                        .map(|(t, cvp)| PlotPoint::new(*t, cvp.velocity))
+                        .collect::<Vec<_>>();
+
+                    egui_plot::Plot::new("prelim")
+                        .default_x_bounds(0., f64::max(duration.as_secs_f64(), 1.))
+                        .show(ui, |plot| {
+                            plot.line(Line::new("position", PlotPoints::Borrowed(&position)));
+                            plot.line(Line::new("velocity", PlotPoints::Borrowed(&velocity)));
+                        });
+                }
                 _ => (),
             }
         });
@@ -989,58 +1948,211 @@ impl MotorInput {
             Self::Step(s) => cache.step = Some(s),
             Self::Impulse(i) => cache.impulse = Some(i),
             Self::Custom(c) => cache.custom = c,
+            Self::Trajectory(t) => cache.trajectory = Some(t),
+            Self::Follow(g) => cache.gearing = Some(g),
             _ => (),
         }
     }
 }
 
 impl eframe::App for AppState {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // normally sent when the close was requested, this only catches other ways of exiting
+        self.shutdown();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.request_repaint();
+        self.handle_close(ctx);
+
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.estop();
+        }
+
+        self.heartbeat();
+
         egui::TopBottomPanel::top("top pannel").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let estop_button = egui::Button::new(
+                        egui::RichText::new("EMERGENCY STOP (esc)")
+                            .strong()
+                            .color(egui::Color32::WHITE),
+                    )
+                    .fill(egui::Color32::RED);
+
+                    if ui.add(estop_button).clicked() {
+                        self.estop();
+                    }
+
+                    if self.shutdown_deadline.is_some() {
+                        ui.label("stopping the motors before closing...");
+                    }
+
+                    if self.estopped {
+                        ui.colored_label(egui::Color32::RED, "emergency stop active");
+                        if ui.button("re-arm").clicked() {
+                            self.rearm();
+                        }
+                    }
+
+                    ui.label("watchdog timeout (empty to disable):");
+                    if ui.text_edit_singleline(&mut self.watchdog_storage).changed() {
+                        let timeout = if self.watchdog_storage.is_empty() {
+                            Some(None)
+                        } else {
+                            self.watchdog_storage
+                                .parse::<duration_string::DurationString>()
+                                .ok()
+                                .map(|timeout| Some(timeout.into()))
+                        };
+
+                        if let Some(timeout) = timeout {
+                            if timeout != self.watchdog_timeout {
+                                self.watchdog_timeout = timeout;
+                                self.set_watchdog();
+                            }
+                        }
+                    }
+                    ui.label(format!("current watchdog timeout: {:?}", self.watchdog_timeout));
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("group start delay:");
+                    if ui.text_edit_singleline(&mut self.group_delay_storage).changed() {
+                        let delay = self
+                            .group_delay_storage
+                            .parse::<duration_string::DurationString>();
+                        self.group_delay = delay.map_or(self.group_delay, Into::into);
+                    }
+                    ui.label(format!("current delay: {:?}", self.group_delay));
+
+                    let mut groups = self
+                        .motors
+                        .iter()
+                        .filter_map(|motor| motor.group)
+                        .collect::<Vec<_>>();
+                    groups.sort_unstable();
+                    groups.dedup();
+
+                    for group in groups {
+                        if ui
+                            .add_enabled(
+                                !self.estopped,
+                                egui::Button::new(format!("start group {group}")),
+                            )
+                            .clicked()
+                        {
+                            // every motor gets the same start instant, so they begin together even
+                            // though the commands reach their backends one after another. fourier
+                            // motors start on their first reply after it, up to a round trip late.
+                            let start = std::time::Instant::now() + self.group_delay;
+                            for motor in self.motors.iter_mut().filter(|m| m.group == Some(group)) {
+                                motor.send_waveform(
+                                    &self.fourier_tx,
+                                    self.ecat.as_ref().map(|ecat| &ecat.ds402_tx),
+                                    &self.protobuf_tx,
+                                    start,
+                                );
+                            }
+                        }
+                    }
+                });
+
             //egui::ScrollArea::both().show(ui, |ui| {
                 ui.horizontal(|ui| {
                     ui.menu_button("ecat interface", |ui| {
                         self.network_ifs.refresh(true);
 
-                        for (interface_name, network) in &self.network_ifs {
+                        let mut chosen = None;
+                        for (interface_name, _) in &self.network_ifs {
                             if ui.button(format!("[{interface_name}]")).clicked() {
-                                match &self.ecat {
-                                    Some(ChosenEcatNetwork { network_itf, .. })
-                                        if network_itf == interface_name =>
-                                    {
-                                        ()
-                                    }
-                                    Some(ChosenEcatNetwork { ds402_tx, .. }) => {}
-                                    None => {
-                                        let (main_tx, thread_rx) = std::sync::mpsc::channel();
-                                        let (thread_tx, main_rx) = std::sync::mpsc::channel();
-
-                                        let ifname = interface_name.to_owned();
-                                        let handle = std::thread::spawn(|| {
-                                            motor_backend::ds402::event_loop(
-                                                thread_rx, thread_tx, ifname,
-                                            );
-                                        });
-
-                                        self.ecat = Some(ChosenEcatNetwork {
-                                            ds402_tx: main_tx,
-                                            ds402_rx: main_rx,
-                                            network_itf: interface_name.to_owned(),
-                                            driver: handle,
-                                        });
-                                    }
-                                }
+                                chosen = Some(interface_name.to_owned());
                             }
                         }
+
+                        match (chosen, &self.ecat) {
+                            (Some(chosen), Some(ChosenEcatNetwork { network_itf, .. }))
+                                if *network_itf == chosen => {}
+                            (Some(chosen), _) => {
+                                self.stop_ecat();
+                                self.next_ecat = Some(chosen);
+                            }
+                            (None, _) => (),
+                        }
                     });
+
+                    ui.label("pdo layout file (empty for the default):");
+                    ui.add_enabled(
+                        self.ecat.is_none(),
+                        egui::TextEdit::singleline(&mut self.pdo_layout_storage),
+                    );
+                    if let Some(e) = &self.pdo_layout_error {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+
+                    ui.label("pcap capture file (empty to not capture):");
+                    ui.add_enabled(
+                        self.ecat.is_none(),
+                        egui::TextEdit::singleline(&mut self.capture_storage),
+                    );
+                    if let Some(e) = &self.ecat_error {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("esi file or directory:");
+                    ui.text_edit_singleline(&mut self.esi_storage);
+                    if ui.button("load").clicked() {
+                        match motor_backend::ds402::EsiLibrary::load(self.esi_storage.trim()) {
+                            Ok(esi) => {
+                                self.esi_status =
+                                    Some(format!("{} device descriptions", esi.device_count()));
+                                self.esi = esi;
+                            }
+                            Err(e) => self.esi_status = Some(e),
+                        }
+                    }
+                    if let Some(status) = &self.esi_status {
+                        ui.label(status);
+                    }
                 });
 
+                if let Some(ecat) = &self.ecat {
+                    ui.collapsing("ethercat bus", |ui| {
+                        // only motors that are not running can be pointed at another subdevice
+                        let motors = self
+                            .motors
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, motor)| match &motor.backend {
+                                MotorUiBackendConfig::Ds402(config) => !config.added,
+                                _ => false,
+                            })
+                            .map(|(id, _)| id)
+                            .collect::<Vec<_>>();
+
+                        let picked = self
+                            .ecat_topology
+                            .display(&ecat.ds402_tx, &motors, ui)
+                            .map(|(idx, motor)| (idx, &mut self.motors[motor].backend));
+                        if let Some((idx, MotorUiBackendConfig::Ds402(config))) = picked {
+                            config.idx = Some(idx);
+                            config.idx_storage = idx.to_string();
+                        }
+                    });
+                }
+
                 if ui.button("add motor").clicked() {
                     self.motors.push(MotorUiConfig::new())
                 }
 
                 while let Ok((rx_ip, msg)) = self.fourier_rx.try_recv() {
+                    if let FourierResponse::WatchdogExpired = msg {
+                        self.watchdog_expired();
+                        continue;
+                    }
+
                     if let Some(motor) = self.motors.iter_mut().find(|m| match m.backend {
                         MotorUiBackendConfig::Fourier(motor_backend::fourier::MotorUiConfig {
                             ip,
@@ -1050,6 +2162,7 @@ impl eframe::App for AppState {
                     }) {
                         match msg {
                             FourierResponse::OutputCVP(cvp, time) => {
+                                motor.last_output = Some((cvp, time));
                                 if !motor.ignore_motor_output {
                                     motor.output.push((cvp, time));
                                 }
@@ -1061,6 +2174,42 @@ impl eframe::App for AppState {
                             FourierResponse::Error(io) => {
                                 println!("io error: {io}");
                             }
+                            FourierResponse::LimitTripped(violation) => {
+                                motor.limit_violation = Some(violation);
+                            }
+                            FourierResponse::EStopActive => {
+                                motor.estop_refused = true;
+                            }
+                            FourierResponse::Removed => {
+                                if let MotorUiBackendConfig::Fourier(config) = &mut motor.backend {
+                                    config.added = false;
+                                }
+                            }
+                            FourierResponse::AddFailed(e) => {
+                                if let MotorUiBackendConfig::Fourier(config) = &mut motor.backend {
+                                    config.added = false;
+                                    config.add_error = Some(e.to_string());
+                                }
+                            }
+                            FourierResponse::Procedure(status) => {
+                                if let MotorUiBackendConfig::Fourier(config) = &mut motor.backend {
+                                    config.procedures.update(status);
+                                }
+                            }
+                            FourierResponse::AxisError(errors) => {
+                                if let MotorUiBackendConfig::Fourier(config) = &mut motor.backend {
+                                    config.errors = Some(errors);
+                                }
+                            }
+                            FourierResponse::Params(..)
+                            | FourierResponse::ParamsWritten { .. }
+                            | FourierResponse::ParamError(_)
+                            | FourierResponse::ConfigSaved
+                            | FourierResponse::Rebooting => {
+                                if let MotorUiBackendConfig::Fourier(config) = &mut motor.backend {
+                                    config.params.update(&msg);
+                                }
+                            }
                             msg => println!("received from fourier: {msg:?}"),
                         }
                     } else {
@@ -1070,6 +2219,90 @@ impl eframe::App for AppState {
 
                 if let Some(ds402_rx) = self.ecat.as_ref().map(|ecat| &ecat.ds402_rx) {
                     while let Ok((rx_idx, msg)) = ds402_rx.try_recv() {
+                    if self.ecat_topology.update(rx_idx, &msg) {
+                        // whether the subdevice started again, or left op, matters to homing
+                        if let Ds402Response::Identity(_) | Ds402Response::SubdeviceStatus(_) = msg
+                        {
+                            for motor in &mut self.motors {
+                                match &mut motor.backend {
+                                    MotorUiBackendConfig::Ds402(config)
+                                        if config.idx == Some(rx_idx) =>
+                                    {
+                                        config.update_drive(&msg);
+                                    }
+                                    _ => (),
+                                }
+                            }
+                        }
+                        continue;
+                    }
+
+                    if let Ds402Response::CaptureFailed(e) = msg {
+                        self.ecat_error = Some(e);
+                        continue;
+                    }
+
+                    // like `watchdog_expired`, which cannot be called while the receiver is
+                    // borrowed
+                    if let Ds402Response::WatchdogExpired = msg {
+                        self.estopped = true;
+                        for motor in &mut self.motors {
+                            motor.ignore_motor_output = true;
+                        }
+                        continue;
+                    }
+
+                    // the object dictionary can be browsed and the drive homed before the motor
+                    // is added
+                    if let Ds402Response::SdoRead(..)
+                    | Ds402Response::SdoWritten(..)
+                    | Ds402Response::Homing(..)
+                    | Ds402Response::ProfileWritten(..) = msg
+                    {
+                        for motor in &mut self.motors {
+                            match &mut motor.backend {
+                                MotorUiBackendConfig::Ds402(config) if config.idx == Some(rx_idx) => {
+                                    config.sdo.update(&msg);
+                                    config.units.update(&msg);
+                                    config.homing.update(&msg);
+                                    config.profile.update(&msg);
+                                }
+                                _ => (),
+                            }
+                        }
+                        continue;
+                    }
+
+                    // the drive's state is shown whether or not its motor is added
+                    if let Ds402Response::PowerState(..)
+                    | Ds402Response::Fault(..)
+                    | Ds402Response::FaultResetFailed
+                    | Ds402Response::EnableTimedOut(..) = msg
+                    {
+                        for motor in &mut self.motors {
+                            match &mut motor.backend {
+                                MotorUiBackendConfig::Ds402(config) if config.idx == Some(rx_idx) => {
+                                    config.update_drive(&msg);
+                                }
+                                _ => (),
+                            }
+                        }
+                        continue;
+                    }
+
+                    // homing is refused before the motor is added
+                    if let Ds402Response::EStopActive = msg {
+                        for motor in &mut self.motors {
+                            match &motor.backend {
+                                MotorUiBackendConfig::Ds402(config) if config.idx == Some(rx_idx) => {
+                                    motor.estop_refused = true;
+                                }
+                                _ => (),
+                            }
+                        }
+                        continue;
+                    }
+
                         if let Some(motor) = self.motors.iter_mut().find(|m| match m.backend {
                             MotorUiBackendConfig::Ds402(motor_backend::ds402::MotorUiConfig {
                                 idx,
@@ -1080,6 +2313,7 @@ impl eframe::App for AppState {
                         }) {
                             match msg {
                                 Ds402Response::OutputCVP(cvp, time) => {
+                                    motor.last_output = Some((cvp, time));
                                     if !motor.ignore_motor_output {
                                         motor.output.push((cvp, time));
                                     }
@@ -1088,6 +2322,19 @@ impl eframe::App for AppState {
                                 Ds402Response::EndWaveform => {
                                     motor.ignore_motor_output = true;
                                 }
+                                Ds402Response::LimitTripped(violation) => {
+                                    motor.limit_violation = Some(violation);
+                                }
+                                Ds402Response::Removed => {
+                                    if let MotorUiBackendConfig::Ds402(config) = &mut motor.backend {
+                                        config.added = false;
+                                    }
+                                }
+                                Ds402Response::ProcessData(data) => {
+                                    if let MotorUiBackendConfig::Ds402(config) = &mut motor.backend {
+                                        config.process_data = data;
+                                    }
+                                }
                                 msg => println!("received from fourier: {msg:?}"),
                             }
                         }
@@ -1095,6 +2342,11 @@ impl eframe::App for AppState {
                 }
 
                 while let Ok((rx_path, msg)) = self.protobuf_rx.try_recv() {
+                    if let ProtobufResponse::WatchdogExpired = msg {
+                        self.watchdog_expired();
+                        continue;
+                    }
+
                     if let Some(motor) = self.motors.iter_mut().find(|m| match &m.backend {
                         MotorUiBackendConfig::Protobuf(motor_backend::protobuf::MotorUiConfig {
                             path,
@@ -1104,6 +2356,7 @@ impl eframe::App for AppState {
                     }) {
                         match msg {
                             ProtobufResponse::OutputCVP(cvp, time) => {
+                                motor.last_output = Some((cvp, time));
                                 if !motor.ignore_motor_output {
                                     motor.output.push((cvp, time));
                                 }
@@ -1115,6 +2368,17 @@ impl eframe::App for AppState {
                             ProtobufResponse::Error(io) => {
                                 println!("io error: {io}");
                             }
+                            ProtobufResponse::LimitTripped(violation) => {
+                                motor.limit_violation = Some(violation);
+                            }
+                            ProtobufResponse::EStopActive => {
+                                motor.estop_refused = true;
+                            }
+                            ProtobufResponse::Removed => {
+                                if let MotorUiBackendConfig::Protobuf(config) = &mut motor.backend {
+                                    config.added = false;
+                                }
+                            }
                             msg => println!("received from protobuf: {msg:?}"),
                         }
                     } else {
@@ -1123,12 +2387,22 @@ impl eframe::App for AppState {
                 }
 
 
+                // the ethercat backend only exits by itself when it failed
+                if let Some(ecat) = self.ecat.take_if(|ecat| ecat.driver.is_finished()) {
+                    self.forget_ecat(ecat, true);
+                }
+                self.poll_stopping_ecat();
+
+                self.update_followers();
+                self.match_esi();
+
                 ui.vertical(|ui| {
                     for (id, motor) in &mut self.motors.iter_mut().enumerate() {
                         motor.display(
                             &self.fourier_tx,
                             self.ecat.as_ref().map(|ecat| &ecat.ds402_tx),
                             &self.protobuf_tx,
+                            self.estopped,
                             ui,
                             ctx,
                             id,
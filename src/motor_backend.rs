@@ -2,7 +2,7 @@ pub(crate) mod ds402;
 pub(crate) mod fourier;
 pub(crate) mod protobuf;
 
-use crate::motor_ctx::MotorConfig;
+use crate::motor_ctx::{ControlState, MotorConfig, SafetyState};
 use std::time;
 
 pub(crate) enum RequestedMotorInput {
@@ -10,12 +10,20 @@ pub(crate) enum RequestedMotorInput {
     Step(crate::StepInput),
     Impulse(crate::ImpulseInput),
     Custom(Vec<(crate::motor_ctx::CVP, time::Duration)>),
+    Trajectory(crate::trajectory::Profile),
+    // the latest setpoint streamed from the ui while following another motor
+    Follow(crate::motor_ctx::CVP),
 }
 
 pub struct Backend<T> {
     motor_config: MotorConfig,
     input_cvp: Option<crate::motor_ctx::CVP>,
     request_input: Option<(std::time::Instant, RequestedMotorInput)>,
+    safety: SafetyState,
+    // the most recent measurement, trajectories start from it.
+    last_output: Option<crate::motor_ctx::CVP>,
+    // the position the current waveform started from, position waveforms are relative to it.
+    origin: f64,
     backend_specific: T,
 }
 
@@ -30,6 +38,216 @@ impl<T> Backend<T> {
             input_cvp: None,
             backend_specific,
             request_input: request_input.map(|inp| (std::time::Instant::now(), inp)),
+            safety: SafetyState::default(),
+            last_output: None,
+            origin: 0.,
         }
     }
+
+    // the command that should actually be sent to the motor for the current `input_cvp`,
+    // with the motor's safety limits applied.
+    pub(crate) fn limited_input(&mut self) -> crate::motor_ctx::CVP {
+        let input = self.input_cvp.unwrap_or_default();
+        self.safety
+            .limit(&self.motor_config.limits, input, time::Instant::now())
+    }
+
+    // limits edited while the motor is added apply from the next command on.
+    pub(crate) fn set_limits(&mut self, limits: crate::motor_ctx::SafetyLimits) {
+        self.motor_config.limits = limits;
+    }
+
+    pub(crate) fn check_output(&mut self, cvp: &crate::motor_ctx::CVP) {
+        self.last_output = Some(*cvp);
+        self.safety.check_measured(&self.motor_config.limits, cvp)
+    }
+
+    // queues `waveform` to begin at `start`. the start may lie in the future so that several motors,
+    // possibly on different backends, begin together. until then the motor holds still at its last
+    // measured position rather than carrying on with the previous waveform.
+    pub(crate) fn set_waveform(&mut self, waveform: crate::MotorInput, start: time::Instant) {
+        use crate::MotorInput;
+        use crate::motor_ctx::CVP;
+
+        self.origin = self
+            .last_output
+            .or(self.input_cvp)
+            .map(|cvp| cvp.position)
+            .unwrap_or_default();
+
+        let (first, input) = match waveform {
+            MotorInput::Constant(c) => {
+                let cvp = self.setpoint(Some(c));
+                (cvp, RequestedMotorInput::Cvp(cvp))
+            }
+            MotorInput::Idle => {
+                let cvp = self.setpoint(None);
+                (cvp, RequestedMotorInput::Cvp(cvp))
+            }
+            MotorInput::Step(s) => {
+                let cvp = if s.delay <= time::Duration::ZERO {
+                    self.setpoint(Some(s.magnitude))
+                } else {
+                    self.setpoint(None)
+                };
+                (cvp, RequestedMotorInput::Step(s))
+            }
+            MotorInput::Impulse(i) => {
+                let cvp = if i.delay < time::Duration::ZERO {
+                    self.setpoint(Some(i.magnitude))
+                } else {
+                    self.setpoint(None)
+                };
+                (cvp, RequestedMotorInput::Impulse(i))
+            }
+            MotorInput::Custom(c) => {
+                let cvps = c
+                    .into_iter()
+                    .map(|(mag, time)| (self.setpoint(Some(mag)), time))
+                    .collect::<Vec<_>>();
+
+                let cvp = cvps.first().map(|(cvp, _)| *cvp).unwrap_or_default();
+                (cvp, RequestedMotorInput::Custom(cvps))
+            }
+            MotorInput::Trajectory(t) => {
+                let profile = self.plan_trajectory(&t);
+                (
+                    profile.sample(time::Duration::ZERO),
+                    RequestedMotorInput::Trajectory(profile),
+                )
+            }
+            // hold still until the first setpoint arrives
+            MotorInput::Follow(_) => {
+                let hold = CVP {
+                    current: 0.,
+                    ..self.last_output.unwrap_or_default()
+                };
+                (hold, RequestedMotorInput::Follow(hold))
+            }
+        };
+
+        self.input_cvp = Some(if start <= time::Instant::now() {
+            first
+        } else {
+            CVP {
+                velocity: 0.,
+                current: 0.,
+                ..self.last_output.unwrap_or_default()
+            }
+        });
+        self.request_input = Some((start, input));
+    }
+
+    // the setpoint of a waveform at `magnitude`, or in between its pulses with `None`. the magnitude
+    // drives whatever the motor is controlled by, a position being relative to where the waveform
+    // started. everything else holds that start position still.
+    pub(crate) fn setpoint(&self, magnitude: Option<f64>) -> crate::motor_ctx::CVP {
+        let magnitude = magnitude.unwrap_or_default();
+        let hold = crate::motor_ctx::CVP {
+            position: self.origin,
+            velocity: 0.,
+            current: 0.,
+        };
+
+        match self.motor_config.state {
+            ControlState::Position { .. } => crate::motor_ctx::CVP {
+                position: self.origin + magnitude,
+                ..hold
+            },
+            ControlState::Velocity { .. } => crate::motor_ctx::CVP {
+                velocity: magnitude,
+                ..hold
+            },
+            ControlState::Torque { .. } => crate::motor_ctx::CVP {
+                current: magnitude,
+                ..hold
+            },
+        }
+    }
+
+    // true while a waveform is queued with a start time that has not been reached yet.
+    pub(crate) fn waiting_to_start(&self) -> bool {
+        matches!(&self.request_input, Some((start, _)) if *start > time::Instant::now())
+    }
+
+    // updates the setpoint of a motor that follows another one, ignored once it stopped following.
+    pub(crate) fn follow(&mut self, cvp: crate::motor_ctx::CVP) {
+        if let Some((_, RequestedMotorInput::Follow(setpoint))) = &mut self.request_input {
+            *setpoint = cvp;
+        }
+    }
+
+    pub(crate) fn plan_trajectory(
+        &self,
+        input: &crate::trajectory::TrajectoryInput,
+    ) -> crate::trajectory::Profile {
+        let start = self
+            .last_output
+            .or(self.input_cvp)
+            .map(|cvp| cvp.position)
+            .unwrap_or_default();
+        input.plan(start)
+    }
+
+    // drops the current waveform and holds still at the last measured position, the next
+    // waveform starts the motor again.
+    pub(crate) fn hold(&mut self) {
+        let hold = crate::motor_ctx::CVP {
+            velocity: 0.,
+            current: 0.,
+            ..self.last_output.unwrap_or_default()
+        };
+        self.input_cvp = Some(hold);
+        self.request_input = Some((time::Instant::now(), RequestedMotorInput::Cvp(hold)));
+    }
+
+    // drops the current waveform and latches a stop until the safety state is released.
+    pub(crate) fn stop(&mut self) {
+        self.request_input = None;
+        self.safety.stop();
+    }
+}
+
+pub(crate) const DEFAULT_WATCHDOG_TIMEOUT: time::Duration = time::Duration::from_millis(500);
+
+// tracks the last time the ui was heard from, so that a backend can stop its motors
+// when the ui stalls or goes away.
+pub(crate) struct Watchdog {
+    timeout: Option<time::Duration>,
+    last_heartbeat: time::Instant,
+    expired: bool,
+}
+
+impl Watchdog {
+    pub(crate) fn new(timeout: Option<time::Duration>) -> Self {
+        Self {
+            timeout,
+            last_heartbeat: time::Instant::now(),
+            expired: false,
+        }
+    }
+
+    pub(crate) fn feed(&mut self) {
+        self.last_heartbeat = time::Instant::now();
+        self.expired = false;
+    }
+
+    pub(crate) fn set_timeout(&mut self, timeout: Option<time::Duration>) {
+        self.timeout = timeout;
+        self.feed();
+    }
+
+    // returns true only once per missed heartbeat, until the watchdog is fed again.
+    pub(crate) fn check(&mut self) -> bool {
+        let Some(timeout) = self.timeout else {
+            return false;
+        };
+
+        if self.expired || self.last_heartbeat.elapsed() < timeout {
+            return false;
+        }
+
+        self.expired = true;
+        true
+    }
 }
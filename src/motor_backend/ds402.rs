@@ -1,4 +1,4 @@
-use ecat::{InitState, PdoConfig, PdoMapping, PdoObject, SdoWrite, TxBuf, TxIndex};
+use ecat::{InitState, PdoConfig, PdoMapping, SdoWrite, TxBuf, TxIndex};
 use ethercrab::{PduHeader, SubDevice, error::Error, received_frame::ReceivedPdu};
 
 use ecat::io::{TIMEOUT_CLEAR_MASK, TIMEOUT_MASK, WRITE_MASK};
@@ -18,21 +18,106 @@ use ethercrab::std::RawSocketDesc;
 use std::sync::mpsc;
 use std::time;
 
-use crate::MotorInput;
 use crate::motor_backend::Backend;
 use crate::motor_backend::RequestedMotorInput;
+use crate::motor_backend::{DEFAULT_WATCHDOG_TIMEOUT, Watchdog};
+
+mod cia402;
+use cia402::{Enabling, Progress};
+pub use cia402::PowerState;
+
+mod pdo;
+use pdo::PdoLayout;
+pub use pdo::{ImageMismatch, PdoLayouts};
+
+mod sdo;
+use sdo::{SdoPurpose, SdoQueue, SdoRequest};
+pub use sdo::{SdoAbort, SdoAddr};
+
+mod fault;
+use fault::FaultField;
+pub use fault::Ds402Fault;
+
+mod browser;
+pub use browser::SdoBrowser;
+
+mod esi;
+pub use esi::{EsiDevice, EsiLibrary};
+
+mod topology;
+use topology::WkcMonitor;
+pub use topology::{BusStatus, SubdeviceStatus, Topology};
+
+mod registers;
+use registers::{RegisterPurpose, RegisterQueue};
+
+mod dc;
+use dc::CycleTimer;
+
+mod homing;
+use homing::Homing;
+pub use homing::{HomingConfig, HomingPanel, HomingProgress};
+
+mod profile;
+pub use profile::{ProfilePanel, ProfileParams};
+
+mod units;
+pub use units::{Ds402Units, UnitsPanel};
+
+mod capture;
+use capture::Capture;
 
 struct UiContext {
     sub: bool,
     input: crate::MotorInput,
 }
 
+// how the drives in OP are being held.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Halt {
+    Running,
+    // disable operation, the drive can be enabled again directly.
+    Disabled,
+    // CiA402 quick stop, the drive has to go through the enable sequence again.
+    QuickStop,
+}
+
+macro_rules! for_each_user {
+    ($state:expr, |$idx:ident, $user:ident| $body:block) => {
+        if let InitState::Op(d, _) = &mut $state {
+            let mut next = 0;
+            while let Some($user) = d.subdev_mut(next) {
+                let $idx: usize = next;
+                next += 1;
+                $body
+            }
+        }
+    };
+}
+
+// why the event loop stopped, as shown in the ui. opening the raw socket is the usual
+// culprit, it needs privileges an ordinary user does not have.
+pub fn describe_error(e: &(dyn std::error::Error + 'static)) -> String {
+    match e.downcast_ref::<std::io::Error>() {
+        Some(e) if e.kind() == std::io::ErrorKind::PermissionDenied => format!(
+            "{e}, opening a raw socket needs root or CAP_NET_RAW \
+            (sudo setcap cap_net_raw,cap_net_admin=eip <binary>)"
+        ),
+        _ => e.to_string(),
+    }
+}
+
 pub(crate) fn event_loop(
     cmd_rx: mpsc::Receiver<(usize, Ds402Cmd)>,
     err_tx: mpsc::Sender<(usize, Ds402Response)>,
     network_interface: impl AsRef<str>,
+    pdo_layouts: PdoLayouts,
+    // a pcap file every frame is written to
+    capture: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let (_tx, mut rx, pdu_loop) = PDU_STORAGE.try_split().expect("cannot split pdu");
+    let (tx, mut rx, pdu_loop) = PDU_STORAGE
+        .try_split()
+        .map_err(|_| "the previous ethercat backend is still running")?;
 
     let mut maindevice = MainDevice::new(
         pdu_loop,
@@ -43,6 +128,31 @@ pub(crate) fn event_loop(
         },
     );
 
+    let result = run(
+        &mut maindevice,
+        &mut rx,
+        cmd_rx,
+        err_tx,
+        network_interface,
+        pdo_layouts,
+        capture,
+    );
+
+    // the ring and socket went away with `run`, so nothing is in flight anymore. the storage
+    // is given back for the next run, on this interface or another.
+    let _ = unsafe { maindevice.release_all(tx, rx) };
+    result
+}
+
+fn run(
+    maindevice: &mut MainDevice<'static>,
+    rx: &mut ethercrab::PduRx<'static>,
+    cmd_rx: mpsc::Receiver<(usize, Ds402Cmd)>,
+    err_tx: mpsc::Sender<(usize, Ds402Response)>,
+    network_interface: impl AsRef<str>,
+    pdo_layouts: PdoLayouts,
+    capture: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
     let mut ring = io_uring::IoUring::new(64)?;
 
     let mut probe = io_uring::register::Probe::new();
@@ -51,9 +161,17 @@ pub(crate) fn event_loop(
     use io_uring::{opcode, types};
 
     if !probe.is_supported(opcode::RecvMulti::CODE) || !probe.is_supported(opcode::Write::CODE) {
-        panic!("readmulti/write opcodes are not supported");
+        return Err("readmulti/write opcodes are not supported by this kernel".into());
     }
 
+    let mut capture = match capture {
+        Some(path) => Some(
+            Capture::create(&path)
+                .map_err(|e| format!("could not create the capture file {path}: {e}"))?,
+        ),
+        None => None,
+    };
+
     let mut sock = RawSocketDesc::new(network_interface.as_ref())?;
     //let mut sock = RawSocketDesc::new("enxf8e43bcd2609")?;
     let mtu = sock.interface_mtu()?;
@@ -89,7 +207,7 @@ pub(crate) fn event_loop(
     let write_entry = |id| id | WRITE_MASK;
 
     state.start(
-        &maindevice,
+        &*maindevice,
         retries,
         &timeout,
         &mut tx_bufs,
@@ -100,58 +218,121 @@ pub(crate) fn event_loop(
 
     let mut pdi_offset = ethercrab::PdiOffset::default();
 
-    let config = PdoConfig::new(
-        // inputs
-        [PdoMapping::new(
-            0x1A00,
-            const {
-                &[
-                    // status word
-                    PdoObject::new::<u16>(0x6041, 0),
-                    // actual position
-                    PdoObject::new::<u32>(0x6064, 0),
-                    // actual velocity
-                    PdoObject::new::<u32>(0x606C, 0),
-                    // actual torque
-                    PdoObject::new::<u16>(0x6077, 0),
-                ]
-            },
-        )],
-        [
-            // outputs
-            PdoMapping::new(
-                0x1600,
-                const {
-                    &[
-                        // control word
-                        PdoObject::new::<u16>(0x6040, 0),
-                        // target velocity
-                        PdoObject::new::<u32>(0x60FF, 0),
-                        // op mode
-                        PdoObject::new::<u8>(0x6060, 0),
-                        // padding
-                        PdoObject::new::<u8>(0, 0),
-                    ]
-                },
-            ),
-        ],
-    );
-
-    let mut ready = false;
+    // one pdo config per distinct layout, each subdevice picks its own by name
+    let layouts = pdo_layouts.layouts().cloned().collect::<Vec<_>>();
+    let objects = layouts
+        .iter()
+        .map(PdoLayout::objects)
+        .collect::<Vec<_>>();
+    let configs = objects
+        .iter()
+        .map(|(inputs, outputs)| {
+            PdoConfig::new(
+                [PdoMapping::new(pdo::TX_PDO, inputs)],
+                [PdoMapping::new(pdo::RX_PDO, outputs)],
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut halt = Halt::Running;
+    let mut wkc = WkcMonitor::new();
+    let mut cycle = CycleTimer::default();
+    // process data held back until its cycle starts, and the time it waits for. the timeout
+    // op reads the timespec when it is submitted.
+    let mut held_cycle: Option<TxBuf> = None;
+    let mut cycle_wait = Timespec::new();
+    // the drift compensation frame, it has to stay put while its write is in flight
+    let mut drift_frame = Vec::new();
+    let mut drift_in_flight = false;
+    let mut watchdog = Watchdog::new(Some(DEFAULT_WATCHDOG_TIMEOUT));
+    let mut shutdown_requested: Option<time::Instant> = None;
+    // commands that arrived before op, handled once it is reached
+    let mut pending = std::collections::VecDeque::new();
 
     loop {
+        if let Some(requested) = shutdown_requested {
+            // give the drives a few cycles to receive the shutdown controlword
+            if requested.elapsed() > SHUTDOWN_GRACE {
+                let _ = err_tx.send((0, Ds402Response::ShutdownComplete));
+                // dropping the ring and socket cancels everything still in flight
+                return Ok(());
+            }
+        }
+
+        // the capture may fail before the bus reaches op, or without it ever getting there
+        if let Some(e) = capture.as_mut().and_then(Capture::take_error) {
+            let _ = err_tx.send((0, Ds402Response::CaptureFailed(e)));
+        }
+
         let cqueue_entry = ring.completion().next();
         if let Some(entry) = cqueue_entry {
             let udata = entry.user_data();
-            if udata & TIMEOUT_CLEAR_MASK == TIMEOUT_CLEAR_MASK {
-                let key = udata & 0xFFFFFF;
-                let res = tx_bufs.remove(&key).expect("could not get received entry");
+            if udata == DRIFT_WRITE {
+                drift_in_flight = false;
+                if let Some(capture) = &mut capture {
+                    capture.record(&drift_frame);
+                }
+                continue;
+            }
+            if udata == CYCLE_TIMER || udata & TIMEOUT_CLEAR_MASK == TIMEOUT_CLEAR_MASK {
+                let res = if udata == CYCLE_TIMER {
+                    match held_cycle.take() {
+                        Some(res) => res,
+                        None => continue,
+                    }
+                } else {
+                    let key = udata & 0xFFFFFF;
+                    tx_bufs.remove(&key).expect("could not get received entry")
+                };
+
+                let is_pdi = res
+                    .received
+                    .as_ref()
+                    .is_some_and(|(header, _)| header.command_code == topology::LRW_COMMAND);
+                if let Some(wait) = cycle.wait().filter(|_| is_pdi) {
+                    cycle_wait = Timespec::from(wait);
+                    let timer = opcode::Timeout::new(&cycle_wait)
+                        .build()
+                        .user_data(CYCLE_TIMER);
+                    while unsafe { ring.submission().push(&timer).is_err() } {
+                        ring.submit().expect("could not submit ops");
+                    }
+                    ring.submit().unwrap();
+                    held_cycle = Some(res);
+                    continue;
+                }
+                if is_pdi {
+                    cycle.advance();
+
+                    // the drift is only compensated while sync0 runs, against the reference
+                    // clock picked when distributed clocks were configured
+                    let mut sync0 = false;
+                    for_each_user!(state, |_idx, user| {
+                        sync0 |= user.sync0.is_some() && user.cycle.is_some();
+                    });
+                    let reference = maindevice.dc_ref_address().filter(|_| sync0);
+                    if let Some(reference) = reference.filter(|_| !drift_in_flight) {
+                        drift_frame = dc::drift_frame(reference);
+                        let write = opcode::Write::new(
+                            types::Fd(sock.as_raw_fd()),
+                            drift_frame.as_ptr(),
+                            drift_frame.len() as _,
+                        )
+                        .build()
+                        .user_data(DRIFT_WRITE);
+                        while unsafe { ring.submission().push(&write).is_err() } {
+                            ring.submit().expect("could not submit ops");
+                        }
+                        ring.submit().unwrap();
+                        drift_in_flight = true;
+                    }
+                }
 
                 if let Some((header, pdu)) = res.received {
                     let _ = state.update(
                         pdu,
                         header,
-                        &mut maindevice,
+                        &mut *maindevice,
                         retries,
                         &timeout,
                         &mut tx_bufs,
@@ -160,7 +341,10 @@ pub(crate) fn event_loop(
                         res.configured_addr,
                         res.identifier,
                         &mut pdi_offset,
-                        |_, subdev| (User::new(subdev), &config),
+                        |_, subdev| {
+                            let layout = pdo_layouts.position(subdev.name());
+                            (User::new(subdev, layouts[layout].clone()), &configs[layout])
+                        },
                         |maindev, dev, received, entries, ring, index, identifier, output_buf| {
                             let flow = dev
                                 .update(
@@ -176,6 +360,7 @@ pub(crate) fn event_loop(
                                     output_buf,
                                     &write_entry,
                                     &err_tx,
+                                    halt,
                                 )
                                 .unwrap();
                             Ok(flow)
@@ -192,22 +377,36 @@ pub(crate) fn event_loop(
                         &write_entry,
                     );
                 } else {
-                    println!("actually timed out");
+                    // every retry went unanswered
+                    let user = match (&mut state, res.configured_addr) {
+                        (InitState::Op(d, _), Some(idx)) => {
+                            d.subdev_mut(idx as _).map(|user| (idx, user))
+                        }
+                        _ => None,
+                    };
+                    if let (Some((idx, user)), Some(kind)) =
+                        (user, topology::FrameKind::of(res.frame()))
+                    {
+                        user.timed_out(kind, &err_tx, idx as _);
+                    }
                 }
                 continue;
             } else if udata & WRITE_MASK == WRITE_MASK {
+                // the frame has gone out, retries included
+                let sent = capture.as_mut().zip(tx_bufs.get(&(udata & 0xFFFFFF)));
+                if let Some((capture, entry)) = sent {
+                    capture.record(entry.frame());
+                }
                 continue;
             } else if udata & TIMEOUT_MASK == TIMEOUT_MASK {
                 if !matches!(-entry.result(), libc::ECANCELED) {
                     let key = entry.user_data() & 0xFFFFFF;
 
+                    // the frame was answered and cleared up as the timeout fired
                     let Some(entry) = tx_bufs.get_mut(&key) else {
-                        println!("could not find entry :(");
                         continue;
                     };
 
-                    println!("timeout");
-
                     if entry.retries_remaining == 0 {
                         let timeout_clear =
                             io_uring::opcode::TimeoutRemove::new(key | TIMEOUT_MASK)
@@ -234,6 +433,17 @@ pub(crate) fn event_loop(
             };
 
             let buf = id.buffer();
+            if let Some(capture) = &mut capture {
+                capture.record(&buf);
+            }
+
+            if dc::is_drift_frame(&buf) {
+                // the reference clock read its time just before the frame came back
+                if let Some(system_time) = dc::drift_system_time(&buf) {
+                    cycle.align(system_time, time::Instant::now());
+                }
+                continue;
+            }
 
             let Some(recv_frame) = rx.receive_frame_io_uring(buf).unwrap() else {
                 continue;
@@ -247,6 +457,24 @@ pub(crate) fn event_loop(
                 };
                 let rx_idx = (idx, header).idx();
 
+                if header.command_code == topology::LRW_COMMAND {
+                    let mut subdevices = 0;
+                    for_each_user!(state, |_idx, _user| {
+                        subdevices += 1;
+                    });
+                    // every subdevice exchanges its process data in its own pdu
+                    let owner = tx_bufs.get(&rx_idx).and_then(|entry| entry.configured_addr);
+                    let expected = match (&mut state, owner) {
+                        (InitState::Op(d, _), Some(idx)) => d
+                            .subdev_mut(idx as _)
+                            .map(|user| user.layout.expected_wkc()),
+                        _ => None,
+                    };
+                    if let Some(expected) = expected {
+                        wkc.record(pdu.working_counter, subdevices, expected);
+                    }
+                }
+
                 if let Some(entry) = tx_bufs.get_mut(&rx_idx) {
                     entry.received = Some((header, pdu));
 
@@ -263,17 +491,69 @@ pub(crate) fn event_loop(
             }
         }
 
-        if ready {
-            while let Ok((idx, cmd)) = cmd_rx.try_recv() {
+        // no drive is enabled before op, so the loop can stop right away. everything else
+        // waits until the subdevices have reached op.
+        if !matches!(state, InitState::Op(..)) {
+            loop {
+                match cmd_rx.try_recv() {
+                    Ok((_, Ds402Cmd::Shutdown)) => {
+                        let _ = err_tx.send((0, Ds402Response::ShutdownComplete));
+                        return Ok(());
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => return Ok(()),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Ok((_, Ds402Cmd::Heartbeat)) => watchdog.feed(),
+                    Ok((_, Ds402Cmd::SetWatchdog(timeout))) => watchdog.set_timeout(timeout),
+                    Ok(cmd) => {
+                        watchdog.feed();
+                        pending.push_back(cmd);
+                    }
+                }
+            }
+        }
+
+        if matches!(state, InitState::Op(..)) {
+            loop {
+                let next = match pending.pop_front() {
+                    Some(cmd) => Ok(cmd),
+                    None => cmd_rx.try_recv(),
+                };
+                let (idx, cmd) = match next {
+                    Ok(cmd) => cmd,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        // the ui is gone, bring the drives to a controlled stop and exit once
+                        // they had the time to receive it
+                        if halt != Halt::QuickStop {
+                            halt = Halt::QuickStop;
+                            for_each_user!(state, |_idx, user| {
+                                if let UserState::Test(_, Some(backend)) = &mut user.state {
+                                    backend.stop();
+                                }
+                            });
+                        }
+                        shutdown_requested.get_or_insert_with(time::Instant::now);
+                        break;
+                    }
+                    Err(mpsc::TryRecvError::Empty) => break,
+                };
+
+                watchdog.feed();
+
                 match cmd {
-                    Ds402Cmd::Add(cfg) => {
-                        if let Some(User { device: motor, state }) = {
+                    Ds402Cmd::Add(position_mode, units, cfg) => {
+                        if let Some(User { state, .. }) = {
                             match &mut state {
                                 InitState::Op(d, _) => d.subdev_mut(idx),
                                 _ => unreachable!(),
                             }
                         } {
-                            let backend = Backend::new(cfg, Ds402Backend, None);
+                            let opmode = OpMode::new(&cfg.state, position_mode);
+                            let units = units.with_gear_reduction(cfg.gear_reduction);
+                            let mut backend =
+                                Backend::new(cfg, Ds402Backend::new(opmode, units), None);
+                            if halt != Halt::Running {
+                                backend.safety.stop();
+                            }
 
                             match state {
                                 UserState::Test(_, s) => {
@@ -284,12 +564,19 @@ pub(crate) fn event_loop(
                                             err_tx.send((idx, Ds402Response::DuplicateInstances));
                                     }
                                 }
+                                // the drive was removed, so it has to be enabled again
+                                UserState::Disabled => {
+                                    *state = UserState::Init(Enabling::new(), Some(backend))
+                                }
                                 _ => (),
                             }
                         }
                     }
-                    Ds402Cmd::SetWaveForm(waveform) => {
-                        if let Some(User { device: motor, state }) = {
+                    Ds402Cmd::SetWaveForm(..) if halt != Halt::Running => {
+                        let _ = err_tx.send((idx, Ds402Response::EStopActive));
+                    }
+                    Ds402Cmd::SetWaveForm(waveform, start) => {
+                        if let Some(User { state, .. }) = {
                             match &mut state {
                                 InitState::Op(d, _) => d.subdev_mut(idx),
                                 _ => unreachable!(),
@@ -299,147 +586,373 @@ pub(crate) fn event_loop(
                                 UserState::Test(_, Some(s)) => s,
                                 _ => continue,
                             };
-                            match waveform {
-                                MotorInput::Constant(c) => {
-                                    let cvp = crate::motor_ctx::CVP {
-                                        velocity: c,
-                                        position: 0.,
-                                        current: 0.,
-                                    };
-
-                                    motor.input_cvp = Some(cvp);
-                                    motor.request_input =
-                                        Some((time::Instant::now(), RequestedMotorInput::Cvp(cvp)))
+                            // a limit that faulted quick stopped the drive, it is enabled again
+                            let faulted = motor.safety.faulted();
+                            motor.safety.reset();
+                            // lifts a stopped waveform, an emergency stop refuses this command
+                            motor.safety.release();
+                            motor.set_waveform(waveform, start);
+
+                            match state {
+                                UserState::Test(_, backend) if faulted => {
+                                    *state = UserState::Init(Enabling::new(), backend.take());
                                 }
-                                MotorInput::Idle => {
-                                    let cvp = crate::motor_ctx::CVP::default();
-                                    motor.input_cvp = Some(cvp);
-                                    motor.request_input =
-                                        Some((time::Instant::now(), RequestedMotorInput::Cvp(cvp)));
+                                _ => (),
+                            }
+                        }
+                    }
+                    Ds402Cmd::Follow(cvp) => {
+                        if let Some(User {
+                            state: UserState::Test(_, Some(motor)),
+                            ..
+                        }) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            motor.follow(cvp);
+                        }
+                    }
+                    Ds402Cmd::StopWaveform => {
+                        if let Some(User { state, .. }) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            // the motor stays added, the next waveform starts it again
+                            if let UserState::Test(_, Some(backend)) = state {
+                                backend.hold();
+                            }
+                            let _ = err_tx.send((idx, Ds402Response::EndWaveform));
+                        }
+                    }
+                    Ds402Cmd::EStop => {
+                        if halt == Halt::Running {
+                            halt = Halt::Disabled;
+                        }
+
+                        for_each_user!(state, |idx, user| {
+                            if let UserState::Test(_, Some(backend)) = &mut user.state {
+                                backend.stop();
+                                let _ = err_tx.send((idx, Ds402Response::EndWaveform));
+                            }
+                        });
+                    }
+                    Ds402Cmd::Rearm => {
+                        let quick_stopped = halt == Halt::QuickStop;
+                        halt = Halt::Running;
+
+                        for_each_user!(state, |_idx, user| {
+                            if let UserState::Test(_, backend) = &mut user.state {
+                                if let Some(backend) = backend {
+                                    backend.safety.release();
                                 }
-                                MotorInput::Step(s) => {
-                                    let cvp = if s.delay <= time::Duration::ZERO {
-                                        crate::motor_ctx::CVP {
-                                            velocity: s.magnitude,
-                                            position: 0.,
-                                            current: 0.,
-                                        }
-                                    } else {
-                                        crate::motor_ctx::CVP::default()
-                                    };
 
-                                    motor.input_cvp = Some(cvp);
-                                    motor.request_input =
-                                        Some((time::Instant::now(), RequestedMotorInput::Step(s)))
+                                if quick_stopped {
+                                    user.state = UserState::Init(Enabling::new(), backend.take());
                                 }
-                                MotorInput::Impulse(i) => {
-                                    let cvp = if i.delay < time::Duration::ZERO {
-                                        crate::motor_ctx::CVP {
-                                            velocity: i.magnitude,
-                                            position: 0.,
-                                            current: 0.,
-                                        }
-                                    } else {
-                                        crate::motor_ctx::CVP::default()
-                                    };
-
-                                    motor.input_cvp = Some(cvp);
-                                    motor.request_input = Some((
-                                        time::Instant::now(),
-                                        RequestedMotorInput::Impulse(i),
-                                    ))
+                            }
+                        });
+                    }
+                    Ds402Cmd::ResetFault => {
+                        if let Some(User {
+                            state: UserState::Init(enabling, _),
+                            ..
+                        }) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            enabling.reset_fault();
+                        }
+                    }
+                    Ds402Cmd::ReadSdo(addr) => {
+                        if let Some(user) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            user.sdo.read(addr, SdoPurpose::Browser);
+                        }
+                    }
+                    Ds402Cmd::WriteSdo(addr, data) => {
+                        if let Some(user) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            user.sdo.write(addr, data, SdoPurpose::Browser);
+                        }
+                    }
+                    Ds402Cmd::SetCycleTime(period) => {
+                        cycle.set_period(period);
+                        for_each_user!(state, |_idx, user| {
+                            if user.sync0.is_some() {
+                                user.configure_sync0(user.sync0, period);
+                            }
+                        });
+                    }
+                    Ds402Cmd::SetSync0(shift) => {
+                        if let Some(user) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            user.configure_sync0(shift, cycle.period());
+                        }
+                    }
+                    Ds402Cmd::Home(..) if halt != Halt::Running => {
+                        let _ = err_tx.send((idx, Ds402Response::EStopActive));
+                    }
+                    Ds402Cmd::Home(config) => {
+                        if let Some(user) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            // a disabled drive is enabled as part of homing
+                            let enabled = match user.state {
+                                UserState::Test(power_state, None) => {
+                                    Some(power_state == PowerState::OperationEnabled)
                                 }
-                                MotorInput::Custom(c) => {
-                                    let cvps = c
-                                        .into_iter()
-                                        .map(|(mag, time)| {
-                                            let cvp = crate::motor_ctx::CVP {
-                                                velocity: mag,
-                                                position: 0.,
-                                                current: 0.,
-                                            };
-                                            (cvp, time)
-                                        })
-                                        .collect::<Vec<_>>();
-
-                                    let cvp =
-                                        cvps.iter().next().map(|(cvp, _)| *cvp).unwrap_or_default();
-
-                                    motor.input_cvp = Some(cvp);
-                                    motor.request_input = Some((
-                                        time::Instant::now(),
-                                        RequestedMotorInput::Custom(cvps),
-                                    ))
+                                UserState::Disabled => Some(false),
+                                _ => None,
+                            };
+                            if let Some(enabled) = enabled {
+                                let writes = config.writes();
+                                let homing = Homing::new(writes.len(), enabled);
+                                let progress = homing.progress().clone();
+                                user.state = UserState::Homing(homing);
+                                for (addr, data) in writes {
+                                    user.sdo.write(addr, data, SdoPurpose::Homing);
                                 }
+                                let _ = err_tx.send((idx, Ds402Response::Homing(progress)));
+                            } else {
+                                let reason = "the motor has to be removed, and the drive not \
+                                    faulted or being enabled"
+                                    .into();
+                                let _ = err_tx.send((
+                                    idx,
+                                    Ds402Response::Homing(HomingProgress::Failed(reason)),
+                                ));
                             }
                         }
                     }
-                    Ds402Cmd::StopWaveform => {
-                        if let Some(User { device: motor, state }) = {
+                    Ds402Cmd::SetProfile(params) => {
+                        if let Some(user) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            for (addr, data) in params.writes() {
+                                user.sdo.write(addr, data, SdoPurpose::Profile);
+                            }
+                        }
+                    }
+                    Ds402Cmd::StopHoming => {
+                        if let Some(User { state, .. }) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            if let UserState::Homing(_) = state {
+                                *state = UserState::Test(PowerState::OperationEnabled, None);
+                                let _ =
+                                    err_tx.send((idx, Ds402Response::Homing(HomingProgress::Stopped)));
+                            }
+                        }
+                    }
+                    Ds402Cmd::Heartbeat => (),
+                    Ds402Cmd::SetWatchdog(timeout) => watchdog.set_timeout(timeout),
+                    Ds402Cmd::SetLimits(limits) => {
+                        if let Some(User { state, .. }) = {
                             match &mut state {
                                 InitState::Op(d, _) => d.subdev_mut(idx),
                                 _ => unreachable!(),
                             }
                         } {
                             match state {
-                                UserState::Test(_, s) => *s = None,
+                                UserState::Init(_, Some(backend))
+                                | UserState::Test(_, Some(backend)) => backend.set_limits(limits),
                                 _ => (),
                             }
-                            let _ = err_tx.send((idx, Ds402Response::EndWaveform));
                         }
                     }
-                    _ => (),
+                    Ds402Cmd::Remove => {
+                        if let Some(User { state, .. }) = {
+                            match &mut state {
+                                InitState::Op(d, _) => d.subdev_mut(idx),
+                                _ => unreachable!(),
+                            }
+                        } {
+                            if let UserState::Test(_, Some(backend)) = state {
+                                backend.stop();
+                            }
+                            *state = UserState::Disabled;
+                        }
+                        let _ = err_tx.send((idx, Ds402Response::Removed));
+                    }
+                    Ds402Cmd::Shutdown => {
+                        for_each_user!(state, |idx, user| {
+                            if let UserState::Test(_, Some(backend)) = &mut user.state {
+                                backend.stop();
+                                let _ = err_tx.send((idx, Ds402Response::Removed));
+                            }
+                            user.state = UserState::Disabled;
+                        });
+                        shutdown_requested.get_or_insert_with(time::Instant::now);
+                    }
                 }
             }
+
+            if let Some(bus) = wkc.report() {
+                let _ = err_tx.send((0, Ds402Response::Bus(bus)));
+                if let Some(capture) = &mut capture {
+                    capture.flush();
+                }
+            }
+
+            if watchdog.check() {
+                halt = Halt::QuickStop;
+                for_each_user!(state, |_idx, user| {
+                    if let UserState::Test(_, Some(backend)) = &mut user.state {
+                        backend.stop();
+                    }
+                });
+                // sent even without motors, the ui latches its emergency stop either way
+                let _ = err_tx.send((0, Ds402Response::WatchdogExpired));
+            }
         }
     }
 }
 
+const SHUTDOWN_GRACE: time::Duration = time::Duration::from_millis(200);
+
+// user data of the timeout that releases held back process data, distinct from every
+// entry key and its masks.
+const CYCLE_TIMER: u64 = u64::MAX;
+// user data of the write that sends the drift compensation frame.
+const DRIFT_WRITE: u64 = u64::MAX - 1;
+
 #[derive(Default)]
 enum UserState {
     #[default]
     Idle,
-    Init(u32),
-    Test(u32, Option<Backend<Ds402Backend>>),
+    Init(Enabling, Option<Backend<Ds402Backend>>),
+    // the last power state reported to the ui
+    Test(PowerState, Option<Backend<Ds402Backend>>),
+    // holds the drive in ready to switch on, with its power stage off.
+    Disabled,
+    // an enabled drive without a backend runs its homing method.
+    Homing(Homing),
 }
 
-#[derive(Copy, Clone, ethercrab_wire::EtherCrabWireRead)]
-#[wire(bytes = 12)]
+// the objects the backend uses, decoded from the process image according to the device's
+// pdo layout. objects that are not mapped read as zero.
+#[derive(Copy, Clone)]
 struct RecvObj {
-    #[wire(bytes = 2)]
     status: u16,
-    #[wire(bytes = 4)]
     position: i32,
-    #[wire(bytes = 4)]
     velocity: i32,
-    #[wire(bytes = 2)]
     torque: i16,
+    opmode: i8,
 }
 
 impl RecvObj {
-    const ERR_MASK: u16 = 0x08;
+    // profile position: the drive latched the new set-point.
+    const SETPOINT_ACK_MASK: u16 = 0x1000;
+
+    fn decode(layout: &PdoLayout, image: &[u8]) -> Self {
+        Self {
+            status: layout.read(image, pdo::STATUSWORD) as u16,
+            position: layout.read(image, pdo::POSITION_ACTUAL) as i32,
+            velocity: layout.read(image, pdo::VELOCITY_ACTUAL) as i32,
+            torque: layout.read(image, pdo::TORQUE_ACTUAL) as i16,
+            opmode: layout.read(image, pdo::OPMODE_DISPLAY) as i8,
+        }
+    }
 }
 
-#[derive(Copy, Clone, ethercrab_wire::EtherCrabWireWrite)]
-#[wire(bytes = 8)]
+#[derive(Copy, Clone)]
 struct WriteObj {
-    #[wire(bytes = 2)]
     control: u16,
-    #[wire(bytes = 4)]
+    target_position: i32,
     target_velocity: i32,
-    #[wire(bytes = 1)]
+    target_torque: i16,
     opmode: u8,
-    #[wire(bytes = 1)]
-    padding: u8,
 }
 
 impl WriteObj {
-    fn new(control: u16, target_velocity: i32, opmode: u8) -> Self {
+    // profile position controlword bits.
+    const NEW_SETPOINT: u16 = 0x0010;
+    const CHANGE_IMMEDIATELY: u16 = 0x0020;
+
+    fn new(control: u16, opmode: OpMode) -> Self {
         Self {
             control,
-            target_velocity,
-            opmode,
-            padding: 0,
+            target_position: 0,
+            target_velocity: 0,
+            target_torque: 0,
+            opmode: opmode as u8,
+        }
+    }
+
+    // objects that are not in the device's layout are left out.
+    fn encode(&self, layout: &PdoLayout, image: &mut [u8]) {
+        image.fill(0);
+        layout.write(image, pdo::CONTROLWORD, self.control as _);
+        layout.write(image, pdo::TARGET_POSITION, self.target_position as _);
+        layout.write(image, pdo::TARGET_VELOCITY, self.target_velocity as _);
+        layout.write(image, pdo::TARGET_TORQUE, self.target_torque as _);
+        layout.write(image, pdo::OPMODE, self.opmode as _);
+    }
+
+    // targets that keep the motor where it is, whatever the mode. a cyclic position drive
+    // would otherwise jump to position zero as soon as it is enabled.
+    fn hold(control: u16, opmode: OpMode, recv: &RecvObj) -> Self {
+        Self {
+            target_position: recv.position,
+            ..Self::new(control, opmode)
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum PositionMode {
+    // the position setpoint is streamed every cycle (csp)
+    #[default]
+    Cyclic,
+    // each new setpoint is handed to the drive's own trajectory generator (pp)
+    Profile,
+}
+
+// modes of operation (0x6060).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[repr(u8)]
+pub enum OpMode {
+    ProfilePosition = 1,
+    Homing = 6,
+    CyclicPosition = 8,
+    CyclicVelocity = 9,
+    CyclicTorque = 10,
+}
+
+impl OpMode {
+    fn new(state: &ControlState, position_mode: PositionMode) -> Self {
+        match (state, position_mode) {
+            (ControlState::Position { .. }, PositionMode::Cyclic) => Self::CyclicPosition,
+            (ControlState::Position { .. }, PositionMode::Profile) => Self::ProfilePosition,
+            (ControlState::Velocity { .. }, _) => Self::CyclicVelocity,
+            (ControlState::Torque { .. }, _) => Self::CyclicTorque,
         }
     }
 }
@@ -472,21 +985,28 @@ impl UserState {
         output_buf: &mut [u8],
         write_entry: impl Fn(u64) -> u64,
         err_tx: &mpsc::Sender<(usize, Ds402Response)>,
+        halt: Halt,
+        layout: &PdoLayout,
+        sdo: &mut SdoQueue,
     ) -> Result<Option<ControlFlow>, Error> {
-        use ethercrab::{EtherCrabWireWrite, EtherCrabWireRead};
         match self {
             Self::Idle => {
                 if received.is_some() {
                     return Ok(None);
                 }
-                println!("attempting to start rx/tx");
-
-                let mut buf = [0; 20];
-                use ethercrab::EtherCrabWireWrite;
-                let write_obj = WriteObj::new(0x0080, 0, 9);
-                write_obj.pack_to_slice(&mut buf[12..]).unwrap();
+                let identity = subdev.identity();
+                let identity = SubdeviceIdentity {
+                    name: subdev.name().to_owned(),
+                    configured_address: subdev.configured_address(),
+                    vendor_id: identity.vendor_id,
+                    product_code: identity.product_id,
+                    revision: identity.revision,
+                };
+                let _ = err_tx.send((idx as _, Ds402Response::Identity(identity)));
 
-                println!("send: {buf:?}");
+                let mut buf = vec![0; layout.input_len() + layout.output_len()];
+                let write_obj = WriteObj::new(cia402::FAULT_RESET, OpMode::CyclicVelocity);
+                write_obj.encode(layout, &mut buf[layout.input_len()..]);
 
                 let (frame, handle) = unsafe { maindevice.prep_rx_tx(0, &buf).unwrap().unwrap() };
                 ecat::setup::setup_write(
@@ -502,70 +1022,208 @@ impl UserState {
                     write_entry,
                 )?;
 
-                *self = Self::Init(0);
+                *self = Self::Init(Enabling::new(), None);
             }
-            Self::Init(step) => {
+            Self::Init(enabling, backend) => {
                 let Some(ecat::DeviceResponse::Pdi(recv_bytes)) = received else {
                     return Ok(None)
                 };
 
-                let recv = RecvObj::unpack_from_slice(recv_bytes).unwrap();
+                let recv = RecvObj::decode(layout, recv_bytes);
 
-                //restart the motors if they are not in the correct state
-                if (*step > 1500 && recv.status & RecvObj::ERR_MASK == RecvObj::ERR_MASK) || (*step > 3500 && recv.status != 0x1637) {
-                    return Ok(Some(ControlFlow::Restart))
+                let (progress, changed) = enabling.update(recv.status);
+                if let Some(power_state) = changed {
+                    let _ = err_tx.send((idx as _, Ds402Response::PowerState(power_state)));
+                    if power_state == PowerState::Fault {
+                        // find out why, the drive waits for an explicit reset
+                        for field in FaultField::ALL {
+                            sdo.read(field.addr(), SdoPurpose::Fault(field));
+                        }
+                    }
                 }
 
-                let step = *step;
-                let ctrl = if step < 500 {
-                    0x0080
-                } else if step < 1500 {
-                    0x0006
-                } else if step < 2500 {
-                    0x0007
-                } else if step < 3500 {
-                    0x000F
-                } else {
-                    0x000F
+                let opmode = backend
+                    .as_ref()
+                    .map_or(OpMode::CyclicVelocity, |b| b.backend_specific.opmode);
+
+                let ctrl = match progress {
+                    Progress::Pending(ctrl) => ctrl,
+                    Progress::Enabled => {
+                        *self = Self::Test(PowerState::OperationEnabled, backend.take());
+                        cia402::ENABLE_OPERATION
+                    }
+                    Progress::TimedOut(power_state) => {
+                        let _ = err_tx.send((idx as _, Ds402Response::EnableTimedOut(power_state)));
+                        // only this drive gives up, adding it again retries. the rest of the bus
+                        // keeps running.
+                        *self = Self::Disabled;
+                        cia402::DISABLE_VOLTAGE
+                    }
+                    Progress::ResetFailed => {
+                        let _ = err_tx.send((idx as _, Ds402Response::FaultResetFailed));
+                        cia402::DISABLE_VOLTAGE
+                    }
                 };
-                let write_obj = WriteObj::new(ctrl, 0, 9);
-                write_obj.pack_to_slice(output_buf)?;
 
-                if step > 5000 {
-                    *self = Self::Test(step, None);
-                }
+                let write_obj = WriteObj::hold(ctrl, opmode, &recv);
+                write_obj.encode(layout, output_buf);
             }
-            Self::Test(step, backend) => {
+            Self::Test(reported, backend) => {
                 let Some(ecat::DeviceResponse::Pdi(recv_bytes)) = received else {
                     return Ok(None)
                 };
 
 
-                let recv = RecvObj::unpack_from_slice(recv_bytes).unwrap();
+                let recv = RecvObj::decode(layout, recv_bytes);
 
-                let out_cvp = crate::motor_ctx::CVP {
-                    current: recv.torque as _,
-                    position: recv.position as _,
-                    velocity: recv.velocity as _,
-                };
+                let power_state = PowerState::from_statusword(recv.status);
+                if power_state.is_fault() {
+                    // stop the waveform and wait for the fault to be diagnosed and reset
+                    let mut backend = backend.take();
+                    if let Some(backend) = &mut backend {
+                        backend.stop();
+                        let _ = err_tx.send((idx as _, Ds402Response::EndWaveform));
+                    }
+                    *self = Self::Init(Enabling::new(), backend);
 
-                err_tx.send((
+                    let write_obj =
+                        WriteObj::hold(cia402::DISABLE_VOLTAGE, OpMode::CyclicVelocity, &recv);
+                    write_obj.encode(layout, output_buf);
+                    return Ok(None);
+                }
+
+                if power_state != *reported {
+                    *reported = power_state;
+                    let _ = err_tx.send((idx as _, Ds402Response::PowerState(power_state)));
+                }
+
+                // a motor that has not been added reports in the drive's units
+                let units = backend
+                    .as_ref()
+                    .map_or(Ds402Units::default(), |b| b.backend_specific.units);
+                let out_cvp = units.cvp(recv.position, recv.velocity, recv.torque);
+
+                let _ = err_tx.send((
                     idx as _,
                     Ds402Response::OutputCVP(out_cvp, time::Instant::now()),
                 ));
 
-                let vel = if let Some(backend) = backend {
+                let opmode = backend
+                    .as_ref()
+                    .map_or(OpMode::CyclicVelocity, |b| b.backend_specific.opmode);
+
+                let write_obj = if let Some(backend) = backend {
+                    backend.check_output(&out_cvp);
                     backend.update_input(err_tx, idx as _);
-                    let input = backend.input_cvp.unwrap_or_default();
-                    input.velocity.round() as i32
+                    let write_obj = backend.output(&recv);
+
+                    if layout.has_extra_inputs() {
+                        backend.report_process_data(layout, recv_bytes, err_tx, idx as _);
+                    }
+
+                    if let Some(violation) = backend.safety.take_violation() {
+                        let _ = err_tx.send((idx as _, Ds402Response::LimitTripped(violation)));
+                    }
+
+                    if backend.safety.faulted() {
+                        WriteObj::hold(cia402::QUICK_STOP, opmode, &recv)
+                    } else {
+                        write_obj
+                    }
+                } else {
+                    WriteObj::hold(cia402::ENABLE_OPERATION, OpMode::CyclicVelocity, &recv)
+                };
+
+                let write_obj = match halt {
+                    Halt::Running => write_obj,
+                    Halt::Disabled => WriteObj::hold(cia402::SWITCH_ON, opmode, &recv),
+                    Halt::QuickStop => WriteObj::hold(cia402::QUICK_STOP, opmode, &recv),
+                };
+
+                write_obj.encode(layout, output_buf);
+            }
+            Self::Homing(homing) => {
+                let Some(ecat::DeviceResponse::Pdi(recv_bytes)) = received else {
+                    return Ok(None)
+                };
+
+                let recv = RecvObj::decode(layout, recv_bytes);
+
+                let power_state = PowerState::from_statusword(recv.status);
+                // while the drive is being enabled only a fault ends homing
+                let left = if homing.is_enabling() {
+                    power_state.is_fault()
                 } else {
-                    0
+                    power_state != PowerState::OperationEnabled
                 };
+                if left || halt != Halt::Running {
+                    // the drive stopped on its own, or was told to. the test state takes over
+                    // and reports why.
+                    let reason = match halt {
+                        Halt::Running => format!("the drive went to {power_state}"),
+                        _ => "emergency stop".into(),
+                    };
+                    let _ = err_tx.send((
+                        idx as _,
+                        Ds402Response::Homing(HomingProgress::Failed(reason)),
+                    ));
+                    *self = Self::Test(PowerState::OperationEnabled, None);
+
+                    let ctrl = match halt {
+                        Halt::Running => cia402::DISABLE_VOLTAGE,
+                        Halt::Disabled => cia402::SWITCH_ON,
+                        Halt::QuickStop => cia402::QUICK_STOP,
+                    };
+                    let write_obj = WriteObj::hold(ctrl, OpMode::CyclicVelocity, &recv);
+                    write_obj.encode(layout, output_buf);
+                    return Ok(None);
+                }
+
+                if let Some((progress, changed)) = homing.enable(recv.status) {
+                    if let Some(power_state) = changed {
+                        let _ = err_tx.send((idx as _, Ds402Response::PowerState(power_state)));
+                    }
+                    let ctrl = match progress {
+                        Progress::Pending(ctrl) => ctrl,
+                        Progress::Enabled => cia402::ENABLE_OPERATION,
+                        Progress::TimedOut(power_state) => {
+                            let reason =
+                                format!("the drive did not enable, it stayed {power_state}");
+                            let _ = err_tx.send((
+                                idx as _,
+                                Ds402Response::Homing(HomingProgress::Failed(reason)),
+                            ));
+                            *self = Self::Disabled;
+                            cia402::DISABLE_VOLTAGE
+                        }
+                        // faults end homing above, before a reset is ever asked for
+                        Progress::ResetFailed => cia402::DISABLE_VOLTAGE,
+                    };
+                    let write_obj = WriteObj::hold(ctrl, OpMode::Homing, &recv);
+                    write_obj.encode(layout, output_buf);
+                    return Ok(None);
+                }
 
-                println!("velocity input: {vel:?}");
+                let (ctrl, progress) = homing.update(recv.status, recv.opmode);
+                let write_obj = WriteObj::hold(ctrl, OpMode::Homing, &recv);
+                write_obj.encode(layout, output_buf);
 
-                let write_obj = WriteObj::new(0x000F, vel, 9);
-                write_obj.pack_to_slice(output_buf).unwrap();
+                if let Some(progress) = progress {
+                    let finished = progress.is_finished();
+                    let _ = err_tx.send((idx as _, Ds402Response::Homing(progress)));
+                    if finished {
+                        *self = Self::Test(PowerState::OperationEnabled, None);
+                    }
+                }
+            }
+            Self::Disabled => {
+                let Some(ecat::DeviceResponse::Pdi(_)) = received else {
+                    return Ok(None)
+                };
+
+                // shutdown
+                let write_obj = WriteObj::new(cia402::SHUTDOWN, OpMode::CyclicVelocity);
+                write_obj.encode(layout, output_buf);
             }
         }
         Ok(None)
@@ -579,15 +1237,41 @@ pub struct CVP {
     pub torque: i16,
 }
 
-use crate::motor_ctx::MotorConfig;
+use crate::motor_ctx::{ControlState, LimitViolation, MotorConfig, SafetyLimits};
 
 #[derive(Debug)]
 pub enum Ds402Cmd {
-    Add(MotorConfig),
+    Add(PositionMode, Ds402Units, MotorConfig),
     Remove,
-    SetWaveForm(crate::MotorInput),
+    // the waveform begins at the given instant, which may be in the future.
+    SetWaveForm(crate::MotorInput, time::Instant),
+    // the next setpoint of a motor whose waveform follows another motor.
+    Follow(crate::motor_ctx::CVP),
     StopWaveform,
     Shutdown,
+    // stops every subdevice, the index is ignored.
+    EStop,
+    Rearm,
+    // pulses the fault reset bit (0x6040 bit 7) of a faulted drive, which is enabled again
+    // once the fault has cleared.
+    ResetFault,
+    // object dictionary access through the mailbox, on any subdevice in op.
+    ReadSdo(SdoAddr),
+    WriteSdo(SdoAddr, Vec<u8>),
+    // the period of the process data cycle, `None` runs it as fast as the bus allows. the
+    // index is ignored.
+    SetCycleTime(Option<time::Duration>),
+    // enables the subdevice's sync0 pulse with the given shift, it needs a cycle time.
+    SetSync0(Option<time::Duration>),
+    // runs the homing method of a drive that has not been added, enabling it first if needed.
+    Home(HomingConfig),
+    StopHoming,
+    // writes the drive's profile parameters and limits, whatever state it is in.
+    SetProfile(ProfileParams),
+    Heartbeat,
+    SetWatchdog(Option<time::Duration>),
+    // replaces the safety limits of an added motor.
+    SetLimits(SafetyLimits),
 }
 
 #[derive(Debug)]
@@ -597,13 +1281,79 @@ pub enum Ds402Response {
     Timeout,
     DuplicateInstances,
     EndWaveform,
+    LimitTripped(LimitViolation),
+    EStopActive,
+    // sent once for the whole bus, with index 0.
+    WatchdogExpired,
+    Removed,
+    ShutdownComplete,
+    // the mapped objects the backend does not use itself, sent whenever one of them changes.
+    ProcessData(Vec<(String, i64)>),
+    // sent whenever the drive's state machine changes state.
+    PowerState(PowerState),
+    // the drive got stuck on the way to operation enabled. it is held disabled and its motor is
+    // removed, until it is added again.
+    EnableTimedOut(PowerState),
+    // what a faulted drive reported about its fault.
+    Fault(Ds402Fault),
+    // the drive was still faulted after a reset.
+    FaultResetFailed,
+    SdoRead(SdoAddr, Result<Vec<u8>, SdoAbort>),
+    SdoWritten(SdoAddr, Result<(), SdoAbort>),
+    // sent once per subdevice when the bus reaches op.
+    Identity(SubdeviceIdentity),
+    // sent whenever the subdevice's al status changes.
+    SubdeviceStatus(SubdeviceStatus),
+    // the working counter health of the whole bus, sent periodically with index 0.
+    Bus(BusStatus),
+    // how far the subdevice's copy of the system time is from the reference clock, in ns.
+    SystemTimeDifference(i64),
+    // an access to the register went unanswered.
+    RegisterTimedOut(u16),
+    // sent whenever a homing run makes progress.
+    Homing(HomingProgress),
+    // one of the profile parameters was written.
+    ProfileWritten(SdoAddr, Result<(), SdoAbort>),
+    // sent when the process image stops or starts matching the subdevice's pdo layout.
+    ImageMismatch(Option<ImageMismatch>),
+    // the capture file could not be written, or not as fast as frames came in.
+    CaptureFailed(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct SubdeviceIdentity {
+    pub name: String,
+    pub configured_address: u16,
+    pub vendor_id: u32,
+    pub product_code: u32,
+    pub revision: u32,
 }
 
 pub struct MotorUiConfig {
     pub(crate) idx_storage: String,
     pub(crate) idx: Option<usize>,
     pub(crate) added: bool,
-    // TODO: other config (max accel/decel, etc)
+    // add was clicked, the motor is added once the drive has its profile parameters
+    pub(crate) adding: bool,
+    // how `ControlState::Position` is commanded, the other states always use the cyclic modes.
+    pub(crate) position_mode: PositionMode,
+    // the extra objects in the device's pdo layout, as last reported
+    pub(crate) process_data: Vec<(String, i64)>,
+    pub(crate) power_state: Option<PowerState>,
+    // the drive's last fault, cleared once it is enabled again
+    pub(crate) fault: Option<Ds402Fault>,
+    // the drive was still faulted after the last reset
+    pub(crate) reset_failed: bool,
+    // the state the drive got stuck in while it was being enabled
+    pub(crate) enable_timed_out: Option<PowerState>,
+    pub(crate) sdo: SdoBrowser,
+    pub(crate) homing: HomingPanel,
+    // what the subdevice at `idx` reported about itself, and its description from the loaded
+    // esi files
+    pub(crate) identity: Option<SubdeviceIdentity>,
+    pub(crate) esi: Option<std::sync::Arc<EsiDevice>>,
+    pub(crate) profile: ProfilePanel,
+    pub(crate) units: UnitsPanel,
 }
 
 impl Default for MotorUiConfig {
@@ -612,11 +1362,53 @@ impl Default for MotorUiConfig {
             idx_storage: String::new(),
             idx: None,
             added: false,
+            adding: false,
+            position_mode: PositionMode::default(),
+            process_data: Vec::new(),
+            power_state: None,
+            fault: None,
+            reset_failed: false,
+            enable_timed_out: None,
+            sdo: SdoBrowser::default(),
+            homing: HomingPanel::default(),
+            profile: ProfilePanel::default(),
+            units: UnitsPanel::default(),
+            identity: None,
+            esi: None,
         }
     }
 }
 
 impl MotorUiConfig {
+    // what the drive reported about itself, whether or not the motor is added.
+    pub(crate) fn update_drive(&mut self, response: &Ds402Response) {
+        match response {
+            Ds402Response::PowerState(power_state) => {
+                self.power_state = Some(*power_state);
+                if *power_state == PowerState::OperationEnabled {
+                    self.fault = None;
+                    self.reset_failed = false;
+                }
+                // a fault or a drive that starts up again may have lost the position
+                if power_state.is_fault() || *power_state == PowerState::NotReadyToSwitchOn {
+                    self.homing.lose_home();
+                }
+            }
+            // the bus started again, or the subdevice dropped out of op and may have lost power
+            Ds402Response::Identity(_) => self.homing.lose_home(),
+            Ds402Response::SubdeviceStatus(status) if status.al_state != topology::AlState::Op => {
+                self.homing.lose_home();
+            }
+            Ds402Response::Fault(fault) => self.fault = Some(fault.clone()),
+            Ds402Response::FaultResetFailed => self.reset_failed = true,
+            Ds402Response::EnableTimedOut(power_state) => {
+                self.enable_timed_out = Some(*power_state);
+                self.added = false;
+            }
+            _ => (),
+        }
+    }
+
     pub(crate) fn display(&mut self, ui: &mut egui::Ui) -> bool {
         let mut changed = false;
 
@@ -645,6 +1437,74 @@ impl MotorUiConfig {
             }
         });
 
+        ui.horizontal(|ui| {
+            ui.label("position mode");
+            ui.add_enabled_ui(!self.added, |ui| {
+                ui.radio_value(&mut self.position_mode, PositionMode::Cyclic, "cyclic (csp)");
+                ui.radio_value(&mut self.position_mode, PositionMode::Profile, "profile (pp)");
+            });
+        });
+
+        if let Some(power_state) = &self.power_state {
+            let color = match power_state {
+                PowerState::Fault | PowerState::FaultReactionActive => egui::Color32::RED,
+                _ => ui.visuals().text_color(),
+            };
+            ui.colored_label(color, format!("drive state: {power_state}"));
+        }
+
+        if let Some(power_state) = &self.enable_timed_out {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("the drive did not leave {power_state} while enabling, add it to retry"),
+            );
+        }
+
+        if let Some(fault) = &self.fault {
+            ui.colored_label(egui::Color32::RED, format!("fault: {fault}"));
+            for (addr, abort) in &fault.aborted {
+                ui.label(format!("could not read {addr}: {abort}"));
+            }
+        }
+
+        if self.reset_failed {
+            ui.colored_label(
+                egui::Color32::RED,
+                "the drive is still faulted after the reset, clear the cause and reset again",
+            );
+        }
+
+        for (name, value) in &self.process_data {
+            ui.label(format!("{name}: {value}"));
+        }
+
+        if let Some(identity) = &self.identity {
+            ui.label(format!(
+                "{}: vendor {:#010x}, product {:#010x}, revision {:#010x}",
+                identity.name, identity.vendor_id, identity.product_code, identity.revision
+            ));
+        }
+
+        if let (Some(identity), Some(esi)) = (&self.identity, &self.esi) {
+            ui.horizontal(|ui| {
+                ui.label(format!("esi: {}", esi.name));
+                match esi.suggested_layout() {
+                    Ok(layout) => {
+                        // layouts are looked up by the name the subdevice reports
+                        if ui.button("copy default pdo layout").clicked() {
+                            let layout = serde_json::json!({ identity.name.clone(): layout });
+                            let layout = serde_json::to_string_pretty(&layout)
+                                .expect("serialization error");
+                            ui.ctx().copy_text(layout);
+                        }
+                    }
+                    Err(e) => {
+                        ui.label(format!("no default pdo layout: {e}"));
+                    }
+                }
+            });
+        }
+
         changed
     }
 
@@ -653,10 +1513,121 @@ impl MotorUiConfig {
     }
 }
 
-struct Ds402Backend;
+struct Ds402Backend {
+    opmode: OpMode,
+    units: Ds402Units,
+    // profile position set-point handshake: the last position handed to the drive, and
+    // whether the new set-point bit is raised until the drive acknowledges it.
+    setpoint: Option<i32>,
+    new_setpoint: bool,
+    // the mapped objects the backend does not use itself, as last reported to the ui
+    process_data: Vec<(String, i64)>,
+}
+
+impl Ds402Backend {
+    fn new(opmode: OpMode, units: Ds402Units) -> Self {
+        Self {
+            opmode,
+            units,
+            setpoint: None,
+            new_setpoint: false,
+            process_data: Vec::new(),
+        }
+    }
+}
 
 impl Backend<Ds402Backend> {
+    // sends the extra mapped objects to the ui whenever one of them changes.
+    fn report_process_data(
+        &mut self,
+        layout: &PdoLayout,
+        image: &[u8],
+        err_tx: &mpsc::Sender<(usize, Ds402Response)>,
+        idx: usize,
+    ) {
+        let reported = &mut self.backend_specific.process_data;
+        let unchanged = layout
+            .extra_inputs(image)
+            .eq(reported.iter().map(|(name, value)| (name.as_str(), *value)));
+        if unchanged {
+            return;
+        }
+
+        *reported = layout
+            .extra_inputs(image)
+            .map(|(name, value)| (name.to_owned(), value))
+            .collect();
+        let _ = err_tx.send((idx, Ds402Response::ProcessData(reported.clone())));
+    }
+
+    // the pdo to send while running, given what the drive reported this cycle.
+    fn output(&mut self, recv: &RecvObj) -> WriteObj {
+        use cia402::ENABLE_OPERATION;
+
+        let opmode = self.backend_specific.opmode;
+        if recv.opmode != opmode as i8 {
+            // hold still until 0x6061 confirms the switch, the targets mean something else in
+            // the previous mode
+            return WriteObj::hold(ENABLE_OPERATION, opmode, recv);
+        }
+
+        let hold = WriteObj::hold(ENABLE_OPERATION, opmode, recv);
+        if self.input_cvp.is_none() {
+            // without a waveform a zero position setpoint would send the motor to zero
+            return hold;
+        }
+        let input = self.limited_input();
+        let units = self.backend_specific.units;
+
+        match opmode {
+            OpMode::CyclicPosition => WriteObj {
+                target_position: units.position(input.position),
+                ..hold
+            },
+            OpMode::CyclicVelocity => WriteObj {
+                target_velocity: units.velocity(input.velocity),
+                ..hold
+            },
+            OpMode::CyclicTorque => WriteObj {
+                target_torque: units.torque(input.current),
+                ..hold
+            },
+            OpMode::ProfilePosition => {
+                let target = units.position(input.position);
+                let acknowledged = recv.status & RecvObj::SETPOINT_ACK_MASK != 0;
+                let pp = &mut self.backend_specific;
+
+                // raise the new set-point bit until the drive acknowledges it, and only hand
+                // over the next set-point once the drive has dropped its acknowledgement.
+                if pp.new_setpoint {
+                    pp.new_setpoint = !acknowledged;
+                } else if !acknowledged && pp.setpoint != Some(target) {
+                    pp.setpoint = Some(target);
+                    pp.new_setpoint = true;
+                }
+
+                let mut control = ENABLE_OPERATION | WriteObj::CHANGE_IMMEDIATELY;
+                if pp.new_setpoint {
+                    control |= WriteObj::NEW_SETPOINT;
+                }
+
+                WriteObj {
+                    control,
+                    target_position: pp.setpoint.unwrap_or(recv.position),
+                    ..hold
+                }
+            }
+            // homing runs without a backend
+            OpMode::Homing => hold,
+        }
+    }
+
     fn update_input(&mut self, end_tx: &mpsc::Sender<(usize, Ds402Response)>, idx: usize) {
+        if self.waiting_to_start() {
+            // keep holding until a synchronized start
+            return;
+        }
+
         let next_input = match &self.request_input {
             None => None,
             Some((_, RequestedMotorInput::Cvp(c))) => Some(*c),
@@ -668,12 +1639,9 @@ impl Backend<Ds402Backend> {
                     let _ = end_tx.send((idx, Ds402Response::EndWaveform));
                     None
                 } else if elapsed > s.delay {
-                    Some(crate::motor_ctx::CVP {
-                        velocity: s.magnitude,
-                        ..Default::default()
-                    })
+                    Some(self.setpoint(Some(s.magnitude)))
                 } else {
-                    Some(Default::default())
+                    Some(self.setpoint(None))
                 }
             }
             Some((then, RequestedMotorInput::Impulse(i))) => {
@@ -686,12 +1654,9 @@ impl Backend<Ds402Backend> {
                     let _ = end_tx.send((idx, Ds402Response::EndWaveform));
                     None
                 } else if elapsed < i.delay + EPSILON && elapsed > i.delay - EPSILON {
-                    Some(crate::motor_ctx::CVP {
-                        velocity: i.magnitude,
-                        ..Default::default()
-                    })
+                    Some(self.setpoint(Some(i.magnitude)))
                 } else {
-                    Some(Default::default())
+                    Some(self.setpoint(None))
                 }
             }
             Some((then, RequestedMotorInput::Custom(c))) => {
@@ -714,6 +1679,19 @@ impl Backend<Ds402Backend> {
                 }
                 cvp
             }
+            Some((then, RequestedMotorInput::Trajectory(profile))) => {
+                let now = time::Instant::now();
+                let elapsed = now.duration_since(*then);
+                let cvp = profile.sample(elapsed);
+
+                if elapsed > profile.duration() {
+                    let _ = end_tx.send((idx, Ds402Response::EndWaveform));
+                    // hold the target rather than falling back to a zero setpoint
+                    self.request_input = Some((now, RequestedMotorInput::Cvp(cvp)));
+                }
+                Some(cvp)
+            }
+            Some((_, RequestedMotorInput::Follow(c))) => Some(*c),
         };
 
         self.input_cvp = next_input;
@@ -725,13 +1703,184 @@ use ecat::user::ControlFlow;
 struct User {
     device: ethercrab::SubDevice,
     state: UserState,
+    layout: PdoLayout,
+    sdo: SdoQueue,
+    // the fault being read from the drive
+    fault: Ds402Fault,
+    registers: RegisterQueue,
+    // when the al status was last read
+    status_read: Option<time::Instant>,
+    status: Option<SubdeviceStatus>,
+    // the shift of the subdevice's sync0 pulse, when it is enabled
+    sync0: Option<time::Duration>,
+    // the cycle time sync0 is being set up for
+    cycle: Option<time::Duration>,
+    // the process image is shorter than the layout, as last reported
+    image_mismatch: Option<ImageMismatch>,
 }
 
 impl User {
-    fn new(device: ethercrab::SubDevice) -> Self {
+    fn new(device: ethercrab::SubDevice, layout: PdoLayout) -> Self {
         Self {
             device,
             state: Default::default(),
+            layout,
+            sdo: SdoQueue::default(),
+            fault: Ds402Fault::default(),
+            registers: RegisterQueue::default(),
+            status_read: None,
+            status: None,
+            sync0: None,
+            cycle: None,
+            image_mismatch: None,
+        }
+    }
+
+    // turns sync0 off, and back on with the new cycle time and shift when both are set. the
+    // start time is only known once the subdevice's system time has been read.
+    fn configure_sync0(&mut self, shift: Option<time::Duration>, cycle: Option<time::Duration>) {
+        self.sync0 = shift;
+        self.cycle = cycle;
+
+        self.registers.write(registers::DC_SYNC_ACTIVATION, vec![0]);
+        if shift.is_some() && cycle.is_some() {
+            self.registers.read(
+                registers::DC_SYSTEM_TIME,
+                8,
+                RegisterPurpose::Sync0SystemTime,
+            );
+        }
+    }
+
+    fn registers_complete(
+        &mut self,
+        data: &[u8],
+        err_tx: &mpsc::Sender<(usize, Ds402Response)>,
+        idx: usize,
+    ) {
+        let Some((request, data)) = self.registers.complete(data) else {
+            return;
+        };
+
+        let mut bytes = [0; 8];
+        let len = data.len().min(bytes.len());
+        bytes[..len].copy_from_slice(&data[..len]);
+
+        match request.purpose {
+            RegisterPurpose::AlStatus => {
+                let changed =
+                    SubdeviceStatus::decode(&data).filter(|status| self.status != Some(*status));
+                if let Some(status) = changed {
+                    self.status = Some(status);
+                    let _ = err_tx.send((idx, Ds402Response::SubdeviceStatus(status)));
+                }
+            }
+            RegisterPurpose::SystemTimeDifference => {
+                let raw = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                let difference = dc::system_time_difference(raw);
+                let _ = err_tx.send((idx, Ds402Response::SystemTimeDifference(difference)));
+            }
+            RegisterPurpose::Sync0SystemTime => {
+                let (Some(shift), Some(cycle)) = (self.sync0, self.cycle) else {
+                    return;
+                };
+                let system_time = u64::from_le_bytes(bytes);
+                let start = dc::sync0_start_time(system_time, cycle, shift);
+
+                // the ui rejects longer cycles
+                let Ok(cycle_ns) = u32::try_from(cycle.as_nanos()) else {
+                    return;
+                };
+                self.registers
+                    .write(registers::DC_SYNC0_CYCLE_TIME, cycle_ns.to_le_bytes().into());
+                self.registers
+                    .write(registers::DC_SYNC0_START_TIME, start.to_le_bytes().into());
+                self.registers
+                    .write(registers::DC_SYNC_ACTIVATION, vec![dc::SYNC0_ACTIVE]);
+            }
+            RegisterPurpose::Configure => (),
+        }
+    }
+
+    fn sdo_complete(
+        &mut self,
+        result: Result<&[u8], u32>,
+        err_tx: &mpsc::Sender<(usize, Ds402Response)>,
+        idx: usize,
+    ) {
+        let Some((request, result)) = self.sdo.complete(result) else {
+            return;
+        };
+        self.sdo_result(request, result, err_tx, idx);
+    }
+
+    // a frame sent to the subdevice went unanswered, whatever it carried failed.
+    fn timed_out(
+        &mut self,
+        kind: topology::FrameKind,
+        err_tx: &mpsc::Sender<(usize, Ds402Response)>,
+        idx: usize,
+    ) {
+        match kind {
+            topology::FrameKind::Mailbox => {
+                if let Some((request, result)) = self.sdo.time_out() {
+                    self.sdo_result(request, result, err_tx, idx);
+                }
+            }
+            topology::FrameKind::Register => {
+                if let Some(request) = self.registers.time_out() {
+                    let response = Ds402Response::RegisterTimedOut(request.register);
+                    let _ = err_tx.send((idx, response));
+                }
+            }
+            // the process data goes out again next cycle, the working counter shows it missing
+            topology::FrameKind::Pdi => (),
+        }
+    }
+
+    fn sdo_result(
+        &mut self,
+        request: SdoRequest,
+        result: Result<Vec<u8>, SdoAbort>,
+        err_tx: &mpsc::Sender<(usize, Ds402Response)>,
+        idx: usize,
+    ) {
+
+        match request.purpose {
+            SdoPurpose::Fault(field) => {
+                self.fault.set(field, result);
+                if field == FaultField::Manufacturer {
+                    let fault = std::mem::take(&mut self.fault);
+                    let _ = err_tx.send((idx, Ds402Response::Fault(fault)));
+                }
+            }
+            SdoPurpose::Browser => {
+                let response = match request.write {
+                    None => Ds402Response::SdoRead(request.addr, result),
+                    Some(_) => Ds402Response::SdoWritten(request.addr, result.map(|_| ())),
+                };
+                let _ = err_tx.send((idx, response));
+            }
+            SdoPurpose::Profile => {
+                let response = Ds402Response::ProfileWritten(request.addr, result.map(|_| ()));
+                let _ = err_tx.send((idx, response));
+            }
+            SdoPurpose::Homing => {
+                let UserState::Homing(homing) = &mut self.state else {
+                    return;
+                };
+                match result {
+                    Ok(_) => homing.configured(),
+                    Err(abort) => {
+                        let reason = format!("could not write {}: {abort}", request.addr);
+                        let _ = err_tx.send((
+                            idx,
+                            Ds402Response::Homing(HomingProgress::Failed(reason)),
+                        ));
+                        self.state = UserState::Test(PowerState::OperationEnabled, None);
+                    }
+                }
+            }
         }
     }
 
@@ -750,22 +1899,101 @@ impl User {
         output_buf: &mut [u8],
         write_entry: impl Fn(u64) -> u64,
         err_tx: &mpsc::Sender<(usize, Ds402Response)>,
+        halt: Halt,
     ) -> Result<Option<ControlFlow>, Error> {
-        self.state.update(
-            received,
-            maindevice,
-            retry_count,
-            timeout_duration,
-            tx_entries,
-            sock,
-            ring,
-            &mut self.device,
-            idx,
-            identifier,
-            output_buf,
-            write_entry,
-            err_tx,
-        )
+        match received {
+            Some(ecat::DeviceResponse::Sdo(result)) => {
+                self.sdo_complete(result, err_tx, idx as _);
+                return Ok(None);
+            }
+            Some(ecat::DeviceResponse::Register(data)) => {
+                self.registers_complete(data, err_tx, idx as _);
+                return Ok(None);
+            }
+            _ => (),
+        }
+        let pdi = matches!(received, Some(ecat::DeviceResponse::Pdi(_)));
+
+        let mismatch = match &received {
+            Some(ecat::DeviceResponse::Pdi(image)) => self.layout.check(image, output_buf),
+            _ => self.image_mismatch,
+        };
+        if mismatch != self.image_mismatch {
+            self.image_mismatch = mismatch;
+            let _ = err_tx.send((idx as _, Ds402Response::ImageMismatch(mismatch)));
+        }
+
+        let flow = if mismatch.is_some() {
+            // the drive's objects would be misread, leave its outputs zeroed instead
+            output_buf.fill(0);
+            None
+        } else {
+            self.state.update(
+                received,
+                maindevice,
+                retry_count,
+                timeout_duration,
+                tx_entries,
+                sock,
+                ring,
+                &mut self.device,
+                idx,
+                identifier,
+                output_buf,
+                &write_entry,
+                err_tx,
+                halt,
+                &self.layout,
+                &mut self.sdo,
+            )?
+        };
+
+        if pdi {
+            self.sdo.poll(
+                maindevice,
+                retry_count,
+                timeout_duration,
+                tx_entries,
+                sock,
+                ring,
+                &mut self.device,
+                idx,
+                &write_entry,
+            )?;
+
+            // status reads wait for any configuration to finish
+            let due = self
+                .status_read
+                .is_none_or(|read| read.elapsed() > topology::STATUS_INTERVAL);
+            if due && self.registers.is_idle() {
+                self.registers.read(
+                    registers::AL_STATUS,
+                    registers::AL_STATUS_LEN,
+                    RegisterPurpose::AlStatus,
+                );
+                if self.sync0.is_some() {
+                    self.registers.read(
+                        registers::DC_SYSTEM_TIME_DIFFERENCE,
+                        4,
+                        RegisterPurpose::SystemTimeDifference,
+                    );
+                }
+                self.status_read = Some(time::Instant::now());
+            }
+
+            self.registers.poll(
+                maindevice,
+                retry_count,
+                timeout_duration,
+                tx_entries,
+                sock,
+                ring,
+                &mut self.device,
+                idx,
+                &write_entry,
+            )?;
+        }
+        Ok(flow)
     }
 }
 
@@ -0,0 +1,393 @@
+use super::esi::EsiDevice;
+use super::pdo::{PdoType, parse_entry};
+use super::sdo::SdoAddr;
+use super::{Ds402Cmd, Ds402Response};
+
+use std::sync::mpsc;
+
+// an object dictionary entry kept in the browser, with its value as last read.
+#[derive(Clone, Debug)]
+struct SdoParam {
+    name: String,
+    addr: SdoAddr,
+    ty: PdoType,
+    value: Option<i64>,
+    storage: String,
+}
+
+impl SdoParam {
+    fn new(name: String, addr: SdoAddr, ty: PdoType) -> Self {
+        Self {
+            name,
+            addr,
+            ty,
+            value: None,
+            storage: String::new(),
+        }
+    }
+
+    fn set(&mut self, data: &[u8]) {
+        let value = decode(self.ty, data);
+        self.value = Some(value);
+        self.storage = value.to_string();
+    }
+
+    fn edited(&self) -> Option<i64> {
+        parse_value(&self.storage).filter(|value| self.value != Some(*value))
+    }
+
+    // objects the esi file does not describe are written unchecked.
+    fn validate(&self, value: i64, esi: Option<&EsiDevice>) -> Result<(), String> {
+        esi.and_then(|esi| esi.object(self.addr))
+            .map_or(Ok(()), |object| object.validate(value))
+    }
+}
+
+fn decode(ty: PdoType, data: &[u8]) -> i64 {
+    // drives may answer with fewer bytes than the type holds
+    let mut bytes = [0; 4];
+    let len = data.len().min(bytes.len());
+    bytes[..len].copy_from_slice(&data[..len]);
+    ty.decode(&bytes)
+}
+
+fn encode(ty: PdoType, value: i64) -> Vec<u8> {
+    let mut bytes = vec![0; ty.size()];
+    ty.encode(value, &mut bytes);
+    bytes
+}
+
+// decimal, or hexadecimal with a `0x` prefix.
+fn parse_value(s: &str) -> Option<i64> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn format_value(ty: PdoType, value: i64) -> String {
+    let width = ty.size() * 2;
+    let mask = (1u64 << (ty.size() * 8)) - 1;
+    format!("{value} ({:#0w$x})", value as u64 & mask, w = width + 2)
+}
+
+// reads and writes a subdevice's object dictionary. parameter sets are json lists of
+// `{ "name": "max profile velocity", "index": "0x607F", "subindex": 0, "type": "u32", "value": 1000 }`,
+// the same entries as a pdo layout plus their value, a number or a string like "0x3E8".
+pub struct SdoBrowser {
+    index_storage: String,
+    subindex_storage: String,
+    ty: PdoType,
+    // the entry being edited, filled in when it is read
+    entry: Option<SdoParam>,
+    favorites: Vec<SdoParam>,
+    path_storage: String,
+    status: Option<String>,
+}
+
+impl Default for SdoBrowser {
+    fn default() -> Self {
+        Self {
+            index_storage: String::new(),
+            subindex_storage: "0".into(),
+            ty: PdoType::U16,
+            entry: None,
+            favorites: Vec::new(),
+            path_storage: String::new(),
+            status: None,
+        }
+    }
+}
+
+impl SdoBrowser {
+    pub(crate) fn update(&mut self, response: &Ds402Response) {
+        match response {
+            Ds402Response::SdoRead(addr, Ok(data)) => {
+                let params = self.entry.iter_mut().chain(self.favorites.iter_mut());
+                for param in params.filter(|param| param.addr == *addr) {
+                    param.set(data);
+                }
+            }
+            Ds402Response::SdoWritten(addr, Ok(())) => self.status = Some(format!("wrote {addr}")),
+            Ds402Response::SdoRead(addr, Err(abort)) => {
+                self.status = Some(format!("could not read {addr}: {abort}"))
+            }
+            Ds402Response::SdoWritten(addr, Err(abort)) => {
+                self.status = Some(format!("could not write {addr}: {abort}"))
+            }
+            _ => (),
+        }
+    }
+
+    fn addr(&self) -> Option<SdoAddr> {
+        let hex = self.index_storage.trim();
+        let hex = hex
+            .strip_prefix("0x")
+            .or_else(|| hex.strip_prefix("0X"))
+            .unwrap_or(hex);
+        let index = u16::from_str_radix(hex, 16).ok()?;
+        let subindex = self.subindex_storage.trim().parse().ok()?;
+        Some(SdoAddr::new(index, subindex))
+    }
+
+    // written values are read back, so the browser shows what the drive actually took.
+    fn write(param: &SdoParam, value: i64, idx: usize, tx: &mpsc::Sender<(usize, Ds402Cmd)>) {
+        let data = encode(param.ty, value);
+        let _ = tx.send((idx, Ds402Cmd::WriteSdo(param.addr, data)));
+        let _ = tx.send((idx, Ds402Cmd::ReadSdo(param.addr)));
+    }
+
+    fn load(
+        &mut self,
+        idx: usize,
+        tx: &mpsc::Sender<(usize, Ds402Cmd)>,
+        esi: Option<&EsiDevice>,
+    ) -> Result<String, String> {
+        let path = self.path_storage.trim();
+        let file = std::fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+        let json = serde_json::from_slice::<serde_json::Value>(&file)
+            .map_err(|e| format!("{path}: {e}"))?;
+        let entries = json
+            .as_array()
+            .ok_or_else(|| format!("{path}: expected a list of parameters"))?;
+
+        let mut params = Vec::new();
+        for entry in entries {
+            let parsed = parse_entry(entry)?;
+            let addr = SdoAddr::new(parsed.index, parsed.subindex);
+            let mut param = SdoParam::new(parsed.name, addr, parsed.ty);
+            // nothing is written when a single value of the set is invalid
+            if let Some(value) = entry.get("value") {
+                let number = match value {
+                    serde_json::Value::String(s) => parse_value(s),
+                    value => value.as_i64(),
+                };
+                let value = number
+                    .ok_or_else(|| format!("{path}: invalid value {value} for {}", param.addr))?;
+                param.validate(value, esi)?;
+                param.storage = value.to_string();
+            }
+            params.push(param);
+        }
+
+        // download every value in the set, the rest are only read
+        let mut written = 0;
+        for param in &params {
+            match parse_value(&param.storage) {
+                Some(value) => {
+                    Self::write(param, value, idx, tx);
+                    written += 1;
+                }
+                None => {
+                    let _ = tx.send((idx, Ds402Cmd::ReadSdo(param.addr)));
+                }
+            }
+        }
+
+        self.favorites = params;
+        Ok(format!("downloading {written} parameters from {path}"))
+    }
+
+    fn save(&self) -> Result<String, String> {
+        let path = self.path_storage.trim();
+        let params = self
+            .favorites
+            .iter()
+            .map(|param| {
+                let mut entry = serde_json::json!({
+                    "name": param.name,
+                    "index": format!("{:#06x}", param.addr.index),
+                    "subindex": param.addr.subindex,
+                    "type": param.ty.name(),
+                });
+                if let Some(value) = param.value {
+                    entry["value"] = value.into();
+                }
+                entry
+            })
+            .collect::<Vec<_>>();
+
+        let json = serde_json::to_vec_pretty(&params).expect("serialization error");
+        std::fs::write(path, json).map_err(|e| format!("{path}: {e}"))?;
+        Ok(format!("saved {} parameters to {path}", params.len()))
+    }
+
+    pub(crate) fn display(
+        &mut self,
+        idx: usize,
+        tx: &mpsc::Sender<(usize, Ds402Cmd)>,
+        esi: Option<&EsiDevice>,
+        ui: &mut egui::Ui,
+    ) {
+        ui.collapsing("object dictionary", |ui| {
+            if let Some(esi) = esi {
+                ui.collapsing(format!("objects of {}", esi.name), |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(200.)
+                        .show(ui, |ui| {
+                            for object in &esi.objects {
+                                let text = format!("{} {}", object.addr, object.name);
+                                if ui.selectable_label(false, text).clicked() {
+                                    self.index_storage = format!("{:#06x}", object.addr.index);
+                                    self.subindex_storage = object.addr.subindex.to_string();
+                                    self.ty = object.ty.unwrap_or(self.ty);
+                                }
+                            }
+                        });
+                });
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("index");
+                ui.text_edit_singleline(&mut self.index_storage);
+                ui.label("subindex");
+                ui.text_edit_singleline(&mut self.subindex_storage);
+
+                let object = self.addr().and_then(|addr| esi?.object(addr));
+                if let Some(object) = object {
+                    ui.label(&object.name);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("type");
+                for ty in PdoType::ALL {
+                    ui.radio_value(&mut self.ty, ty, ty.name());
+                }
+
+                let addr = self.addr();
+                if ui
+                    .add_enabled(addr.is_some(), egui::Button::new("read"))
+                    .clicked()
+                {
+                    if let Some(addr) = addr {
+                        let name = esi
+                            .and_then(|esi| esi.object(addr))
+                            .map_or_else(|| addr.to_string(), |object| object.name.clone());
+                        self.entry = Some(SdoParam::new(name, addr, self.ty));
+                        let _ = tx.send((idx, Ds402Cmd::ReadSdo(addr)));
+                    }
+                }
+                if addr.is_none() && !self.index_storage.is_empty() {
+                    ui.label("invalid index");
+                }
+            });
+
+            let mut favorite = None;
+            if let Some(entry) = &mut self.entry {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} ({})", entry.name, entry.addr));
+                    if let Some(value) = entry.value {
+                        ui.label(format_value(entry.ty, value));
+                    }
+                    ui.text_edit_singleline(&mut entry.storage);
+
+                    let edited = entry.edited();
+                    let valid = edited.map(|value| entry.validate(value, esi));
+                    if ui
+                        .add_enabled(matches!(valid, Some(Ok(()))), egui::Button::new("write"))
+                        .clicked()
+                    {
+                        if let Some(value) = edited {
+                            Self::write(entry, value, idx, tx);
+                        }
+                    }
+                    if let Some(Err(e)) = valid {
+                        ui.label(e);
+                    }
+                    if ui.button("add to favorites").clicked() {
+                        favorite = Some(entry.clone());
+                    }
+                });
+            }
+            if let Some(favorite) = favorite {
+                self.favorites.retain(|param| param.addr != favorite.addr);
+                self.favorites.push(favorite);
+            }
+
+            if !self.favorites.is_empty() {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("favorites");
+                    // upload, in CANopen terms: from the drive to the tool
+                    if ui.button("read all").clicked() {
+                        for param in &self.favorites {
+                            let _ = tx.send((idx, Ds402Cmd::ReadSdo(param.addr)));
+                        }
+                    }
+                    let edited = self.favorites.iter().filter_map(|p| Some((p, p.edited()?)));
+                    if ui.button("write edited").clicked() {
+                        let mut rejected = Vec::new();
+                        for (param, value) in edited {
+                            match param.validate(value, esi) {
+                                Ok(()) => Self::write(param, value, idx, tx),
+                                Err(e) => rejected.push(e),
+                            }
+                        }
+                        if !rejected.is_empty() {
+                            self.status = Some(format!("not written: {}", rejected.join(", ")));
+                        }
+                    }
+                });
+
+                let mut removed = None;
+                for (i, param) in self.favorites.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} ({}, {})",
+                            param.name,
+                            param.addr,
+                            param.ty.name()
+                        ));
+                        if let Some(value) = param.value {
+                            ui.label(format_value(param.ty, value));
+                        }
+                        ui.text_edit_singleline(&mut param.storage);
+                        match parse_value(&param.storage) {
+                            Some(value) => {
+                                if let Err(e) = param.validate(value, esi) {
+                                    ui.label(e);
+                                }
+                            }
+                            None if !param.storage.is_empty() => {
+                                ui.label("invalid value");
+                            }
+                            None => (),
+                        }
+                        if ui.button("remove").clicked() {
+                            removed = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = removed {
+                    self.favorites.remove(i);
+                }
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("parameter set");
+                ui.text_edit_singleline(&mut self.path_storage);
+                let has_path = !self.path_storage.trim().is_empty();
+
+                if ui
+                    .add_enabled(has_path, egui::Button::new("load and download"))
+                    .clicked()
+                {
+                    self.status = Some(self.load(idx, tx, esi).unwrap_or_else(|e| e));
+                }
+                if ui
+                    .add_enabled(has_path, egui::Button::new("save favorites"))
+                    .clicked()
+                {
+                    self.status = Some(self.save().unwrap_or_else(|e| e));
+                }
+            });
+
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+    }
+}
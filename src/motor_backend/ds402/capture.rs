@@ -0,0 +1,138 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// classic pcap with microsecond timestamps, version 2.4.
+const PCAP_MAGIC: u32 = 0xA1B2_C3D4;
+const PCAP_VERSION: (u16, u16) = (2, 4);
+const LINKTYPE_ETHERNET: u32 = 1;
+const SNAPLEN: u32 = 65535;
+// frames waiting for the writer, beyond that they are dropped rather than holding up the bus.
+const QUEUE_LEN: usize = 4096;
+
+enum Record {
+    // a frame and when it was sent or received, since the unix epoch
+    Frame(Duration, Vec<u8>),
+    Flush,
+}
+
+// writes every frame sent and received to a pcap file, which wireshark decodes with its
+// ethercat dissector. the file is written on its own thread, away from the event loop.
+pub(crate) struct Capture {
+    // `None` once the writer stopped
+    tx: Option<mpsc::SyncSender<Record>>,
+    writer: Option<thread::JoinHandle<()>>,
+    // the error the writer stopped at
+    failed: mpsc::Receiver<std::io::Error>,
+    // frames the writer could not keep up with, and how many of them were reported
+    dropped: u64,
+    reported_dropped: u64,
+}
+
+impl Capture {
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        file.write_all(&PCAP_MAGIC.to_le_bytes())?;
+        file.write_all(&PCAP_VERSION.0.to_le_bytes())?;
+        file.write_all(&PCAP_VERSION.1.to_le_bytes())?;
+        // timezone offset and timestamp accuracy, always zero
+        file.write_all(&0i32.to_le_bytes())?;
+        file.write_all(&0u32.to_le_bytes())?;
+        file.write_all(&SNAPLEN.to_le_bytes())?;
+        file.write_all(&LINKTYPE_ETHERNET.to_le_bytes())?;
+
+        let (tx, rx) = mpsc::sync_channel(QUEUE_LEN);
+        let (failed_tx, failed) = mpsc::channel();
+        let writer = thread::Builder::new()
+            .name("ethercat capture".into())
+            .spawn(move || write(file, rx, failed_tx))?;
+
+        Ok(Self {
+            tx: Some(tx),
+            writer: Some(writer),
+            failed,
+            dropped: 0,
+            reported_dropped: 0,
+        })
+    }
+
+    pub fn record(&mut self, frame: &[u8]) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        if tx.try_send(Record::Frame(now, frame.to_vec())).is_err() {
+            self.dropped += 1;
+        }
+    }
+
+    // called periodically, so a capture is readable while the bus is running.
+    pub fn flush(&mut self) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.try_send(Record::Flush);
+        }
+    }
+
+    // what went wrong with the capture since the last call, for the ui.
+    pub fn take_error(&mut self) -> Option<String> {
+        if let Ok(e) = self.failed.try_recv() {
+            self.tx = None;
+            return Some(format!("stopped capturing ethercat frames: {e}"));
+        }
+        if self.dropped > self.reported_dropped {
+            self.reported_dropped = self.dropped;
+            return Some(format!(
+                "{} ethercat frames were not captured, writing the file could not keep up",
+                self.dropped
+            ));
+        }
+        None
+    }
+}
+
+impl Drop for Capture {
+    // the frames still queued end up in the file before the event loop exits.
+    fn drop(&mut self) {
+        self.tx = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write(
+    mut file: BufWriter<File>,
+    rx: mpsc::Receiver<Record>,
+    failed: mpsc::Sender<std::io::Error>,
+) {
+    for record in rx {
+        let result = match record {
+            Record::Frame(at, frame) => write_record(&mut file, at, &frame),
+            Record::Flush => file.flush(),
+        };
+        if let Err(e) = result {
+            let _ = failed.send(e);
+            return;
+        }
+    }
+    if let Err(e) = file.flush() {
+        let _ = failed.send(e);
+    }
+}
+
+fn write_record(file: &mut impl Write, at: Duration, frame: &[u8]) -> std::io::Result<()> {
+    let len = frame.len() as u32;
+    let captured = len.min(SNAPLEN);
+
+    file.write_all(&(at.as_secs() as u32).to_le_bytes())?;
+    file.write_all(&at.subsec_micros().to_le_bytes())?;
+    file.write_all(&captured.to_le_bytes())?;
+    file.write_all(&len.to_le_bytes())?;
+    file.write_all(&frame[..captured as usize])
+}
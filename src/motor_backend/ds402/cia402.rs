@@ -0,0 +1,277 @@
+use std::time::{Duration, Instant};
+
+// controlword (0x6040) commands.
+pub(crate) const DISABLE_VOLTAGE: u16 = 0x0000;
+pub(crate) const QUICK_STOP: u16 = 0x0002;
+pub(crate) const SHUTDOWN: u16 = 0x0006;
+pub(crate) const SWITCH_ON: u16 = 0x0007;
+pub(crate) const ENABLE_OPERATION: u16 = 0x000F;
+// acts on the rising edge only.
+pub(crate) const FAULT_RESET: u16 = 0x0080;
+
+// how long the fault reset bit is held low, then high, so that the drive always sees an edge.
+const FAULT_RESET_PULSE: Duration = Duration::from_millis(100);
+// a few reset pulses, in case the fault was still present for the first ones
+const FAULT_RESET_TIMEOUT: Duration = Duration::from_secs(2);
+
+// the power states of the CiA402 drive state machine, decoded from the statusword (0x6041).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PowerState {
+    NotReadyToSwitchOn,
+    SwitchOnDisabled,
+    ReadyToSwitchOn,
+    SwitchedOn,
+    OperationEnabled,
+    QuickStopActive,
+    FaultReactionActive,
+    Fault,
+    // a statusword that matches none of the states
+    Unknown(u16),
+}
+
+impl PowerState {
+    pub fn from_statusword(status: u16) -> Self {
+        match (status & 0x4F, status & 0x6F) {
+            (0x00, _) => Self::NotReadyToSwitchOn,
+            (0x40, _) => Self::SwitchOnDisabled,
+            (0x0F, _) => Self::FaultReactionActive,
+            (0x08, _) => Self::Fault,
+            (_, 0x21) => Self::ReadyToSwitchOn,
+            (_, 0x23) => Self::SwitchedOn,
+            (_, 0x27) => Self::OperationEnabled,
+            (_, 0x07) => Self::QuickStopActive,
+            _ => Self::Unknown(status),
+        }
+    }
+
+    // how long the drive may stay in this state before enabling is given up on.
+    fn timeout(&self) -> Option<Duration> {
+        match self {
+            // the drive runs its self test, or brings the motor to a stop
+            Self::NotReadyToSwitchOn | Self::FaultReactionActive => Some(Duration::from_secs(5)),
+            // a fault stays until the operator resets it
+            Self::Fault => None,
+            _ => Some(Duration::from_secs(1)),
+        }
+    }
+
+    pub fn is_fault(&self) -> bool {
+        matches!(self, Self::Fault | Self::FaultReactionActive)
+    }
+}
+
+impl core::fmt::Display for PowerState {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotReadyToSwitchOn => write!(f, "not ready to switch on"),
+            Self::SwitchOnDisabled => write!(f, "switch on disabled"),
+            Self::ReadyToSwitchOn => write!(f, "ready to switch on"),
+            Self::SwitchedOn => write!(f, "switched on"),
+            Self::OperationEnabled => write!(f, "operation enabled"),
+            Self::QuickStopActive => write!(f, "quick stop active"),
+            Self::FaultReactionActive => write!(f, "fault reaction active"),
+            Self::Fault => write!(f, "fault"),
+            Self::Unknown(status) => write!(f, "unknown (statusword {status:#06x})"),
+        }
+    }
+}
+
+pub(crate) enum Progress {
+    // send the controlword and keep going
+    Pending(u16),
+    Enabled,
+    // the drive did not leave this state in time
+    TimedOut(PowerState),
+    // the drive was still faulted after the reset pulses, it waits for another reset
+    ResetFailed,
+}
+
+// takes a drive to operation enabled, one transition at a time as soon as each state is reached.
+pub(crate) struct Enabling {
+    state: Option<PowerState>,
+    since: Instant,
+    // when the operator asked for the fault to be reset
+    reset_requested: Option<Instant>,
+}
+
+impl Enabling {
+    pub fn new() -> Self {
+        Self {
+            state: None,
+            since: Instant::now(),
+            reset_requested: None,
+        }
+    }
+
+    // pulses the fault reset bit, the drive is only reset on request so that the operator gets
+    // to see the fault first.
+    pub fn reset_fault(&mut self) {
+        self.reset_requested = Some(Instant::now());
+    }
+
+    // what to do next, along with the new state on the cycle the drive reached it.
+    pub fn update(&mut self, status: u16) -> (Progress, Option<PowerState>) {
+        let state = PowerState::from_statusword(status);
+        let changed = (self.state != Some(state)).then_some(state);
+        if changed.is_some() {
+            self.state = Some(state);
+            self.since = Instant::now();
+        }
+
+        let elapsed = self.since.elapsed();
+        if state.timeout().is_some_and(|timeout| elapsed > timeout) {
+            return (Progress::TimedOut(state), changed);
+        }
+
+        if state != PowerState::Fault {
+            self.reset_requested = None;
+        }
+
+        let controlword = match state {
+            PowerState::OperationEnabled => return (Progress::Enabled, changed),
+            PowerState::SwitchOnDisabled => SHUTDOWN,
+            PowerState::ReadyToSwitchOn => SWITCH_ON,
+            PowerState::SwitchedOn => ENABLE_OPERATION,
+            PowerState::Fault => match self.reset_requested.map(|r| r.elapsed()) {
+                Some(requested) if requested > FAULT_RESET_TIMEOUT => {
+                    self.reset_requested = None;
+                    return (Progress::ResetFailed, changed);
+                }
+                Some(requested)
+                    if (requested.as_millis() / FAULT_RESET_PULSE.as_millis()) % 2 == 1 =>
+                {
+                    FAULT_RESET
+                }
+                _ => DISABLE_VOLTAGE,
+            },
+            // the drive leaves these on its own, or goes to switch on disabled once the voltage is off
+            PowerState::NotReadyToSwitchOn
+            | PowerState::FaultReactionActive
+            | PowerState::QuickStopActive
+            | PowerState::Unknown(_) => DISABLE_VOLTAGE,
+        };
+        (Progress::Pending(controlword), changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_every_power_state() {
+        for (status, state) in [
+            (0x0000, PowerState::NotReadyToSwitchOn),
+            (0x0040, PowerState::SwitchOnDisabled),
+            (0x0021, PowerState::ReadyToSwitchOn),
+            (0x0023, PowerState::SwitchedOn),
+            (0x0027, PowerState::OperationEnabled),
+            (0x0007, PowerState::QuickStopActive),
+            (0x000F, PowerState::FaultReactionActive),
+            (0x0008, PowerState::Fault),
+        ] {
+            assert_eq!(PowerState::from_statusword(status), state, "{status:#06x}");
+        }
+    }
+
+    #[test]
+    fn ignores_bits_outside_the_state() {
+        // voltage enabled, remote, target reached and manufacturer specific bits
+        let extra = 0x0010 | 0x0200 | 0x0400 | 0x8000;
+        assert_eq!(
+            PowerState::from_statusword(0x0027 | extra),
+            PowerState::OperationEnabled
+        );
+        assert_eq!(
+            PowerState::from_statusword(0x0040 | extra),
+            PowerState::SwitchOnDisabled
+        );
+    }
+
+    #[test]
+    fn reports_unknown_statuswords() {
+        assert_eq!(
+            PowerState::from_statusword(0x0001),
+            PowerState::Unknown(0x0001)
+        );
+    }
+
+    fn pending(progress: Progress) -> u16 {
+        match progress {
+            Progress::Pending(controlword) => controlword,
+            _ => panic!("enabling is no longer pending"),
+        }
+    }
+
+    #[test]
+    fn walks_the_state_machine() {
+        let mut enabling = Enabling::new();
+
+        let (progress, changed) = enabling.update(0x0040);
+        assert_eq!(pending(progress), SHUTDOWN);
+        assert_eq!(changed, Some(PowerState::SwitchOnDisabled));
+
+        // the new state is only reported on the cycle it was reached
+        let (progress, changed) = enabling.update(0x0040);
+        assert_eq!(pending(progress), SHUTDOWN);
+        assert_eq!(changed, None);
+
+        assert_eq!(pending(enabling.update(0x0021).0), SWITCH_ON);
+        assert_eq!(pending(enabling.update(0x0023).0), ENABLE_OPERATION);
+
+        let (progress, changed) = enabling.update(0x0027);
+        assert!(matches!(progress, Progress::Enabled));
+        assert_eq!(changed, Some(PowerState::OperationEnabled));
+    }
+
+    #[test]
+    fn times_out_in_a_state() {
+        let mut enabling = Enabling::new();
+        enabling.update(0x0021);
+        enabling.since = Instant::now() - Duration::from_secs(2);
+
+        let (progress, _) = enabling.update(0x0021);
+        assert!(matches!(
+            progress,
+            Progress::TimedOut(PowerState::ReadyToSwitchOn)
+        ));
+    }
+
+    #[test]
+    fn waits_in_fault_until_reset() {
+        let mut enabling = Enabling::new();
+        enabling.update(0x0008);
+        enabling.since = Instant::now() - Duration::from_secs(60);
+
+        assert_eq!(pending(enabling.update(0x0008).0), DISABLE_VOLTAGE);
+    }
+
+    #[test]
+    fn pulses_the_fault_reset() {
+        let mut enabling = Enabling::new();
+        enabling.update(0x0008);
+        enabling.reset_fault();
+
+        // low first, so the drive sees a rising edge
+        assert_eq!(pending(enabling.update(0x0008).0), DISABLE_VOLTAGE);
+
+        enabling.reset_requested = Some(Instant::now() - FAULT_RESET_PULSE * 3 / 2);
+        assert_eq!(pending(enabling.update(0x0008).0), FAULT_RESET);
+
+        enabling.reset_requested = Some(Instant::now() - FAULT_RESET_TIMEOUT * 2);
+        assert!(matches!(enabling.update(0x0008).0, Progress::ResetFailed));
+
+        // another reset has to be requested
+        assert_eq!(pending(enabling.update(0x0008).0), DISABLE_VOLTAGE);
+    }
+
+    #[test]
+    fn forgets_the_reset_once_the_fault_clears() {
+        let mut enabling = Enabling::new();
+        enabling.update(0x0008);
+        enabling.reset_fault();
+
+        assert_eq!(pending(enabling.update(0x0040).0), SHUTDOWN);
+        assert!(enabling.reset_requested.is_none());
+    }
+}
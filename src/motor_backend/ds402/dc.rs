@@ -0,0 +1,230 @@
+use super::registers;
+
+use std::time::{Duration, Instant};
+
+// how far after the subdevice's current system time sync0 starts, so the start time is
+// still in the future once the remaining register writes have gone through.
+const SYNC0_START_DELAY: Duration = Duration::from_millis(100);
+
+// sync0 activation (0x0981): cyclic operation and the sync0 signal.
+pub(crate) const SYNC0_ACTIVE: u8 = 0x03;
+
+// the sync0 cycle time register (0x09A0) holds 32 bit nanoseconds.
+pub(crate) const MAX_CYCLE: Duration = Duration::from_nanos(u32::MAX as u64);
+
+// drift compensation: a configured address read multiple write of the reference clock's system
+// time. the reference clock puts its time into the datagram and every subdevice after it copies
+// the time into its own, so their clocks do not drift apart while running.
+const FRMW_COMMAND: u8 = 0x0E;
+// picks the drift frames out of the received ones, they never reach the pdu loop.
+const DRIFT_INDEX: u8 = 0xFF;
+const ETHERCAT_TYPE: u16 = 0x88A4;
+// the same source address the maindevice sends from.
+const SOURCE_ADDRESS: [u8; 6] = [0x10; 6];
+// frames shorter than this are padded, not every driver does it for raw sockets.
+const MIN_FRAME_LEN: usize = 60;
+const DATAGRAM_OFFSET: usize = 16;
+const DATAGRAM_HEADER_LEN: usize = 10;
+const SYSTEM_TIME_LEN: usize = 8;
+
+// a drift compensation frame for the reference clock at the configured address `reference`.
+pub(crate) fn drift_frame(reference: u16) -> Vec<u8> {
+    let datagram_len = DATAGRAM_HEADER_LEN + SYSTEM_TIME_LEN + 2;
+
+    let mut frame = Vec::with_capacity(MIN_FRAME_LEN);
+    frame.extend_from_slice(&[0xFF; 6]);
+    frame.extend_from_slice(&SOURCE_ADDRESS);
+    frame.extend_from_slice(&ETHERCAT_TYPE.to_be_bytes());
+    // the frame length in the low 11 bits, datagrams are type 1
+    frame.extend_from_slice(&(datagram_len as u16 | 1 << 12).to_le_bytes());
+
+    frame.extend_from_slice(&[FRMW_COMMAND, DRIFT_INDEX]);
+    frame.extend_from_slice(&reference.to_le_bytes());
+    frame.extend_from_slice(&registers::DC_SYSTEM_TIME.to_le_bytes());
+    frame.extend_from_slice(&(SYSTEM_TIME_LEN as u16).to_le_bytes());
+    // no interrupt, then the time and the working counter
+    frame.resize(frame.len() + 2 + SYSTEM_TIME_LEN + 2, 0);
+
+    frame.resize(frame.len().max(MIN_FRAME_LEN), 0);
+    frame
+}
+
+pub(crate) fn is_drift_frame(frame: &[u8]) -> bool {
+    frame.len() >= DATAGRAM_OFFSET + DATAGRAM_HEADER_LEN + SYSTEM_TIME_LEN + 2
+        && frame[12..14] == ETHERCAT_TYPE.to_be_bytes()
+        && frame[DATAGRAM_OFFSET..DATAGRAM_OFFSET + 2] == [FRMW_COMMAND, DRIFT_INDEX]
+}
+
+// the reference clock's system time in a drift frame that came back, `None` when the reference
+// clock did not answer.
+pub(crate) fn drift_system_time(frame: &[u8]) -> Option<u64> {
+    if !is_drift_frame(frame) {
+        return None;
+    }
+    let time = DATAGRAM_OFFSET + DATAGRAM_HEADER_LEN;
+    let wkc = time + SYSTEM_TIME_LEN;
+    if frame[wkc..wkc + 2] == [0, 0] {
+        return None;
+    }
+    let mut bytes = [0; SYSTEM_TIME_LEN];
+    bytes.copy_from_slice(&frame[time..wkc]);
+    Some(u64::from_le_bytes(bytes))
+}
+
+// the first sync0 pulse, on a multiple of the cycle time plus the subdevice's shift, so that
+// every subdevice pulses at the same points in time apart from its shift.
+pub(crate) fn sync0_start_time(system_time: u64, cycle: Duration, shift: Duration) -> u64 {
+    let cycle = cycle.as_nanos() as u64;
+    let earliest = system_time + SYNC0_START_DELAY.as_nanos() as u64;
+    (earliest / cycle + 1) * cycle + shift.as_nanos() as u64
+}
+
+// the system time difference register (0x092C) holds a sign bit over the magnitude. negative
+// when the subdevice's copy of the system time is behind the reference clock.
+pub(crate) fn system_time_difference(raw: u32) -> i64 {
+    let magnitude = (raw & 0x7FFF_FFFF) as i64;
+    if raw & 0x8000_0000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+// holds each process data exchange back until the next cycle starts, rather than sending the
+// next one as soon as the previous one came back.
+#[derive(Default)]
+pub(crate) struct CycleTimer {
+    period: Option<Duration>,
+    next: Option<Instant>,
+}
+
+impl CycleTimer {
+    // `None` runs the process data as fast as the bus allows.
+    pub fn set_period(&mut self, period: Option<Duration>) {
+        self.period = period;
+        self.next = None;
+    }
+
+    pub fn period(&self) -> Option<Duration> {
+        self.period
+    }
+
+    // how long process data that just arrived has to wait for its cycle.
+    pub fn wait(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.next.filter(|next| *next > now).map(|next| next - now)
+    }
+
+    // moves the cycles onto the reference clock's cycle boundaries, which every sync0 pulse is
+    // shifted from. the process data then goes out on a boundary and the sync0 shift leaves it
+    // time to arrive. `system_time` was read by a frame that came back at `at`, the local clock
+    // drifts against it so the cycles would otherwise slide through the sync0 pulses.
+    pub fn align(&mut self, system_time: u64, at: Instant) {
+        let (Some(period), Some(next)) = (self.period, self.next) else {
+            return;
+        };
+        let cycle = period.as_nanos() as u64;
+        let boundary = at + Duration::from_nanos(cycle - system_time % cycle);
+
+        // the boundary closest to the planned start of the next cycle
+        let closest = |offset: Duration| {
+            let cycles = (offset.as_nanos() as u64 + cycle / 2) / cycle;
+            Duration::from_nanos(cycles * cycle)
+        };
+        self.next = Some(if next >= boundary {
+            boundary + closest(next - boundary)
+        } else {
+            boundary - closest(boundary - next)
+        });
+    }
+
+    // a cycle was handed on, the next one starts a period later. after an overrun the cycle
+    // starts over from now instead of trying to catch up.
+    pub fn advance(&mut self) {
+        let Some(period) = self.period else {
+            return;
+        };
+        let now = Instant::now();
+        self.next = Some(match self.next {
+            Some(next) if next + period > now => next + period,
+            _ => now + period,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CYCLE: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn sync0_starts_on_a_cycle_boundary() {
+        let system_time = 1_234_567_891;
+        let start = sync0_start_time(system_time, CYCLE, Duration::ZERO);
+
+        assert_eq!(start % CYCLE.as_nanos() as u64, 0);
+        assert!(start > system_time + SYNC0_START_DELAY.as_nanos() as u64);
+        assert!(start <= system_time + (SYNC0_START_DELAY + CYCLE).as_nanos() as u64);
+    }
+
+    #[test]
+    fn sync0_start_is_shared_between_subdevices() {
+        // subdevices read their system time at slightly different points
+        let first = sync0_start_time(5_000_100_000, CYCLE, Duration::ZERO);
+        let second = sync0_start_time(5_000_400_000, CYCLE, Duration::ZERO);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn sync0_start_is_shifted() {
+        let shift = Duration::from_micros(250);
+        let unshifted = sync0_start_time(5_000_100_000, CYCLE, Duration::ZERO);
+        let shifted = sync0_start_time(5_000_100_000, CYCLE, shift);
+        assert_eq!(shifted - unshifted, shift.as_nanos() as u64);
+    }
+
+    #[test]
+    fn cycles_move_onto_the_reference_clock() {
+        let mut timer = CycleTimer::default();
+        timer.set_period(Some(CYCLE));
+        timer.advance();
+
+        // a quarter into a cycle of the reference clock, the next boundary is three quarters away
+        let at = Instant::now();
+        timer.align(5_000_250_000, at);
+        assert_eq!(timer.next, Some(at + Duration::from_micros(750)));
+
+        // without a period there is nothing to align
+        let mut timer = CycleTimer::default();
+        timer.align(5_000_250_000, at);
+        assert_eq!(timer.next, None);
+    }
+
+    #[test]
+    fn drift_frame_reads_the_reference_clock() {
+        let mut frame = drift_frame(0x1001);
+        assert_eq!(frame.len(), MIN_FRAME_LEN);
+        assert!(is_drift_frame(&frame));
+        assert_eq!(frame[18..20], [0x01, 0x10]);
+        assert_eq!(frame[20..22], registers::DC_SYSTEM_TIME.to_le_bytes());
+        // not answered yet
+        assert_eq!(drift_system_time(&frame), None);
+
+        frame[26..34].copy_from_slice(&1_234_567_891u64.to_le_bytes());
+        frame[34] = 3;
+        assert_eq!(drift_system_time(&frame), Some(1_234_567_891));
+
+        frame[16] = 0x0C;
+        assert!(!is_drift_frame(&frame));
+    }
+
+    #[test]
+    fn system_time_difference_is_sign_magnitude() {
+        assert_eq!(system_time_difference(0), 0);
+        assert_eq!(system_time_difference(1500), 1500);
+        assert_eq!(system_time_difference(0x8000_0000 | 1500), -1500);
+        assert_eq!(system_time_difference(0x7FFF_FFFF), 0x7FFF_FFFF);
+        assert_eq!(system_time_difference(0xFFFF_FFFF), -0x7FFF_FFFF);
+    }
+}
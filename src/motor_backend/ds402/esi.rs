@@ -0,0 +1,602 @@
+use super::pdo::PdoType;
+use super::sdo::SdoAddr;
+
+use std::sync::Arc;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum EsiAccess {
+    ReadOnly,
+    ReadWrite,
+    WriteOnly,
+}
+
+impl EsiAccess {
+    fn parse(s: &str) -> Option<Self> {
+        // `rwr`/`rww` only restrict writes to some al states
+        Some(match s.trim() {
+            "ro" => Self::ReadOnly,
+            "rw" | "rwr" | "rww" => Self::ReadWrite,
+            "wo" => Self::WriteOnly,
+            _ => return None,
+        })
+    }
+}
+
+// an object dictionary entry as described by the device's esi file.
+#[derive(Clone, Debug)]
+pub struct EsiObject {
+    pub addr: SdoAddr,
+    pub name: String,
+    // `None` for types the browser cannot show, such as strings and floats
+    pub ty: Option<PdoType>,
+    pub access: EsiAccess,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+impl EsiObject {
+    // whether `value` may be written, according to the access rights and the allowed range.
+    pub fn validate(&self, value: i64) -> Result<(), String> {
+        if self.access == EsiAccess::ReadOnly {
+            return Err(format!("{} is read only", self.name));
+        }
+
+        let (ty_min, ty_max) = match self.ty {
+            Some(PdoType::U8) => (0, u8::MAX as i64),
+            Some(PdoType::I8) => (i8::MIN as i64, i8::MAX as i64),
+            Some(PdoType::U16) => (0, u16::MAX as i64),
+            Some(PdoType::I16) => (i16::MIN as i64, i16::MAX as i64),
+            Some(PdoType::U32) => (0, u32::MAX as i64),
+            Some(PdoType::I32) => (i32::MIN as i64, i32::MAX as i64),
+            None => (i64::MIN, i64::MAX),
+        };
+        let min = self.min.unwrap_or(ty_min).max(ty_min);
+        let max = self.max.unwrap_or(ty_max).min(ty_max);
+
+        if !(min..=max).contains(&value) {
+            return Err(format!("{} has to be within {min}..={max}", self.name));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EsiPdoEntry {
+    pub name: String,
+    // index 0 is padding
+    pub index: u16,
+    pub subindex: u8,
+    pub bits: u32,
+    pub ty: Option<PdoType>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EsiDevice {
+    pub name: String,
+    pub vendor_id: u32,
+    pub product_code: u32,
+    pub revision: u32,
+    pub objects: Vec<EsiObject>,
+    // the entries of the pdos assigned by default, in order
+    pub rx_pdo: Vec<EsiPdoEntry>,
+    pub tx_pdo: Vec<EsiPdoEntry>,
+}
+
+impl EsiDevice {
+    pub fn object(&self, addr: SdoAddr) -> Option<&EsiObject> {
+        self.objects.iter().find(|object| object.addr == addr)
+    }
+
+    // the default pdo mapping, as an entry of the pdo layout file.
+    pub fn suggested_layout(&self) -> Result<serde_json::Value, String> {
+        let entries = |pdo: &[EsiPdoEntry]| -> Result<Vec<serde_json::Value>, String> {
+            pdo.iter()
+                .map(|entry| {
+                    let ty = entry.ty.filter(|ty| ty.size() * 8 == entry.bits as usize);
+                    let Some(ty) = ty else {
+                        return Err(format!(
+                            "{} ({} bits) has no byte sized type a layout can hold",
+                            entry.name, entry.bits
+                        ));
+                    };
+                    Ok(serde_json::json!({
+                        "name": entry.name,
+                        "index": format!("{:#06x}", entry.index),
+                        "subindex": entry.subindex,
+                        "type": ty.name(),
+                    }))
+                })
+                .collect()
+        };
+
+        Ok(serde_json::json!({
+            "inputs": entries(&self.tx_pdo)?,
+            "outputs": entries(&self.rx_pdo)?,
+        }))
+    }
+}
+
+// the devices of every esi file loaded.
+#[derive(Clone, Debug, Default)]
+pub struct EsiLibrary {
+    devices: Vec<Arc<EsiDevice>>,
+}
+
+impl EsiLibrary {
+    // `path` is a single esi file or a directory of them.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let files = if path.is_dir() {
+            let entries =
+                std::fs::read_dir(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            let mut files = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| {
+                    file.extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
+                })
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        } else {
+            vec![path.to_owned()]
+        };
+
+        let mut library = Self::default();
+        for file in files {
+            let text =
+                std::fs::read_to_string(&file).map_err(|e| format!("{}: {e}", file.display()))?;
+            let devices = parse(&text).map_err(|e| format!("{}: {e}", file.display()))?;
+            library.devices.extend(devices.into_iter().map(Arc::new));
+        }
+        Ok(library)
+    }
+
+    pub fn device_count(&self) -> usize {
+        self.devices.len()
+    }
+
+    // the exact revision if the library has it, otherwise any revision of the product.
+    pub fn find(&self, vendor_id: u32, product_code: u32, revision: u32) -> Option<Arc<EsiDevice>> {
+        let mut products = self
+            .devices
+            .iter()
+            .filter(|d| d.vendor_id == vendor_id && d.product_code == product_code);
+        let exact = products.clone().find(|d| d.revision == revision);
+        exact.or_else(|| products.next()).cloned()
+    }
+}
+
+// esi numbers are decimal or hexadecimal with a `#x` prefix.
+fn parse_number(s: &str) -> Option<i64> {
+    let s = s.trim();
+    match s.strip_prefix("#x").or_else(|| s.strip_prefix("#X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn pdo_type(name: &str) -> Option<PdoType> {
+    Some(match name.trim() {
+        "BOOL" | "USINT" | "BYTE" => PdoType::U8,
+        "SINT" => PdoType::I8,
+        "UINT" | "WORD" => PdoType::U16,
+        "INT" => PdoType::I16,
+        "UDINT" | "DWORD" => PdoType::U32,
+        "DINT" => PdoType::I32,
+        _ => return None,
+    })
+}
+
+type Node<'a, 'i> = roxmltree::Node<'a, 'i>;
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|c| c.has_tag_name(name))
+}
+
+fn children<'a, 'i: 'a>(node: Node<'a, 'i>, name: &'a str) -> impl Iterator<Item = Node<'a, 'i>> {
+    node.children().filter(move |c| c.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|c| c.text())
+}
+
+fn child_number(node: Node, name: &str) -> Option<i64> {
+    child_text(node, name).and_then(parse_number)
+}
+
+fn access(node: Node) -> Option<EsiAccess> {
+    child(node, "Flags")
+        .and_then(|flags| child_text(flags, "Access"))
+        .and_then(EsiAccess::parse)
+}
+
+fn parse(text: &str) -> Result<Vec<EsiDevice>, String> {
+    let doc = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+
+    let vendor_id = child(root, "Vendor")
+        .and_then(|vendor| child_number(vendor, "Id"))
+        .ok_or("missing vendor id")? as u32;
+
+    let devices = child(root, "Descriptions")
+        .and_then(|descriptions| child(descriptions, "Devices"))
+        .ok_or("missing device descriptions")?;
+
+    children(devices, "Device")
+        .map(|device| parse_device(device, vendor_id))
+        .collect()
+}
+
+fn parse_device(device: Node, vendor_id: u32) -> Result<EsiDevice, String> {
+    let ty = child(device, "Type").ok_or("device without a type")?;
+    let number = |attribute: &str| ty.attribute(attribute).and_then(parse_number);
+    let product_code = number("ProductCode").ok_or("device without a product code")? as u32;
+    let revision = number("RevisionNo").unwrap_or(0) as u32;
+
+    let name = child_text(device, "Name")
+        .or_else(|| ty.text())
+        .unwrap_or_default()
+        .trim()
+        .to_owned();
+
+    let dictionary = child(device, "Profile").and_then(|profile| child(profile, "Dictionary"));
+    let objects = match dictionary {
+        Some(dictionary) => parse_objects(dictionary),
+        None => Vec::new(),
+    };
+
+    Ok(EsiDevice {
+        name,
+        vendor_id,
+        product_code,
+        revision,
+        objects,
+        rx_pdo: parse_pdos(device, "RxPdo"),
+        tx_pdo: parse_pdos(device, "TxPdo"),
+    })
+}
+
+fn parse_objects(dictionary: Node) -> Vec<EsiObject> {
+    let data_types = child(dictionary, "DataTypes")
+        .map(|types| children(types, "DataType").collect::<Vec<_>>())
+        .unwrap_or_default();
+    let data_type = |name: &str| {
+        data_types
+            .iter()
+            .find(|ty| child_text(**ty, "Name").is_some_and(|n| n.trim() == name))
+            .copied()
+    };
+
+    let mut objects = Vec::new();
+    let Some(list) = child(dictionary, "Objects") else {
+        return objects;
+    };
+
+    for object in children(list, "Object") {
+        let Some(index) = child_number(object, "Index") else {
+            continue;
+        };
+        let index = index as u16;
+        let name = child_text(object, "Name")
+            .unwrap_or_default()
+            .trim()
+            .to_owned();
+        let type_name = child_text(object, "Type").unwrap_or_default().trim();
+        let object_access = access(object).unwrap_or(EsiAccess::ReadOnly);
+
+        let sub_items = data_type(type_name)
+            .map(|ty| children(ty, "SubItem").collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        if sub_items.is_empty() {
+            let info = child(object, "Info");
+            objects.push(EsiObject {
+                addr: SdoAddr::new(index, 0),
+                name,
+                ty: pdo_type(type_name),
+                access: object_access,
+                min: info.and_then(|info| child_number(info, "MinValue")),
+                max: info.and_then(|info| child_number(info, "MaxValue")),
+            });
+            continue;
+        }
+
+        for item in sub_items {
+            let item_name = child_text(item, "Name").unwrap_or_default().trim();
+            let item_type = child_text(item, "Type").unwrap_or_default().trim();
+            let item_access = access(item).unwrap_or(object_access);
+
+            // arrays are a single sub item whose type lists the elements
+            let array = data_type(item_type).and_then(|ty| child(ty, "ArrayInfo").map(|a| (ty, a)));
+            if let Some((array_ty, info)) = array {
+                let first = child_number(info, "LBound").unwrap_or(1);
+                let elements = child_number(info, "Elements").unwrap_or(0);
+                let base = child_text(array_ty, "BaseType").and_then(pdo_type);
+                for subindex in first..first + elements {
+                    objects.push(EsiObject {
+                        addr: SdoAddr::new(index, subindex as u8),
+                        name: format!("{name} {subindex}"),
+                        ty: base,
+                        access: item_access,
+                        min: None,
+                        max: None,
+                    });
+                }
+                continue;
+            }
+
+            let Some(subindex) = child_number(item, "SubIdx") else {
+                continue;
+            };
+            objects.push(EsiObject {
+                addr: SdoAddr::new(index, subindex as u8),
+                name: format!("{name}: {item_name}"),
+                ty: pdo_type(item_type),
+                access: item_access,
+                min: None,
+                max: None,
+            });
+        }
+    }
+    objects
+}
+
+// the entries of the pdos assigned to a sync manager by default, or of the first pdo when
+// none are.
+fn parse_pdos(device: Node, tag: &str) -> Vec<EsiPdoEntry> {
+    let pdos = children(device, tag).collect::<Vec<_>>();
+    let assigned = pdos
+        .iter()
+        .filter(|pdo| pdo.attribute("Sm").is_some())
+        .copied()
+        .collect::<Vec<_>>();
+    let pdos = if assigned.is_empty() {
+        pdos.into_iter().take(1).collect()
+    } else {
+        assigned
+    };
+
+    pdos.into_iter()
+        .flat_map(|pdo| children(pdo, "Entry"))
+        .filter_map(|entry| {
+            let index = child_number(entry, "Index")? as u16;
+            let bits = child_number(entry, "BitLen")? as u32;
+            let name = match child_text(entry, "Name") {
+                Some(name) => name.trim().to_owned(),
+                None if index == 0 => "padding".into(),
+                None => format!("{index:#06x}"),
+            };
+            let ty = match index {
+                // padding only has a length
+                0 => [PdoType::U8, PdoType::U16, PdoType::U32]
+                    .into_iter()
+                    .find(|ty| ty.size() * 8 == bits as usize),
+                _ => child_text(entry, "DataType").and_then(pdo_type),
+            };
+
+            Some(EsiPdoEntry {
+                name,
+                index,
+                subindex: child_number(entry, "SubIndex").unwrap_or(0) as u8,
+                bits,
+                ty,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ESI: &str = r##"<?xml version="1.0" encoding="ISO-8859-1"?>
+<EtherCATInfo>
+  <Vendor>
+    <Id>#x000000AB</Id>
+  </Vendor>
+  <Descriptions>
+    <Devices>
+      <Device>
+        <Type ProductCode="#x00001234" RevisionNo="#x00000002">Drive</Type>
+        <Name>Test Drive</Name>
+        <Profile>
+          <Dictionary>
+            <DataTypes>
+              <DataType>
+                <Name>DT1018</Name>
+                <SubItem>
+                  <SubIdx>0</SubIdx>
+                  <Name>Number of entries</Name>
+                  <Type>USINT</Type>
+                </SubItem>
+                <SubItem>
+                  <SubIdx>1</SubIdx>
+                  <Name>Vendor ID</Name>
+                  <Type>UDINT</Type>
+                </SubItem>
+              </DataType>
+              <DataType>
+                <Name>DT1C12ARR</Name>
+                <BaseType>UINT</BaseType>
+                <ArrayInfo>
+                  <LBound>1</LBound>
+                  <Elements>2</Elements>
+                </ArrayInfo>
+              </DataType>
+              <DataType>
+                <Name>DT1C12</Name>
+                <SubItem>
+                  <Name>Elements</Name>
+                  <Type>DT1C12ARR</Type>
+                  <Flags>
+                    <Access>rw</Access>
+                  </Flags>
+                </SubItem>
+              </DataType>
+            </DataTypes>
+            <Objects>
+              <Object>
+                <Index>#x6060</Index>
+                <Name>Modes of operation</Name>
+                <Type>SINT</Type>
+                <Info>
+                  <MinValue>-3</MinValue>
+                  <MaxValue>10</MaxValue>
+                </Info>
+                <Flags>
+                  <Access>rww</Access>
+                </Flags>
+              </Object>
+              <Object>
+                <Index>#x1018</Index>
+                <Name>Identity</Name>
+                <Type>DT1018</Type>
+              </Object>
+              <Object>
+                <Index>#x1C12</Index>
+                <Name>RxPDO assign</Name>
+                <Type>DT1C12</Type>
+              </Object>
+              <Object>
+                <Index>#x1008</Index>
+                <Name>Device name</Name>
+                <Type>STRING(10)</Type>
+              </Object>
+            </Objects>
+          </Dictionary>
+        </Profile>
+        <RxPdo Sm="2">
+          <Index>#x1600</Index>
+          <Entry>
+            <Index>#x6040</Index>
+            <SubIndex>0</SubIndex>
+            <BitLen>16</BitLen>
+            <Name>Controlword</Name>
+            <DataType>UINT</DataType>
+          </Entry>
+          <Entry>
+            <Index>#x0</Index>
+            <BitLen>8</BitLen>
+          </Entry>
+        </RxPdo>
+        <TxPdo>
+          <Index>#x1A00</Index>
+          <Entry>
+            <Index>#x6041</Index>
+            <SubIndex>0</SubIndex>
+            <BitLen>16</BitLen>
+            <Name>Statusword</Name>
+            <DataType>UINT</DataType>
+          </Entry>
+        </TxPdo>
+        <TxPdo>
+          <Index>#x1A01</Index>
+          <Entry>
+            <Index>#x6064</Index>
+            <SubIndex>0</SubIndex>
+            <BitLen>32</BitLen>
+            <Name>Position actual value</Name>
+            <DataType>DINT</DataType>
+          </Entry>
+        </TxPdo>
+      </Device>
+    </Devices>
+  </Descriptions>
+</EtherCATInfo>
+"##;
+
+    fn device() -> EsiDevice {
+        let mut devices = parse(ESI).unwrap();
+        assert_eq!(devices.len(), 1);
+        devices.remove(0)
+    }
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(parse_number("#x1A"), Some(0x1A));
+        assert_eq!(parse_number(" #X1a "), Some(0x1A));
+        assert_eq!(parse_number("-12"), Some(-12));
+        assert_eq!(parse_number("#xZZ"), None);
+    }
+
+    #[test]
+    fn parses_the_identity() {
+        let device = device();
+        assert_eq!(device.name, "Test Drive");
+        assert_eq!(device.vendor_id, 0xAB);
+        assert_eq!(device.product_code, 0x1234);
+        assert_eq!(device.revision, 2);
+    }
+
+    #[test]
+    fn parses_plain_objects() {
+        let device = device();
+        let modes = device.object(SdoAddr::new(0x6060, 0)).unwrap();
+        assert_eq!(modes.name, "Modes of operation");
+        assert_eq!(modes.ty, Some(PdoType::I8));
+        assert_eq!(modes.access, EsiAccess::ReadWrite);
+        assert_eq!((modes.min, modes.max), (Some(-3), Some(10)));
+
+        assert!(modes.validate(8).is_ok());
+        assert!(modes.validate(11).is_err());
+
+        // strings have no type the browser can show, and objects default to read only
+        let name = device.object(SdoAddr::new(0x1008, 0)).unwrap();
+        assert_eq!(name.ty, None);
+        assert_eq!(name.access, EsiAccess::ReadOnly);
+        assert!(name.validate(0).is_err());
+    }
+
+    #[test]
+    fn parses_records() {
+        let device = device();
+        let vendor = device.object(SdoAddr::new(0x1018, 1)).unwrap();
+        assert_eq!(vendor.name, "Identity: Vendor ID");
+        assert_eq!(vendor.ty, Some(PdoType::U32));
+        assert!(device.object(SdoAddr::new(0x1018, 0)).is_some());
+    }
+
+    #[test]
+    fn parses_arrays() {
+        let device = device();
+        for subindex in [1, 2] {
+            let element = device.object(SdoAddr::new(0x1C12, subindex)).unwrap();
+            assert_eq!(element.name, format!("RxPDO assign {subindex}"));
+            assert_eq!(element.ty, Some(PdoType::U16));
+            assert_eq!(element.access, EsiAccess::ReadWrite);
+        }
+        assert!(device.object(SdoAddr::new(0x1C12, 3)).is_none());
+    }
+
+    #[test]
+    fn parses_the_default_pdos() {
+        let device = device();
+
+        let rx = &device.rx_pdo;
+        assert_eq!(rx.len(), 2);
+        assert_eq!((rx[0].index, rx[0].bits), (0x6040, 16));
+        assert_eq!(rx[0].ty, Some(PdoType::U16));
+        assert_eq!(rx[1].name, "padding");
+        assert_eq!(rx[1].ty, Some(PdoType::U8));
+
+        // without an assigned pdo only the first one is used
+        let tx = &device.tx_pdo;
+        assert_eq!(tx.len(), 1);
+        assert_eq!(tx[0].name, "Statusword");
+    }
+
+    #[test]
+    fn suggests_a_layout() {
+        let layout = device().suggested_layout().unwrap();
+        assert_eq!(layout["inputs"][0]["index"], "0x6041");
+        assert_eq!(layout["inputs"][0]["type"], "u16");
+        assert_eq!(layout["outputs"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejects_files_without_a_vendor() {
+        assert!(parse("<EtherCATInfo><Descriptions/></EtherCATInfo>").is_err());
+    }
+}
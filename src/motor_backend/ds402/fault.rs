@@ -0,0 +1,177 @@
+use super::sdo::{SdoAbort, SdoAddr};
+
+// the objects read through sdo once a drive reports a fault, in that order.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum FaultField {
+    // 0x603F, the CiA402 error code
+    ErrorCode,
+    // 0x1001, the CiA301 error register
+    ErrorRegister,
+    // 0x1003:1, the newest entry of the error history, carrying the manufacturer specific code
+    Manufacturer,
+}
+
+impl FaultField {
+    pub const ALL: [Self; 3] = [Self::ErrorCode, Self::ErrorRegister, Self::Manufacturer];
+
+    pub fn addr(&self) -> SdoAddr {
+        match self {
+            Self::ErrorCode => SdoAddr::new(0x603F, 0),
+            Self::ErrorRegister => SdoAddr::new(0x1001, 0),
+            Self::Manufacturer => SdoAddr::new(0x1003, 1),
+        }
+    }
+}
+
+// what a faulted drive reported about its fault. objects the drive does not implement are
+// left out.
+#[derive(Clone, Debug, Default)]
+pub struct Ds402Fault {
+    pub error_code: Option<u16>,
+    pub error_register: Option<u8>,
+    pub manufacturer: Option<u32>,
+    // objects that could not be read, and why
+    pub aborted: Vec<(SdoAddr, SdoAbort)>,
+}
+
+impl Ds402Fault {
+    pub(crate) fn set(&mut self, field: FaultField, result: Result<Vec<u8>, SdoAbort>) {
+        let data = match result {
+            Ok(data) => data,
+            Err(abort) => {
+                self.aborted.push((field.addr(), abort));
+                return;
+            }
+        };
+
+        let le = |len: usize| {
+            let mut bytes = [0; 4];
+            let len = len.min(data.len());
+            bytes[..len].copy_from_slice(&data[..len]);
+            u32::from_le_bytes(bytes)
+        };
+
+        match field {
+            FaultField::ErrorCode => self.error_code = Some(le(2) as u16),
+            FaultField::ErrorRegister => self.error_register = Some(le(1) as u8),
+            FaultField::Manufacturer => self.manufacturer = Some(le(4)),
+        }
+    }
+
+    pub fn description(&self) -> Option<&'static str> {
+        self.error_code.map(describe_error_code)
+    }
+
+    // the bits set in the error register, by name.
+    pub fn register_flags(&self) -> Vec<&'static str> {
+        const FLAGS: [(u8, &str); 7] = [
+            (0x01, "generic"),
+            (0x02, "current"),
+            (0x04, "voltage"),
+            (0x08, "temperature"),
+            (0x10, "communication"),
+            (0x20, "device profile"),
+            (0x80, "manufacturer"),
+        ];
+
+        let register = self.error_register.unwrap_or(0);
+        FLAGS
+            .iter()
+            .filter(|(bit, _)| register & bit != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+impl core::fmt::Display for Ds402Fault {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.error_code {
+            Some(code) => write!(f, "{code:#06x}: {}", describe_error_code(code))?,
+            None => write!(f, "unknown error")?,
+        }
+
+        if let Some(register) = self.error_register {
+            write!(f, ", error register {register:#04x}")?;
+            let flags = self.register_flags();
+            if !flags.is_empty() {
+                write!(f, " ({})", flags.join(", "))?;
+            }
+        }
+
+        // the low word repeats the error code, the high word is up to the manufacturer
+        if let Some(manufacturer) = self.manufacturer {
+            write!(f, ", manufacturer code {:#06x}", manufacturer >> 16)?;
+        }
+        Ok(())
+    }
+}
+
+// the CiA402/CiA301 meaning of an error code, falling back to its class.
+pub fn describe_error_code(code: u16) -> &'static str {
+    match code {
+        0x0000 => "no error",
+        0x1000 => "generic error",
+        0x2220 => "continuous overcurrent inside the device",
+        0x2250 => "short circuit inside the device",
+        0x2310 => "continuous overcurrent",
+        0x2311 => "continuous overcurrent (motor)",
+        0x2320 => "short circuit or earth leakage",
+        0x2330 => "earth leakage",
+        0x2340 => "short circuit",
+        0x3110 => "mains overvoltage",
+        0x3120 => "mains undervoltage",
+        0x3130 => "phase failure",
+        0x3210 => "dc link overvoltage",
+        0x3220 => "dc link undervoltage",
+        0x3230 => "load error",
+        0x4110 => "excess ambient temperature",
+        0x4120 => "too low ambient temperature",
+        0x4210 => "excess device temperature",
+        0x4220 => "too low device temperature",
+        0x4310 => "excess drive temperature",
+        0x4320 => "too low drive temperature",
+        0x4410 => "excess supply temperature",
+        0x5112 => "supply low voltage",
+        0x5114 => "supply low voltage (u1)",
+        0x5400 => "power section",
+        0x5441 => "positive limit switch",
+        0x5442 => "negative limit switch",
+        0x5443 => "quick stop input",
+        0x5530 => "data storage (eeprom) error",
+        0x6010 => "software reset (watchdog)",
+        0x6320 => "parameter error",
+        0x7110 => "brake chopper",
+        0x7120 => "motor error",
+        0x7121 => "motor blocked",
+        0x7122 => "motor error or commutation malfunction",
+        0x7300 => "sensor error",
+        0x7303 => "resolver 1 fault",
+        0x7304 => "resolver 2 fault",
+        0x7305 => "incremental sensor 1 fault",
+        0x7306 => "incremental sensor 2 fault",
+        0x7310 => "speed sensor",
+        0x7320 => "position sensor",
+        0x8110 => "can overrun",
+        0x8120 => "can error passive",
+        0x8130 => "life guard or heartbeat error",
+        0x8400 => "velocity control error",
+        0x8500 => "position control error",
+        0x8611 => "following error",
+        0x8612 => "reference limit",
+        0x8700 => "sync controller error",
+        0x8A00 => "homing error",
+        0xFF00..=0xFFFF => "device specific",
+        _ => match code >> 12 {
+            0x2 => "current",
+            0x3 => "voltage",
+            0x4 => "temperature",
+            0x5 => "device hardware",
+            0x6 => "device software",
+            0x7 => "additional modules",
+            0x8 => "monitoring",
+            0x9 => "external error",
+            0xF => "additional functions",
+            _ => "unknown error",
+        },
+    }
+}
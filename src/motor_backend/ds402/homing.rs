@@ -0,0 +1,338 @@
+use super::cia402::{ENABLE_OPERATION, Enabling, PowerState, Progress};
+use super::sdo::SdoAddr;
+use super::{Ds402Cmd, Ds402Response, Ds402Units};
+
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+// homing objects, written over sdo before each homing run.
+const HOMING_METHOD: SdoAddr = SdoAddr::new(0x6098, 0);
+const SWITCH_SEARCH_SPEED: SdoAddr = SdoAddr::new(0x6099, 1);
+const ZERO_SEARCH_SPEED: SdoAddr = SdoAddr::new(0x6099, 2);
+const HOMING_ACCELERATION: SdoAddr = SdoAddr::new(0x609A, 0);
+const HOME_OFFSET: SdoAddr = SdoAddr::new(0x607C, 0);
+
+// controlword bit 4 in homing mode, homing starts on its rising edge.
+const HOMING_START: u16 = 0x0010;
+// statusword bits in homing mode.
+const TARGET_REACHED: u16 = 0x0400;
+const HOMING_ATTAINED: u16 = 0x1000;
+const HOMING_ERROR: u16 = 0x2000;
+
+// right after the start the statusword may still show the end of a previous run.
+const START_GRACE: Duration = Duration::from_millis(100);
+// a drive that does not show homing mode by then is not going to.
+const SWITCH_TIMEOUT: Duration = Duration::from_secs(2);
+// searching for a switch at a low speed can take a while
+const HOMING_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct HomingConfig {
+    // one of the CiA402 homing methods, e.g. 35 or 37 to take the current position as home
+    pub method: i8,
+    pub switch_speed: u32,
+    pub zero_speed: u32,
+    pub acceleration: u32,
+    pub offset: i32,
+}
+
+impl HomingConfig {
+    pub(crate) fn writes(&self) -> [(SdoAddr, Vec<u8>); 5] {
+        [
+            (HOMING_METHOD, self.method.to_le_bytes().into()),
+            (SWITCH_SEARCH_SPEED, self.switch_speed.to_le_bytes().into()),
+            (ZERO_SEARCH_SPEED, self.zero_speed.to_le_bytes().into()),
+            (HOMING_ACCELERATION, self.acceleration.to_le_bytes().into()),
+            (HOME_OFFSET, self.offset.to_le_bytes().into()),
+        ]
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum HomingProgress {
+    // the drive was not enabled when homing was asked for
+    Enabling,
+    // the homing objects are being written
+    Configuring,
+    // waiting for the drive to switch to homing mode
+    Starting,
+    Running,
+    // home was found, the drive is still moving to it
+    Attained,
+    Done,
+    Stopped,
+    Failed(String),
+}
+
+impl core::fmt::Display for HomingProgress {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Enabling => write!(f, "enabling the drive"),
+            Self::Configuring => write!(f, "writing the homing parameters"),
+            Self::Starting => write!(f, "switching to homing mode"),
+            Self::Running => write!(f, "searching for home"),
+            Self::Attained => write!(f, "home found, moving to it"),
+            Self::Done => write!(f, "homed"),
+            Self::Stopped => write!(f, "stopped"),
+            Self::Failed(reason) => write!(f, "failed: {reason}"),
+        }
+    }
+}
+
+impl HomingProgress {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Done | Self::Stopped | Self::Failed(_))
+    }
+}
+
+// one homing run, once the drive is enabled and its parameters are written.
+pub(crate) struct Homing {
+    // takes a drive that was not enabled there first
+    enabling: Option<Enabling>,
+    pending_writes: usize,
+    // when homing mode was first asked for
+    switching: Option<Instant>,
+    started: Option<Instant>,
+    reported: HomingProgress,
+}
+
+impl Homing {
+    pub fn new(pending_writes: usize, enabled: bool) -> Self {
+        let (enabling, reported) = if enabled {
+            (None, HomingProgress::Configuring)
+        } else {
+            (Some(Enabling::new()), HomingProgress::Enabling)
+        };
+        Self {
+            enabling,
+            pending_writes,
+            switching: None,
+            started: None,
+            reported,
+        }
+    }
+
+    pub fn progress(&self) -> &HomingProgress {
+        &self.reported
+    }
+
+    pub fn is_enabling(&self) -> bool {
+        self.enabling.is_some()
+    }
+
+    // takes the drive to operation enabled before homing, `None` once it is there.
+    pub fn enable(&mut self, status: u16) -> Option<(Progress, Option<PowerState>)> {
+        let (progress, changed) = self.enabling.as_mut()?.update(status);
+        if let Progress::Enabled = progress {
+            self.enabling = None;
+        }
+        Some((progress, changed))
+    }
+
+    // one of the homing parameters was written.
+    pub fn configured(&mut self) {
+        self.pending_writes = self.pending_writes.saturating_sub(1);
+    }
+
+    // the controlword to send, along with the progress whenever it changes.
+    pub fn update(&mut self, status: u16, opmode: i8) -> (u16, Option<HomingProgress>) {
+        let (control, progress) = self.next(status, opmode);
+        let changed = (progress != self.reported).then(|| progress.clone());
+        self.reported = progress;
+        (control, changed)
+    }
+
+    fn next(&mut self, status: u16, opmode: i8) -> (u16, HomingProgress) {
+        if self.pending_writes > 0 {
+            return (ENABLE_OPERATION, HomingProgress::Configuring);
+        }
+        if opmode != super::OpMode::Homing as i8 {
+            let switching = self.switching.get_or_insert_with(Instant::now);
+            if switching.elapsed() > SWITCH_TIMEOUT {
+                let reason = format!("the drive stayed in op mode {opmode} instead of homing");
+                return (ENABLE_OPERATION, HomingProgress::Failed(reason));
+            }
+            return (ENABLE_OPERATION, HomingProgress::Starting);
+        }
+
+        // the start bit stays up for the whole run, the drive stops homing when it drops
+        let control = ENABLE_OPERATION | HOMING_START;
+        let elapsed = self.started.get_or_insert_with(Instant::now).elapsed();
+        if elapsed < START_GRACE {
+            return (control, HomingProgress::Running);
+        }
+        if elapsed > HOMING_TIMEOUT {
+            return (ENABLE_OPERATION, HomingProgress::Failed("timed out".into()));
+        }
+
+        let progress = if status & HOMING_ERROR != 0 {
+            HomingProgress::Failed("the drive reported a homing error".into())
+        } else if status & HOMING_ATTAINED == 0 {
+            HomingProgress::Running
+        } else if status & TARGET_REACHED == 0 {
+            HomingProgress::Attained
+        } else {
+            HomingProgress::Done
+        };
+        (control, progress)
+    }
+}
+
+// the homing parameters of a motor and how its last run went.
+pub struct HomingPanel {
+    method_storage: String,
+    switch_speed_storage: String,
+    zero_speed_storage: String,
+    acceleration_storage: String,
+    offset_storage: String,
+    progress: Option<HomingProgress>,
+    homed: bool,
+}
+
+impl Default for HomingPanel {
+    fn default() -> Self {
+        Self {
+            // the current position becomes home, without moving
+            method_storage: "37".into(),
+            switch_speed_storage: "0.5".into(),
+            zero_speed_storage: "0.05".into(),
+            acceleration_storage: "5".into(),
+            offset_storage: "0".into(),
+            progress: None,
+            homed: false,
+        }
+    }
+}
+
+impl HomingPanel {
+    // whether the drive has a defined home, which position mode testing needs.
+    pub fn homed(&self) -> bool {
+        self.homed
+    }
+
+    // the drive may have lost its position, how the last run went is still shown unless it
+    // claims the drive is homed.
+    pub fn lose_home(&mut self) {
+        self.homed = false;
+        if self.progress == Some(HomingProgress::Done) {
+            self.progress = None;
+        }
+    }
+
+    // a drive that lost its process data connection may have lost its home as well.
+    pub fn forget(&mut self) {
+        self.progress = None;
+        self.homed = false;
+    }
+
+    pub(crate) fn update(&mut self, response: &Ds402Response) {
+        if let Ds402Response::Homing(progress) = response {
+            match progress {
+                HomingProgress::Done => self.homed = true,
+                HomingProgress::Enabling | HomingProgress::Configuring => self.homed = false,
+                _ => (),
+            }
+            self.progress = Some(progress.clone());
+        }
+    }
+
+    // converted to the drive's units.
+    fn config(&self, units: &Ds402Units) -> Result<HomingConfig, String> {
+        fn parse<T>(name: &str, s: &str, convert: impl Fn(f64) -> Option<T>) -> Result<T, String> {
+            s.trim()
+                .parse()
+                .ok()
+                .and_then(convert)
+                .ok_or_else(|| format!("invalid {name}: {s}"))
+        }
+
+        Ok(HomingConfig {
+            method: self
+                .method_storage
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid homing method: {}", self.method_storage))?,
+            switch_speed: parse("switch search speed", &self.switch_speed_storage, |v| {
+                units.velocity_param(v)
+            })?,
+            zero_speed: parse("zero search speed", &self.zero_speed_storage, |v| {
+                units.velocity_param(v)
+            })?,
+            acceleration: parse("homing acceleration", &self.acceleration_storage, |a| {
+                units.acceleration_param(a)
+            })?,
+            offset: parse("home offset", &self.offset_storage, |p| {
+                units.position_param(p)
+            })?,
+        })
+    }
+
+    pub(crate) fn display(
+        &mut self,
+        idx: usize,
+        tx: &mpsc::Sender<(usize, Ds402Cmd)>,
+        // `None` while the units do not parse
+        units: Option<&Ds402Units>,
+        ui: &mut egui::Ui,
+    ) {
+        // converted with the motor's units, the drive's own units pass through as they are
+        ui.collapsing("homing", |ui| {
+            let running = self.progress.as_ref().is_some_and(|p| !p.is_finished());
+
+            ui.add_enabled_ui(!running, |ui| {
+                egui::Grid::new(("homing", idx)).show(ui, |ui| {
+                    for (label, storage) in [
+                        ("method (0x6098)", &mut self.method_storage),
+                        (
+                            "switch search speed, rad/s (0x6099:1)",
+                            &mut self.switch_speed_storage,
+                        ),
+                        (
+                            "zero search speed, rad/s (0x6099:2)",
+                            &mut self.zero_speed_storage,
+                        ),
+                        (
+                            "acceleration, rad/s² (0x609A)",
+                            &mut self.acceleration_storage,
+                        ),
+                        ("home offset, rad (0x607C)", &mut self.offset_storage),
+                    ] {
+                        ui.label(label);
+                        ui.text_edit_singleline(storage);
+                        ui.end_row();
+                    }
+                });
+            });
+
+            ui.horizontal(|ui| {
+                if running {
+                    if ui.button("stop homing").clicked() {
+                        let _ = tx.send((idx, Ds402Cmd::StopHoming));
+                    }
+                } else {
+                    let home = ui.add_enabled(units.is_some(), egui::Button::new("home"));
+                    let config = units.filter(|_| home.clicked()).map(|u| self.config(u));
+                    match config {
+                        Some(Ok(config)) => {
+                            let _ = tx.send((idx, Ds402Cmd::Home(config)));
+                        }
+                        Some(Err(e)) => self.progress = Some(HomingProgress::Failed(e)),
+                        None => (),
+                    }
+                }
+
+                match &self.progress {
+                    Some(progress @ HomingProgress::Failed(_)) => {
+                        ui.colored_label(egui::Color32::RED, progress.to_string());
+                    }
+                    Some(progress) => {
+                        ui.label(progress.to_string());
+                    }
+                    None => {
+                        ui.label("not homed");
+                    }
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,385 @@
+use ecat::PdoObject;
+
+// the objects the backend itself reads and writes, every other mapped object is passed on to the ui.
+pub(crate) const STATUSWORD: u16 = 0x6041;
+pub(crate) const POSITION_ACTUAL: u16 = 0x6064;
+pub(crate) const VELOCITY_ACTUAL: u16 = 0x606C;
+pub(crate) const TORQUE_ACTUAL: u16 = 0x6077;
+pub(crate) const OPMODE_DISPLAY: u16 = 0x6061;
+
+pub(crate) const CONTROLWORD: u16 = 0x6040;
+pub(crate) const TARGET_POSITION: u16 = 0x607A;
+pub(crate) const TARGET_VELOCITY: u16 = 0x60FF;
+pub(crate) const TARGET_TORQUE: u16 = 0x6071;
+pub(crate) const OPMODE: u16 = 0x6060;
+
+pub(crate) const TX_PDO: u16 = 0x1A00;
+pub(crate) const RX_PDO: u16 = 0x1600;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PdoType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+}
+
+impl PdoType {
+    pub const ALL: [Self; 6] = [Self::U8, Self::I8, Self::U16, Self::I16, Self::U32, Self::I32];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::I8 => "i8",
+            Self::U16 => "u16",
+            Self::I16 => "i16",
+            Self::U32 => "u32",
+            Self::I32 => "i32",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "u8" => Self::U8,
+            "i8" => Self::I8,
+            "u16" => Self::U16,
+            "i16" => Self::I16,
+            "u32" => Self::U32,
+            "i32" => Self::I32,
+            _ => return None,
+        })
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        match self {
+            Self::U8 | Self::I8 => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::U32 | Self::I32 => 4,
+        }
+    }
+
+    pub(crate) fn decode(&self, bytes: &[u8]) -> i64 {
+        match self {
+            Self::U8 => bytes[0] as i64,
+            Self::I8 => bytes[0] as i8 as i64,
+            Self::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as i64,
+            Self::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as i64,
+            Self::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64,
+            Self::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64,
+        }
+    }
+
+    // values out of range wrap, as they would when cast to the object's type.
+    pub(crate) fn encode(&self, value: i64, bytes: &mut [u8]) {
+        let size = self.size();
+        bytes[..size].copy_from_slice(&value.to_le_bytes()[..size]);
+    }
+
+    fn object(&self, index: u16, subindex: u8) -> PdoObject {
+        // only the size matters to the mapping
+        match self.size() {
+            1 => PdoObject::new::<u8>(index, subindex),
+            2 => PdoObject::new::<u16>(index, subindex),
+            _ => PdoObject::new::<u32>(index, subindex),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PdoEntry {
+    pub name: String,
+    // index 0 maps padding
+    pub index: u16,
+    pub subindex: u8,
+    pub ty: PdoType,
+    // byte offset into the process image of its pdo
+    offset: usize,
+}
+
+impl PdoEntry {
+    fn new(name: &str, index: u16, ty: PdoType) -> Self {
+        Self {
+            name: name.into(),
+            index,
+            subindex: 0,
+            ty,
+            offset: 0,
+        }
+    }
+
+    fn read(&self, image: &[u8]) -> Option<i64> {
+        let bytes = image.get(self.offset..self.offset + self.ty.size())?;
+        Some(self.ty.decode(bytes))
+    }
+
+    // mapped inputs the backend does not use itself, such as a following error or digital inputs.
+    fn is_extra_input(&self) -> bool {
+        const USED: [u16; 5] = [
+            STATUSWORD,
+            POSITION_ACTUAL,
+            VELOCITY_ACTUAL,
+            TORQUE_ACTUAL,
+            OPMODE_DISPLAY,
+        ];
+
+        // index 0 is padding
+        self.index != 0 && !USED.contains(&self.index)
+    }
+}
+
+// the process image of a subdevice, in bytes, against what its pdo layout maps.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ImageMismatch {
+    pub inputs: usize,
+    pub expected_inputs: usize,
+    pub outputs: usize,
+    pub expected_outputs: usize,
+}
+
+impl core::fmt::Display for ImageMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "the process image holds {} of {} input and {} of {} output bytes the pdo layout maps",
+            self.inputs, self.expected_inputs, self.outputs, self.expected_outputs
+        )
+    }
+}
+
+// the objects mapped into a drive's txpdo (inputs) and rxpdo (outputs), in order.
+#[derive(Clone, Debug)]
+pub struct PdoLayout {
+    inputs: Vec<PdoEntry>,
+    outputs: Vec<PdoEntry>,
+}
+
+impl Default for PdoLayout {
+    fn default() -> Self {
+        use PdoType::*;
+
+        Self::new(
+            vec![
+                PdoEntry::new("statusword", STATUSWORD, U16),
+                PdoEntry::new("actual position", POSITION_ACTUAL, I32),
+                PdoEntry::new("actual velocity", VELOCITY_ACTUAL, I32),
+                PdoEntry::new("actual torque", TORQUE_ACTUAL, I16),
+                PdoEntry::new("op mode display", OPMODE_DISPLAY, I8),
+                PdoEntry::new("padding", 0, U8),
+            ],
+            vec![
+                PdoEntry::new("controlword", CONTROLWORD, U16),
+                PdoEntry::new("target position", TARGET_POSITION, I32),
+                PdoEntry::new("target velocity", TARGET_VELOCITY, I32),
+                PdoEntry::new("target torque", TARGET_TORQUE, I16),
+                PdoEntry::new("op mode", OPMODE, I8),
+                PdoEntry::new("padding", 0, U8),
+            ],
+        )
+        .expect("invalid default pdo layout")
+    }
+}
+
+impl PdoLayout {
+    fn new(mut inputs: Vec<PdoEntry>, mut outputs: Vec<PdoEntry>) -> Result<Self, String> {
+        for entries in [&mut inputs, &mut outputs] {
+            let mut offset = 0;
+            for entry in entries.iter_mut() {
+                entry.offset = offset;
+                offset += entry.ty.size();
+            }
+        }
+
+        let layout = Self { inputs, outputs };
+        if layout.input(STATUSWORD).is_none() {
+            return Err("the statusword (0x6041) has to be mapped to the inputs".into());
+        }
+        if layout.output(CONTROLWORD).is_none() {
+            return Err("the controlword (0x6040) has to be mapped to the outputs".into());
+        }
+        // switching modes waits for the drive to show the mode it was sent
+        if layout.input(OPMODE_DISPLAY).is_none() {
+            return Err("the op mode display (0x6061) has to be mapped to the inputs".into());
+        }
+        if layout.output(OPMODE).is_none() {
+            return Err("the op mode (0x6060) has to be mapped to the outputs".into());
+        }
+        Ok(layout)
+    }
+
+    pub(crate) fn input_len(&self) -> usize {
+        self.inputs.iter().map(|e| e.ty.size()).sum()
+    }
+
+    pub(crate) fn output_len(&self) -> usize {
+        self.outputs.iter().map(|e| e.ty.size()).sum()
+    }
+
+    // what the subdevice adds to the working counter of the logical read write carrying its
+    // process data, 1 for reading its inputs and 2 for writing its outputs.
+    pub(crate) fn expected_wkc(&self) -> u16 {
+        let inputs = if self.input_len() > 0 { 1 } else { 0 };
+        let outputs = if self.output_len() > 0 { 2 } else { 0 };
+        inputs + outputs
+    }
+
+    fn input(&self, index: u16) -> Option<&PdoEntry> {
+        self.inputs.iter().find(|e| e.index == index)
+    }
+
+    fn output(&self, index: u16) -> Option<&PdoEntry> {
+        self.outputs.iter().find(|e| e.index == index)
+    }
+
+    // the value of the input object at `index`, zero when it is not mapped or not in the image.
+    pub(crate) fn read(&self, image: &[u8], index: u16) -> i64 {
+        self.input(index).and_then(|e| e.read(image)).unwrap_or(0)
+    }
+
+    // writes `value` to the output object at `index`, nothing happens when it is not mapped or
+    // not in the image.
+    pub(crate) fn write(&self, image: &mut [u8], index: u16, value: i64) {
+        let Some(e) = self.output(index) else {
+            return;
+        };
+        if let Some(bytes) = image.get_mut(e.offset..e.offset + e.ty.size()) {
+            e.ty.encode(value, bytes);
+        }
+    }
+
+    // `Some` when the process image the subdevice was given is shorter than the layout maps.
+    pub(crate) fn check(&self, inputs: &[u8], outputs: &[u8]) -> Option<ImageMismatch> {
+        let mismatch = ImageMismatch {
+            inputs: inputs.len(),
+            expected_inputs: self.input_len(),
+            outputs: outputs.len(),
+            expected_outputs: self.output_len(),
+        };
+        (mismatch.inputs < mismatch.expected_inputs || mismatch.outputs < mismatch.expected_outputs)
+            .then_some(mismatch)
+    }
+
+    pub(crate) fn extra_inputs<'a>(
+        &'a self,
+        image: &'a [u8],
+    ) -> impl Iterator<Item = (&'a str, i64)> + 'a {
+        self.inputs
+            .iter()
+            .filter(|e| e.is_extra_input())
+            .filter_map(|e| Some((e.name.as_str(), e.read(image)?)))
+    }
+
+    pub(crate) fn has_extra_inputs(&self) -> bool {
+        self.inputs.iter().any(|e| e.is_extra_input())
+    }
+
+    pub(crate) fn objects(&self) -> (Vec<PdoObject>, Vec<PdoObject>) {
+        let objects = |entries: &[PdoEntry]| {
+            entries
+                .iter()
+                .map(|e| e.ty.object(e.index, e.subindex))
+                .collect()
+        };
+        (objects(&self.inputs), objects(&self.outputs))
+    }
+
+    fn parse(value: &serde_json::Value) -> Result<Self, String> {
+        let entries = |key: &str| -> Result<Vec<PdoEntry>, String> {
+            let list = value
+                .get(key)
+                .and_then(|list| list.as_array())
+                .ok_or_else(|| format!("missing `{key}` list"))?;
+            list.iter().map(parse_entry).collect()
+        };
+
+        Self::new(entries("inputs")?, entries("outputs")?)
+    }
+}
+
+// an entry looks like `{ "name": "following error", "index": "0x60F4", "subindex": 0, "type": "i32" }`,
+// the subindex may be left out.
+pub(crate) fn parse_entry(entry: &serde_json::Value) -> Result<PdoEntry, String> {
+    let index = match entry.get("index") {
+        Some(serde_json::Value::String(s)) => {
+            let hex = s.trim_start_matches("0x").trim_start_matches("0X");
+            u16::from_str_radix(hex, 16).ok()
+        }
+        Some(index) => index.as_u64().and_then(|i| u16::try_from(i).ok()),
+        None => None,
+    }
+    .ok_or_else(|| format!("invalid or missing index in {entry}"))?;
+
+    let subindex = match entry.get("subindex") {
+        Some(subindex) => subindex
+            .as_u64()
+            .and_then(|s| u8::try_from(s).ok())
+            .ok_or_else(|| format!("invalid subindex in {entry}"))?,
+        None => 0,
+    };
+
+    let ty = entry
+        .get("type")
+        .and_then(|ty| ty.as_str())
+        .and_then(PdoType::parse)
+        .ok_or_else(|| {
+            format!("invalid or missing type in {entry}, expected u8, i8, u16, i16, u32 or i32")
+        })?;
+
+    let name = match entry.get("name").and_then(|name| name.as_str()) {
+        Some(name) => name.to_owned(),
+        None => format!("{index:#06x}:{subindex}"),
+    };
+
+    Ok(PdoEntry {
+        name,
+        index,
+        subindex,
+        ty,
+        offset: 0,
+    })
+}
+
+// pdo layouts by subdevice name, with `default` for every device that is not listed.
+#[derive(Clone, Debug, Default)]
+pub struct PdoLayouts {
+    default: PdoLayout,
+    devices: Vec<(String, PdoLayout)>,
+}
+
+impl PdoLayouts {
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let json = serde_json::from_slice::<serde_json::Value>(&file)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+
+        let serde_json::Value::Object(devices) = json else {
+            return Err(format!("{}: expected an object of layouts", path.display()));
+        };
+
+        let mut layouts = Self::default();
+        for (name, layout) in &devices {
+            let layout = PdoLayout::parse(layout).map_err(|e| format!("{name}: {e}"))?;
+            if name == "default" {
+                layouts.default = layout;
+            } else {
+                layouts.devices.push((name.clone(), layout));
+            }
+        }
+        Ok(layouts)
+    }
+
+    // every distinct layout, the default first.
+    pub(crate) fn layouts(&self) -> impl Iterator<Item = &PdoLayout> {
+        std::iter::once(&self.default).chain(self.devices.iter().map(|(_, layout)| layout))
+    }
+
+    // the position of the device's layout within `layouts`.
+    pub(crate) fn position(&self, device_name: &str) -> usize {
+        self.devices
+            .iter()
+            .position(|(name, _)| name == device_name)
+            .map_or(0, |i| i + 1)
+    }
+}
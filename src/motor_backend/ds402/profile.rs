@@ -0,0 +1,242 @@
+use super::sdo::SdoAddr;
+use super::{Ds402Cmd, Ds402Response, Ds402Units};
+
+use std::sync::mpsc;
+
+const PROFILE_ACCELERATION: SdoAddr = SdoAddr::new(0x6083, 0);
+const PROFILE_DECELERATION: SdoAddr = SdoAddr::new(0x6084, 0);
+const QUICK_STOP_DECELERATION: SdoAddr = SdoAddr::new(0x6085, 0);
+const MAX_PROFILE_VELOCITY: SdoAddr = SdoAddr::new(0x607F, 0);
+const MIN_POSITION_LIMIT: SdoAddr = SdoAddr::new(0x607D, 1);
+const MAX_POSITION_LIMIT: SdoAddr = SdoAddr::new(0x607D, 2);
+const MAX_TORQUE: SdoAddr = SdoAddr::new(0x6072, 0);
+
+// the drive's own motion limits, in its units. parameters that are `None` keep the value the
+// drive already has.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct ProfileParams {
+    pub acceleration: Option<u32>,
+    pub deceleration: Option<u32>,
+    pub quick_stop_deceleration: Option<u32>,
+    pub max_velocity: Option<u32>,
+    pub min_position: Option<i32>,
+    pub max_position: Option<i32>,
+    // per thousand of the rated torque
+    pub max_torque: Option<u16>,
+}
+
+impl ProfileParams {
+    pub(crate) fn writes(&self) -> Vec<(SdoAddr, Vec<u8>)> {
+        let u32s = [
+            (PROFILE_ACCELERATION, self.acceleration),
+            (PROFILE_DECELERATION, self.deceleration),
+            (QUICK_STOP_DECELERATION, self.quick_stop_deceleration),
+            (MAX_PROFILE_VELOCITY, self.max_velocity),
+        ];
+        let i32s = [
+            (MIN_POSITION_LIMIT, self.min_position),
+            (MAX_POSITION_LIMIT, self.max_position),
+        ];
+
+        let mut writes = Vec::new();
+        for (addr, value) in u32s {
+            if let Some(value) = value {
+                writes.push((addr, value.to_le_bytes().into()));
+            }
+        }
+        for (addr, value) in i32s {
+            if let Some(value) = value {
+                writes.push((addr, value.to_le_bytes().into()));
+            }
+        }
+        if let Some(value) = self.max_torque {
+            writes.push((MAX_TORQUE, value.to_le_bytes().into()));
+        }
+        writes
+    }
+}
+
+// the profile parameters of a motor as entered at the output, and how writing them last went.
+#[derive(Default)]
+pub struct ProfilePanel {
+    acceleration_storage: String,
+    deceleration_storage: String,
+    quick_stop_deceleration_storage: String,
+    max_velocity_storage: String,
+    min_position_storage: String,
+    max_position_storage: String,
+    max_torque_storage: String,
+    // writes that have not been answered yet
+    pending: usize,
+    errors: Vec<String>,
+}
+
+impl ProfilePanel {
+    // converted to the drive's units.
+    fn params(&self, units: &Ds402Units) -> Result<ProfileParams, String> {
+        // an empty field keeps the drive's value
+        fn parse<T>(
+            name: &str,
+            s: &str,
+            convert: impl Fn(f64) -> Option<T>,
+        ) -> Result<Option<T>, String> {
+            match s.trim() {
+                "" => Ok(None),
+                s => s
+                    .parse()
+                    .ok()
+                    .and_then(convert)
+                    .map(Some)
+                    .ok_or_else(|| format!("invalid {name}: {s}")),
+            }
+        }
+
+        let acceleration = |a| units.acceleration_param(a);
+        let params = ProfileParams {
+            acceleration: parse(
+                "profile acceleration",
+                &self.acceleration_storage,
+                acceleration,
+            )?,
+            deceleration: parse(
+                "profile deceleration",
+                &self.deceleration_storage,
+                acceleration,
+            )?,
+            quick_stop_deceleration: parse(
+                "quick stop deceleration",
+                &self.quick_stop_deceleration_storage,
+                acceleration,
+            )?,
+            max_velocity: parse("max profile velocity", &self.max_velocity_storage, |v| {
+                units.velocity_param(v)
+            })?,
+            min_position: parse("min position limit", &self.min_position_storage, |p| {
+                units.position_param(p)
+            })?,
+            max_position: parse("max position limit", &self.max_position_storage, |p| {
+                units.position_param(p)
+            })?,
+            max_torque: parse("max torque", &self.max_torque_storage, |t| {
+                units.torque_param(t)
+            })?,
+        };
+
+        match (params.min_position, params.max_position) {
+            (Some(min), Some(max)) if min > max => Err(format!(
+                "min position limit {min} is above the max position limit {max}"
+            )),
+            _ => Ok(params),
+        }
+    }
+
+    // sends the parameters to the drive, unless one of them does not parse.
+    pub(crate) fn apply(
+        &mut self,
+        idx: usize,
+        tx: &mpsc::Sender<(usize, Ds402Cmd)>,
+        units: &Ds402Units,
+    ) -> bool {
+        match self.params(units) {
+            Ok(params) => {
+                self.pending = params.writes().len();
+                self.errors.clear();
+                let _ = tx.send((idx, Ds402Cmd::SetProfile(params)));
+                true
+            }
+            Err(e) => {
+                self.pending = 0;
+                self.errors = vec![e];
+                false
+            }
+        }
+    }
+
+    // writes that are still out.
+    pub(crate) fn writing(&self) -> bool {
+        self.pending > 0
+    }
+
+    // whether a parameter did not parse or could not be written.
+    pub(crate) fn failed(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    // the writes still out are never answered once the backend is gone.
+    pub(crate) fn forget(&mut self) {
+        self.pending = 0;
+    }
+
+    pub(crate) fn update(&mut self, response: &Ds402Response) {
+        if let Ds402Response::ProfileWritten(addr, result) = response {
+            self.pending = self.pending.saturating_sub(1);
+            if let Err(abort) = result {
+                self.errors.push(format!("could not write {addr}: {abort}"));
+            }
+        }
+    }
+
+    pub(crate) fn display(
+        &mut self,
+        idx: usize,
+        tx: &mpsc::Sender<(usize, Ds402Cmd)>,
+        added: bool,
+        // `None` while the units do not parse
+        units: Option<&Ds402Units>,
+        ui: &mut egui::Ui,
+    ) {
+        // converted with the motor's units, the drive's own units pass through as they are
+        let title = "profile parameters (empty keeps the drive's value)";
+        ui.collapsing(title, |ui| {
+            egui::Grid::new(("profile", idx)).show(ui, |ui| {
+                for (label, storage) in [
+                    (
+                        "profile acceleration, rad/s² (0x6083)",
+                        &mut self.acceleration_storage,
+                    ),
+                    (
+                        "profile deceleration, rad/s² (0x6084)",
+                        &mut self.deceleration_storage,
+                    ),
+                    (
+                        "quick stop deceleration, rad/s² (0x6085)",
+                        &mut self.quick_stop_deceleration_storage,
+                    ),
+                    (
+                        "max profile velocity, rad/s (0x607F)",
+                        &mut self.max_velocity_storage,
+                    ),
+                    (
+                        "min position limit, rad (0x607D:1)",
+                        &mut self.min_position_storage,
+                    ),
+                    (
+                        "max position limit, rad (0x607D:2)",
+                        &mut self.max_position_storage,
+                    ),
+                    ("max torque, Nm (0x6072)", &mut self.max_torque_storage),
+                ] {
+                    ui.label(label);
+                    ui.text_edit_singleline(storage);
+                    ui.end_row();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                // the parameters are applied along with adding the motor, and by hand after
+                if added {
+                    let apply = ui.add_enabled(units.is_some(), egui::Button::new("apply"));
+                    if let Some(units) = units.filter(|_| apply.clicked()) {
+                        self.apply(idx, tx, units);
+                    }
+                }
+                if self.pending > 0 {
+                    ui.label("writing...");
+                }
+            });
+            for e in &self.errors {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+        });
+    }
+}
@@ -0,0 +1,129 @@
+use ecat::{RegisterRead, RegisterWrite, TxBuf};
+use ethercrab::std::RawSocketDesc;
+use ethercrab::{MainDevice, SubDevice, error::Error};
+use io_uring::types::Timespec;
+use std::collections::{BTreeMap, VecDeque};
+
+// esc registers, read and written by configured address.
+pub(crate) const AL_STATUS: u16 = 0x0130;
+// al status and al status code (0x0134), read in one go.
+pub(crate) const AL_STATUS_LEN: u16 = 6;
+pub(crate) const DC_SYSTEM_TIME: u16 = 0x0910;
+pub(crate) const DC_SYSTEM_TIME_DIFFERENCE: u16 = 0x092C;
+pub(crate) const DC_SYNC_ACTIVATION: u16 = 0x0981;
+pub(crate) const DC_SYNC0_START_TIME: u16 = 0x0990;
+pub(crate) const DC_SYNC0_CYCLE_TIME: u16 = 0x09A0;
+
+// what the result of a register access is needed for.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum RegisterPurpose {
+    AlStatus,
+    SystemTimeDifference,
+    // the subdevice's system time, which the sync0 start time is derived from
+    Sync0SystemTime,
+    // writes whose result is of no interest beyond succeeding
+    Configure,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct RegisterRequest {
+    pub register: u16,
+    pub len: u16,
+    // `None` reads the register
+    pub write: Option<Vec<u8>>,
+    pub purpose: RegisterPurpose,
+}
+
+// the register accesses of one subdevice, one at a time and in the order they were queued.
+#[derive(Default)]
+pub(crate) struct RegisterQueue {
+    queued: VecDeque<RegisterRequest>,
+    in_flight: Option<RegisterRequest>,
+}
+
+impl RegisterQueue {
+    pub fn read(&mut self, register: u16, len: u16, purpose: RegisterPurpose) {
+        self.queued.push_back(RegisterRequest {
+            register,
+            len,
+            write: None,
+            purpose,
+        });
+    }
+
+    pub fn write(&mut self, register: u16, data: Vec<u8>) {
+        self.queued.push_back(RegisterRequest {
+            register,
+            len: data.len() as u16,
+            write: Some(data),
+            purpose: RegisterPurpose::Configure,
+        });
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.in_flight.is_none() && self.queued.is_empty()
+    }
+
+    // starts the next access once the previous one has completed, called once per process
+    // data cycle like the sdo queue.
+    #[allow(clippy::too_many_arguments)]
+    pub fn poll(
+        &mut self,
+        maindevice: &MainDevice,
+        retry_count: usize,
+        timeout_duration: &Timespec,
+        tx_entries: &mut BTreeMap<u64, TxBuf>,
+        sock: &RawSocketDesc,
+        ring: &mut io_uring::IoUring,
+        subdev: &mut SubDevice,
+        idx: u16,
+        write_entry: impl Fn(u64) -> u64,
+    ) -> Result<(), Error> {
+        if self.in_flight.is_some() {
+            return Ok(());
+        }
+        let Some(request) = self.queued.pop_front() else {
+            return Ok(());
+        };
+
+        match &request.write {
+            None => RegisterRead::new(request.register, request.len).start(
+                subdev,
+                maindevice,
+                retry_count,
+                timeout_duration,
+                tx_entries,
+                sock,
+                ring,
+                idx,
+                write_entry,
+            )?,
+            Some(data) => RegisterWrite::new(request.register, data).start(
+                subdev,
+                maindevice,
+                retry_count,
+                timeout_duration,
+                tx_entries,
+                sock,
+                ring,
+                idx,
+                write_entry,
+            )?,
+        }
+
+        self.in_flight = Some(request);
+        Ok(())
+    }
+
+    // the access in flight along with the data read, empty for a write.
+    pub fn complete(&mut self, data: &[u8]) -> Option<(RegisterRequest, Vec<u8>)> {
+        let request = self.in_flight.take()?;
+        Some((request, data.to_vec()))
+    }
+
+    // the frame of the access in flight went unanswered through all its retries, the next
+    // access can start.
+    pub fn time_out(&mut self) -> Option<RegisterRequest> {
+        self.in_flight.take()
+    }
+}
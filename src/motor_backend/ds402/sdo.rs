@@ -0,0 +1,173 @@
+use ecat::{SdoRead, SdoWrite, TxBuf};
+use ethercrab::std::RawSocketDesc;
+use ethercrab::{MainDevice, SubDevice, error::Error};
+use io_uring::types::Timespec;
+use std::collections::{BTreeMap, VecDeque};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct SdoAddr {
+    pub index: u16,
+    pub subindex: u8,
+}
+
+impl SdoAddr {
+    pub const fn new(index: u16, subindex: u8) -> Self {
+        Self { index, subindex }
+    }
+}
+
+impl core::fmt::Display for SdoAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:#06x}:{}", self.index, self.subindex)
+    }
+}
+
+// what the result of a transfer is needed for.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum SdoPurpose {
+    Fault(super::fault::FaultField),
+    // requested from the object dictionary browser, the result goes to the ui as is
+    Browser,
+    // the parameters of a homing run, which starts once all of them are written
+    Homing,
+    // profile parameters and limits, applied as they are written
+    Profile,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SdoRequest {
+    pub addr: SdoAddr,
+    // `None` reads the object
+    pub write: Option<Vec<u8>>,
+    pub purpose: SdoPurpose,
+}
+
+// the drive refused the transfer, with its CiA301 abort code.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct SdoAbort(pub u32);
+
+impl SdoAbort {
+    // also reported for transfers whose frames went unanswered
+    pub const TIMED_OUT: Self = Self(0x0504_0000);
+}
+
+impl core::fmt::Display for SdoAbort {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let reason = match self.0 {
+            0x0503_0000 => "toggle bit not alternated",
+            0x0504_0000 => "sdo protocol timed out",
+            0x0504_0005 => "out of memory",
+            0x0601_0000 => "unsupported access to the object",
+            0x0601_0001 => "object is write only",
+            0x0601_0002 => "object is read only",
+            0x0602_0000 => "object does not exist",
+            0x0604_0041 => "object cannot be mapped to a pdo",
+            0x0606_0000 => "access failed due to a hardware error",
+            0x0607_0010 | 0x0607_0012 | 0x0607_0013 => "data type or length does not match",
+            0x0609_0011 => "subindex does not exist",
+            0x0609_0030 => "value out of range",
+            0x0609_0031 => "value too high",
+            0x0609_0032 => "value too low",
+            0x0800_0000 => "general error",
+            0x0800_0020 => "data cannot be transferred or stored",
+            0x0800_0021 => "data cannot be transferred because of local control",
+            0x0800_0022 => "data cannot be transferred in the present device state",
+            _ => "unknown abort code",
+        };
+        write!(f, "{reason} ({:#010x})", self.0)
+    }
+}
+
+// the mailbox transfers of one subdevice, one at a time and in the order they were queued.
+#[derive(Default)]
+pub(crate) struct SdoQueue {
+    queued: VecDeque<SdoRequest>,
+    in_flight: Option<SdoRequest>,
+}
+
+impl SdoQueue {
+    pub fn read(&mut self, addr: SdoAddr, purpose: SdoPurpose) {
+        self.queued.push_back(SdoRequest {
+            addr,
+            write: None,
+            purpose,
+        });
+    }
+
+    pub fn write(&mut self, addr: SdoAddr, data: Vec<u8>, purpose: SdoPurpose) {
+        self.queued.push_back(SdoRequest {
+            addr,
+            write: Some(data),
+            purpose,
+        });
+    }
+
+    // starts the next transfer once the previous one has completed. the mailbox frame goes out
+    // alongside the cyclic pdi, so this is called once per process data cycle.
+    #[allow(clippy::too_many_arguments)]
+    pub fn poll(
+        &mut self,
+        maindevice: &MainDevice,
+        retry_count: usize,
+        timeout_duration: &Timespec,
+        tx_entries: &mut BTreeMap<u64, TxBuf>,
+        sock: &RawSocketDesc,
+        ring: &mut io_uring::IoUring,
+        subdev: &mut SubDevice,
+        idx: u16,
+        write_entry: impl Fn(u64) -> u64,
+    ) -> Result<(), Error> {
+        if self.in_flight.is_some() {
+            return Ok(());
+        }
+        let Some(request) = self.queued.pop_front() else {
+            return Ok(());
+        };
+
+        let SdoAddr { index, subindex } = request.addr;
+        match &request.write {
+            None => SdoRead::new(index, subindex).start(
+                subdev,
+                maindevice,
+                retry_count,
+                timeout_duration,
+                tx_entries,
+                sock,
+                ring,
+                idx,
+                write_entry,
+            )?,
+            Some(data) => SdoWrite::new(index, subindex, data).start(
+                subdev,
+                maindevice,
+                retry_count,
+                timeout_duration,
+                tx_entries,
+                sock,
+                ring,
+                idx,
+                write_entry,
+            )?,
+        }
+
+        self.in_flight = Some(request);
+        Ok(())
+    }
+
+    // the transfer in flight along with its result, the data read (empty for a write) or the
+    // abort code.
+    pub fn complete(
+        &mut self,
+        result: Result<&[u8], u32>,
+    ) -> Option<(SdoRequest, Result<Vec<u8>, SdoAbort>)> {
+        let request = self.in_flight.take()?;
+        Some((request, result.map(<[u8]>::to_vec).map_err(SdoAbort)))
+    }
+
+    // a frame of the transfer in flight went unanswered through all its retries, the next
+    // transfer can start.
+    pub fn time_out(&mut self) -> Option<(SdoRequest, Result<Vec<u8>, SdoAbort>)> {
+        let request = self.in_flight.take()?;
+        Some((request, Err(SdoAbort::TIMED_OUT)))
+    }
+}
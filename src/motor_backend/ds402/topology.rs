@@ -0,0 +1,428 @@
+use super::{Ds402Cmd, Ds402Response, ImageMismatch, SubdeviceIdentity, dc};
+
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+// how often the al status of each subdevice and the bus working counter are reported.
+pub(crate) const STATUS_INTERVAL: Duration = Duration::from_millis(500);
+
+// the command code of the logical read write that carries the process data.
+pub(crate) const LRW_COMMAND: u8 = 0x0C;
+// the ethernet and ethercat headers in front of a frame's first datagram.
+const DATAGRAM_OFFSET: usize = 16;
+// the esc registers end where its process memory, which holds the mailboxes, begins.
+const PROCESS_MEMORY: u16 = 0x1000;
+
+// what a frame sent to one subdevice carried, going by its first datagram.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum FrameKind {
+    Pdi,
+    Register,
+    Mailbox,
+}
+
+impl FrameKind {
+    pub(crate) fn of(frame: &[u8]) -> Option<Self> {
+        // command, index, configured address and offset
+        let header = frame.get(DATAGRAM_OFFSET..DATAGRAM_OFFSET + 6)?;
+        if header[0] == LRW_COMMAND {
+            return Some(Self::Pdi);
+        }
+        let offset = u16::from_le_bytes([header[4], header[5]]);
+        Some(if offset < PROCESS_MEMORY {
+            Self::Register
+        } else {
+            Self::Mailbox
+        })
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AlState {
+    Init,
+    PreOp,
+    Bootstrap,
+    SafeOp,
+    Op,
+    Unknown(u8),
+}
+
+impl AlState {
+    fn from_register(status: u16) -> Self {
+        match status & 0x0F {
+            1 => Self::Init,
+            2 => Self::PreOp,
+            3 => Self::Bootstrap,
+            4 => Self::SafeOp,
+            8 => Self::Op,
+            state => Self::Unknown(state as u8),
+        }
+    }
+}
+
+impl core::fmt::Display for AlState {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Init => write!(f, "init"),
+            Self::PreOp => write!(f, "pre-op"),
+            Self::Bootstrap => write!(f, "bootstrap"),
+            Self::SafeOp => write!(f, "safe-op"),
+            Self::Op => write!(f, "op"),
+            Self::Unknown(state) => write!(f, "unknown ({state:#x})"),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct SubdeviceStatus {
+    pub al_state: AlState,
+    // the subdevice refused a state change, `al_status_code` tells why
+    pub error: bool,
+    pub al_status_code: u16,
+}
+
+impl SubdeviceStatus {
+    pub(crate) fn decode(registers: &[u8]) -> Option<Self> {
+        let status = u16::from_le_bytes([*registers.first()?, *registers.get(1)?]);
+        let code = u16::from_le_bytes([*registers.get(4)?, *registers.get(5)?]);
+        Some(Self {
+            al_state: AlState::from_register(status),
+            error: status & 0x10 != 0,
+            al_status_code: code,
+        })
+    }
+}
+
+pub fn describe_al_status_code(code: u16) -> &'static str {
+    match code {
+        0x0000 => "no error",
+        0x0001 => "unspecified error",
+        0x0002 => "no memory",
+        0x0011 => "invalid requested state change",
+        0x0012 => "unknown requested state",
+        0x0013 => "bootstrap not supported",
+        0x0014 => "no valid firmware",
+        0x0015 => "invalid mailbox configuration (bootstrap)",
+        0x0016 => "invalid mailbox configuration (pre-op)",
+        0x0017 => "invalid sync manager configuration",
+        0x0018 => "no valid inputs available",
+        0x0019 => "no valid outputs",
+        0x001A => "synchronization error",
+        0x001B => "sync manager watchdog",
+        0x001C => "invalid sync manager types",
+        0x001D => "invalid output configuration",
+        0x001E => "invalid input configuration",
+        0x001F => "invalid watchdog configuration",
+        0x0020 => "subdevice needs cold start",
+        0x0021 => "subdevice needs init",
+        0x0022 => "subdevice needs pre-op",
+        0x0023 => "subdevice needs safe-op",
+        0x0024 => "invalid input mapping",
+        0x0025 => "invalid output mapping",
+        0x0026 => "inconsistent settings",
+        0x0027 => "free run not supported",
+        0x0028 => "sync mode not supported",
+        0x0029 => "free run needs 3 buffer mode",
+        0x002A => "background watchdog",
+        0x002B => "no valid inputs and outputs",
+        0x002C => "fatal sync error",
+        0x002D => "no sync error",
+        0x0030 => "invalid dc sync configuration",
+        0x0031 => "invalid dc latch configuration",
+        0x0032 => "pll error",
+        0x0033 => "dc sync io error",
+        0x0034 => "dc sync timeout error",
+        0x0035 => "dc invalid sync cycle time",
+        0x0036 => "dc sync0 cycle time",
+        0x0037 => "dc sync1 cycle time",
+        0x0041 => "mailbox aoe",
+        0x0042 => "mailbox eoe",
+        0x0043 => "mailbox coe",
+        0x0044 => "mailbox foe",
+        0x0045 => "mailbox soe",
+        0x004F => "mailbox voe",
+        0x0050 => "eeprom no access",
+        0x0051 => "eeprom error",
+        0x0060 => "subdevice restarted locally",
+        _ => "unknown al status code",
+    }
+}
+
+// the working counter of the cyclic logical read write, over the last status interval.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct BusStatus {
+    pub subdevices: usize,
+    pub expected_wkc: u16,
+    pub last_wkc: Option<u16>,
+    pub cycles: u32,
+    // cycles whose working counter was not the expected one
+    pub wkc_errors: u32,
+    // the average time between two cycles
+    pub period: Option<Duration>,
+}
+
+impl BusStatus {
+    pub fn healthy(&self) -> bool {
+        self.cycles > 0 && self.wkc_errors == 0
+    }
+}
+
+// counts process data cycles and working counter mismatches between two reports.
+pub(crate) struct WkcMonitor {
+    status: BusStatus,
+    since: Instant,
+}
+
+impl WkcMonitor {
+    pub fn new() -> Self {
+        Self {
+            status: BusStatus::default(),
+            since: Instant::now(),
+        }
+    }
+
+    // `expected` is what the subdevices in the pdu add to the working counter.
+    pub fn record(&mut self, wkc: u16, subdevices: usize, expected: u16) {
+        let status = &mut self.status;
+        status.subdevices = subdevices;
+        status.expected_wkc = expected;
+        status.cycles += 1;
+        status.last_wkc = Some(wkc);
+        if wkc != status.expected_wkc {
+            status.wkc_errors += 1;
+        }
+    }
+
+    // the status of the interval that just ended, once one has.
+    pub fn report(&mut self) -> Option<BusStatus> {
+        if self.since.elapsed() < STATUS_INTERVAL {
+            return None;
+        }
+        let mut status = std::mem::take(&mut self.status);
+        status.period = (status.cycles > 0).then(|| self.since.elapsed() / status.cycles);
+        self.since = Instant::now();
+        Some(status)
+    }
+}
+
+// sync0 pulses every cycle, so the cycle has to fit its register.
+fn check_cycle(period: Duration) -> Result<Duration, String> {
+    if period.is_zero() {
+        Err("the cycle time cannot be zero".into())
+    } else if period > dc::MAX_CYCLE {
+        Err(format!("the cycle time can be at most {:?}", dc::MAX_CYCLE))
+    } else {
+        Ok(period)
+    }
+}
+
+// every subdevice on the bus and its last reported state, as shown in the topology view.
+#[derive(Default)]
+pub struct Topology {
+    identities: BTreeMap<usize, SubdeviceIdentity>,
+    statuses: BTreeMap<usize, SubdeviceStatus>,
+    bus: Option<BusStatus>,
+    // the last system time difference of every subdevice with sync0 enabled
+    dc_differences: BTreeMap<usize, i64>,
+    // subdevices whose process image does not fit their pdo layout
+    image_mismatches: BTreeMap<usize, ImageMismatch>,
+    // the last register of each subdevice an access to went unanswered
+    register_timeouts: BTreeMap<usize, u16>,
+    cycle_storage: String,
+    // why the cycle time entered was not used
+    cycle_error: Option<String>,
+    // whether sync0 is enabled for a subdevice, and the shift entered for it
+    sync0: BTreeMap<usize, (bool, String)>,
+    // why the shift entered for a subdevice was not used
+    sync0_errors: BTreeMap<usize, String>,
+}
+
+impl Topology {
+    pub fn identity(&self, idx: usize) -> Option<&SubdeviceIdentity> {
+        self.identities.get(&idx)
+    }
+
+    // whether the response was about the bus rather than a motor.
+    pub(crate) fn update(&mut self, idx: usize, response: &Ds402Response) -> bool {
+        match response {
+            Ds402Response::Identity(identity) => {
+                // the bus came up again
+                self.register_timeouts.remove(&idx);
+                self.identities.insert(idx, identity.clone());
+            }
+            Ds402Response::SubdeviceStatus(status) => {
+                self.statuses.insert(idx, *status);
+            }
+            Ds402Response::Bus(status) => self.bus = Some(*status),
+            Ds402Response::SystemTimeDifference(difference) => {
+                self.dc_differences.insert(idx, *difference);
+            }
+            Ds402Response::ImageMismatch(Some(mismatch)) => {
+                self.image_mismatches.insert(idx, *mismatch);
+            }
+            Ds402Response::ImageMismatch(None) => {
+                self.image_mismatches.remove(&idx);
+            }
+            Ds402Response::RegisterTimedOut(register) => {
+                self.register_timeouts.insert(idx, *register);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // the subdevice picked for one of the `motors` (ids of ds402 motors that are not added),
+    // along with the motor.
+    pub(crate) fn display(
+        &mut self,
+        tx: &mpsc::Sender<(usize, Ds402Cmd)>,
+        motors: &[usize],
+        ui: &mut egui::Ui,
+    ) -> Option<(usize, usize)> {
+        let mut picked = None;
+
+        ui.horizontal(|ui| {
+            ui.label("cycle time (empty to free run):");
+            if ui.text_edit_singleline(&mut self.cycle_storage).changed() {
+                let period = match self.cycle_storage.as_str() {
+                    "" => Ok(None),
+                    s => s
+                        .parse::<duration_string::DurationString>()
+                        .map_err(|e| e.to_string())
+                        .map(Duration::from)
+                        .and_then(check_cycle)
+                        .map(Some),
+                };
+                match period {
+                    Ok(period) => {
+                        self.cycle_error = None;
+                        let _ = tx.send((0, Ds402Cmd::SetCycleTime(period)));
+                    }
+                    Err(e) => self.cycle_error = Some(e),
+                }
+            }
+            if let Some(e) = &self.cycle_error {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            if let Some(period) = self.bus.and_then(|bus| bus.period) {
+                ui.label(format!("measured cycle: {period:?}"));
+            }
+        });
+
+        if let Some(bus) = &self.bus {
+            let text = format!(
+                "{} subdevices, working counter {} of {} expected, {} of {} cycles wrong",
+                bus.subdevices,
+                bus.last_wkc.map_or("-".into(), |wkc| wkc.to_string()),
+                bus.expected_wkc,
+                bus.wkc_errors,
+                bus.cycles,
+            );
+            if bus.healthy() {
+                ui.label(text);
+            } else {
+                ui.colored_label(egui::Color32::RED, text);
+            }
+        }
+
+        egui::Grid::new("ethercat topology")
+            .striped(true)
+            .show(ui, |ui| {
+                for header in [
+                    "position",
+                    "address",
+                    "name",
+                    "vendor",
+                    "product",
+                    "revision",
+                    "al state",
+                    "sync0 shift",
+                    "dc difference",
+                    "",
+                ] {
+                    ui.label(header);
+                }
+                ui.end_row();
+
+                for (idx, identity) in &self.identities {
+                    ui.label(idx.to_string());
+                    ui.label(format!("{:#06x}", identity.configured_address));
+                    ui.label(&identity.name);
+                    ui.label(format!("{:#010x}", identity.vendor_id));
+                    ui.label(format!("{:#010x}", identity.product_code));
+                    ui.label(format!("{:#010x}", identity.revision));
+
+                    match self.statuses.get(idx) {
+                        Some(status) if status.error => ui.colored_label(
+                            egui::Color32::RED,
+                            format!(
+                                "{} ({:#06x}: {})",
+                                status.al_state,
+                                status.al_status_code,
+                                describe_al_status_code(status.al_status_code)
+                            ),
+                        ),
+                        Some(status) => ui.label(status.al_state.to_string()),
+                        None => ui.label("-"),
+                    };
+
+                    let (enabled, shift) = self.sync0.entry(*idx).or_default();
+                    ui.horizontal(|ui| {
+                        let toggled = ui.checkbox(enabled, "").changed();
+                        let edited = ui.text_edit_singleline(shift).changed();
+                        if toggled || (edited && *enabled) {
+                            let shift = match shift.as_str() {
+                                "" => Ok(Duration::ZERO),
+                                s => s
+                                    .parse::<duration_string::DurationString>()
+                                    .map_err(|e| e.to_string())
+                                    .map(Duration::from),
+                            };
+                            match shift {
+                                Ok(shift) => {
+                                    self.sync0_errors.remove(idx);
+                                    let shift = enabled.then_some(shift);
+                                    let _ = tx.send((*idx, Ds402Cmd::SetSync0(shift)));
+                                }
+                                Err(e) => {
+                                    self.sync0_errors.insert(*idx, e);
+                                }
+                            }
+                        }
+                        if let Some(e) = self.sync0_errors.get(idx) {
+                            ui.colored_label(egui::Color32::RED, e);
+                        }
+                    });
+                    match self.dc_differences.get(idx).filter(|_| *enabled) {
+                        Some(difference) => ui.label(format!("{difference} ns")),
+                        None => ui.label("-"),
+                    };
+
+                    ui.add_enabled_ui(!motors.is_empty(), |ui| {
+                        ui.menu_button("use for motor", |ui| {
+                            for motor in motors {
+                                if ui.button(format!("motor {motor}")).clicked() {
+                                    picked = Some((*idx, *motor));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                    ui.end_row();
+                }
+            });
+
+        for (idx, mismatch) in &self.image_mismatches {
+            ui.colored_label(egui::Color32::RED, format!("subdevice {idx}: {mismatch}"));
+        }
+        for (idx, register) in &self.register_timeouts {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("subdevice {idx}: register {register:#06x} did not answer"),
+            );
+        }
+
+        picked
+    }
+}
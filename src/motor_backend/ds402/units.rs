@@ -0,0 +1,280 @@
+use super::sdo::SdoAddr;
+use super::{Ds402Cmd, Ds402Response};
+use crate::motor_ctx::CVP;
+
+use std::collections::BTreeMap;
+use std::f64::consts::TAU;
+use std::sync::mpsc;
+
+// factor group objects: encoder increments (subindex 1) per motor revolutions (subindex 2),
+// for positions and for velocities in increments per second.
+const POSITION_INCREMENTS: SdoAddr = SdoAddr::new(0x608F, 1);
+const POSITION_REVOLUTIONS: SdoAddr = SdoAddr::new(0x608F, 2);
+const VELOCITY_INCREMENTS: SdoAddr = SdoAddr::new(0x6090, 1);
+const VELOCITY_REVOLUTIONS: SdoAddr = SdoAddr::new(0x6090, 2);
+// positions and velocities are in the drive's user units, given by its gear ratio (motor
+// revolutions per driving shaft revolutions) and feed constant (feed per driving shaft
+// revolutions). drives without them count encoder increments.
+const GEAR_MOTOR_REVOLUTIONS: SdoAddr = SdoAddr::new(0x6091, 1);
+const GEAR_SHAFT_REVOLUTIONS: SdoAddr = SdoAddr::new(0x6091, 2);
+const FEED: SdoAddr = SdoAddr::new(0x6092, 1);
+const FEED_SHAFT_REVOLUTIONS: SdoAddr = SdoAddr::new(0x6092, 2);
+// numerator (subindex 1) and divisor (subindex 2) of the position units per second in one
+// velocity unit, and of the velocity units per second in one acceleration unit. drives without
+// them take velocities in position units per second and accelerations in velocity units per
+// second.
+const VELOCITY_FACTOR: SdoAddr = SdoAddr::new(0x6096, 1);
+const VELOCITY_FACTOR_DIVISOR: SdoAddr = SdoAddr::new(0x6096, 2);
+const ACCELERATION_FACTOR: SdoAddr = SdoAddr::new(0x6097, 1);
+const ACCELERATION_FACTOR_DIVISOR: SdoAddr = SdoAddr::new(0x6097, 2);
+// in mNm, the torque objects are per mille of it.
+const MOTOR_RATED_TORQUE: SdoAddr = SdoAddr::new(0x6076, 0);
+
+const FACTOR_GROUP: [SdoAddr; 13] = [
+    POSITION_INCREMENTS,
+    POSITION_REVOLUTIONS,
+    VELOCITY_INCREMENTS,
+    VELOCITY_REVOLUTIONS,
+    GEAR_MOTOR_REVOLUTIONS,
+    GEAR_SHAFT_REVOLUTIONS,
+    FEED,
+    FEED_SHAFT_REVOLUTIONS,
+    VELOCITY_FACTOR,
+    VELOCITY_FACTOR_DIVISOR,
+    ACCELERATION_FACTOR,
+    ACCELERATION_FACTOR_DIVISOR,
+    MOTOR_RATED_TORQUE,
+];
+
+// converts between the drive's units at the motor and rad, rad/s, rad/s² and Nm at the
+// output, like the other backends report. the default passes the drive's units through as they
+// are.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Ds402Units {
+    // drive units per rad, per rad/s, per rad/s² and per Nm at the motor
+    position_scale: f64,
+    velocity_scale: f64,
+    acceleration_scale: f64,
+    torque_scale: f64,
+    // motor revolutions per output revolution
+    gear_reduction: f64,
+}
+
+impl Default for Ds402Units {
+    fn default() -> Self {
+        Self {
+            position_scale: 1.,
+            velocity_scale: 1.,
+            acceleration_scale: 1.,
+            torque_scale: 1.,
+            gear_reduction: 1.,
+        }
+    }
+}
+
+impl Ds402Units {
+    pub fn with_gear_reduction(self, gear_reduction: f64) -> Self {
+        Self {
+            gear_reduction,
+            ..self
+        }
+    }
+
+    pub(crate) fn cvp(&self, position: i32, velocity: i32, torque: i16) -> CVP {
+        CVP {
+            position: position as f64 / self.position_scale / self.gear_reduction,
+            velocity: velocity as f64 / self.velocity_scale / self.gear_reduction,
+            current: torque as f64 / self.torque_scale * self.gear_reduction,
+        }
+    }
+
+    pub(crate) fn position(&self, position: f64) -> i32 {
+        (position * self.gear_reduction * self.position_scale).round() as i32
+    }
+
+    pub(crate) fn velocity(&self, velocity: f64) -> i32 {
+        (velocity * self.gear_reduction * self.velocity_scale).round() as i32
+    }
+
+    pub(crate) fn torque(&self, torque: f64) -> i16 {
+        (torque / self.gear_reduction * self.torque_scale).round() as i16
+    }
+
+    // profile and homing parameters, `None` when the value does not fit the drive's object.
+    pub(crate) fn position_param(&self, position: f64) -> Option<i32> {
+        round_into(position * self.gear_reduction * self.position_scale)
+    }
+
+    pub(crate) fn velocity_param(&self, velocity: f64) -> Option<u32> {
+        round_into(velocity * self.gear_reduction * self.velocity_scale)
+    }
+
+    pub(crate) fn acceleration_param(&self, acceleration: f64) -> Option<u32> {
+        round_into(acceleration * self.gear_reduction * self.acceleration_scale)
+    }
+
+    pub(crate) fn torque_param(&self, torque: f64) -> Option<u16> {
+        round_into(torque / self.gear_reduction * self.torque_scale)
+    }
+}
+
+fn round_into<T: TryFrom<i64>>(value: f64) -> Option<T> {
+    let value = value.round();
+    // `as` saturates, which the conversion rejects for every type narrower than i64
+    value
+        .is_finite()
+        .then(|| value as i64)
+        .and_then(|value| T::try_from(value).ok())
+}
+
+// the unit conversion of a motor as entered, or as read from the drive's factor group.
+#[derive(Default)]
+pub struct UnitsPanel {
+    counts_per_rev_storage: String,
+    velocity_per_rev_storage: String,
+    acceleration_per_rev_storage: String,
+    rated_torque_storage: String,
+    // factor group objects read so far
+    read: BTreeMap<(u16, u8), u32>,
+}
+
+impl UnitsPanel {
+    // the drive's own units where a field is left empty, accelerations follow the velocities
+    // unless they are given.
+    pub(crate) fn units(&self) -> Result<Ds402Units, String> {
+        fn parse(name: &str, s: &str) -> Result<Option<f64>, String> {
+            match s.trim() {
+                "" => Ok(None),
+                s => match s.parse::<f64>() {
+                    Ok(value) if value > 0. => Ok(Some(value)),
+                    _ => Err(format!("invalid {name}: {s}")),
+                },
+            }
+        }
+
+        let counts_per_rev = parse("counts per revolution", &self.counts_per_rev_storage)?;
+        let velocity_per_rev = parse("velocity per revolution", &self.velocity_per_rev_storage)?;
+        let acceleration_per_rev = parse(
+            "acceleration per revolution",
+            &self.acceleration_per_rev_storage,
+        )?;
+        let rated_torque = parse("rated torque", &self.rated_torque_storage)?;
+
+        let raw = Ds402Units::default();
+        let velocity_scale = velocity_per_rev.map_or(raw.velocity_scale, |v| v / TAU);
+        Ok(Ds402Units {
+            position_scale: counts_per_rev.map_or(raw.position_scale, |counts| counts / TAU),
+            velocity_scale,
+            acceleration_scale: acceleration_per_rev.map_or(velocity_scale, |a| a / TAU),
+            torque_scale: rated_torque.map_or(raw.torque_scale, |torque| 1000. / torque),
+            ..raw
+        })
+    }
+
+    pub(crate) fn update(&mut self, response: &Ds402Response) {
+        let Ds402Response::SdoRead(addr, Ok(data)) = response else {
+            return;
+        };
+        if !FACTOR_GROUP.contains(addr) {
+            return;
+        }
+
+        let mut bytes = [0; 4];
+        let len = data.len().min(bytes.len());
+        bytes[..len].copy_from_slice(&data[..len]);
+        self.read
+            .insert((addr.index, addr.subindex), u32::from_le_bytes(bytes));
+
+        let get = |addr: SdoAddr| self.read.get(&(addr.index, addr.subindex)).copied();
+        let ratio = |increments, revolutions| match (get(increments), get(revolutions)) {
+            (Some(increments), Some(revolutions)) if revolutions != 0 => {
+                Some(increments as f64 / revolutions as f64)
+            }
+            _ => None,
+        };
+
+        // user units per motor revolution, the feed per shaft revolution over the motor
+        // revolutions per shaft revolution
+        let user_units = match (
+            ratio(FEED, FEED_SHAFT_REVOLUTIONS),
+            ratio(GEAR_MOTOR_REVOLUTIONS, GEAR_SHAFT_REVOLUTIONS),
+        ) {
+            (Some(feed), Some(gear)) if gear != 0. => Some(feed / gear),
+            _ => None,
+        };
+
+        // velocity units per motor revolution per second, and acceleration units per motor
+        // revolution per second²
+        let velocity = match (user_units, ratio(VELOCITY_FACTOR, VELOCITY_FACTOR_DIVISOR)) {
+            (Some(user_units), Some(factor)) if factor != 0. => Some(user_units / factor),
+            (Some(user_units), _) => Some(user_units),
+            (None, _) => ratio(VELOCITY_INCREMENTS, VELOCITY_REVOLUTIONS),
+        };
+        let acceleration = match (
+            velocity,
+            ratio(ACCELERATION_FACTOR, ACCELERATION_FACTOR_DIVISOR),
+        ) {
+            (Some(velocity), Some(factor)) if factor != 0. => Some(velocity / factor),
+            (velocity, _) => velocity,
+        };
+
+        if let Some(counts) = user_units.or(ratio(POSITION_INCREMENTS, POSITION_REVOLUTIONS)) {
+            self.counts_per_rev_storage = counts.to_string();
+        }
+        if let Some(velocity) = velocity {
+            self.velocity_per_rev_storage = velocity.to_string();
+        }
+        if let Some(acceleration) = acceleration {
+            self.acceleration_per_rev_storage = acceleration.to_string();
+        }
+        if let Some(torque) = get(MOTOR_RATED_TORQUE).filter(|torque| *torque != 0) {
+            self.rated_torque_storage = (torque as f64 / 1000.).to_string();
+        }
+    }
+
+    pub(crate) fn display(
+        &mut self,
+        idx: usize,
+        tx: &mpsc::Sender<(usize, Ds402Cmd)>,
+        added: bool,
+        ui: &mut egui::Ui,
+    ) {
+        ui.collapsing("units (empty keeps the drive's units)", |ui| {
+            // the conversion is handed to the backend when the motor is added
+            ui.add_enabled_ui(!added, |ui| {
+                egui::Grid::new(("units", idx)).show(ui, |ui| {
+                    for (label, storage) in [
+                        (
+                            "position units per motor revolution",
+                            &mut self.counts_per_rev_storage,
+                        ),
+                        (
+                            "velocity units per motor revolution per second",
+                            &mut self.velocity_per_rev_storage,
+                        ),
+                        (
+                            "acceleration units per motor revolution per second²",
+                            &mut self.acceleration_per_rev_storage,
+                        ),
+                        ("rated torque in Nm", &mut self.rated_torque_storage),
+                    ] {
+                        ui.label(label);
+                        ui.text_edit_singleline(storage);
+                        ui.end_row();
+                    }
+                });
+
+                if ui.button("read from the drive's factor group").clicked() {
+                    self.read.clear();
+                    for addr in FACTOR_GROUP {
+                        let _ = tx.send((idx, Ds402Cmd::ReadSdo(addr)));
+                    }
+                }
+            });
+
+            if let Err(e) = self.units() {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+        });
+    }
+}
@@ -2,7 +2,7 @@ use io_uring::squeue::Entry as SQEntry;
 use io_uring::squeue::PushError as SQPushError;
 use io_uring::types::Timespec;
 
-use super::{Backend, RequestedMotorInput};
+use super::{Backend, DEFAULT_WATCHDOG_TIMEOUT, RequestedMotorInput, Watchdog};
 
 use std::os::fd::{AsRawFd, RawFd};
 
@@ -11,13 +11,161 @@ pub(crate) struct FourierConfig {
     pub read_timeout: Option<Timespec>,
 }
 
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
+
+mod discovery;
+pub use discovery::Discovery;
+
+mod errors;
+pub use errors::AxisErrors;
+
+mod procedure;
+use procedure::Homing;
+pub use procedure::{HomingConfig, Procedure, ProcedurePanel, ProcedureStatus};
+
+mod params;
+use params::ParamRequest;
+
+mod service;
+pub use params::{ParamGroup, ParamPanel, ParamValues};
+
+// a motor's address, along with the scope (interface index) needed to reach link-local ipv6 addresses.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct FourierAddr {
+    ip: IpAddr,
+    scope_id: u32,
+}
+
+#[derive(Debug)]
+pub enum FourierAddrError {
+    Ip(std::net::AddrParseError),
+    UnknownInterface(String),
+    ScopeOnIpv4,
+}
+
+impl core::fmt::Display for FourierAddrError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Ip(e) => write!(f, "{e}"),
+            Self::UnknownInterface(name) => write!(f, "unknown network interface: {name}"),
+            Self::ScopeOnIpv4 => write!(f, "only ipv6 addresses can have a scope"),
+        }
+    }
+}
+
+impl FourierAddr {
+    pub const UNSPECIFIED: Self = Self {
+        ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        scope_id: 0,
+    };
+
+    pub fn ip(&self) -> IpAddr {
+        self.ip
+    }
+
+    fn sock_addr(&self, port: u16) -> SockAddr {
+        match self.ip {
+            IpAddr::V4(ip) => {
+                let mut addr: libc::sockaddr_in = unsafe { core::mem::zeroed() };
+                addr.sin_family = libc::AF_INET as _;
+                addr.sin_port = port.to_be();
+                addr.sin_addr.s_addr = u32::from_ne_bytes(ip.octets());
+                SockAddr::V4(addr)
+            }
+            IpAddr::V6(ip) => {
+                let mut addr: libc::sockaddr_in6 = unsafe { core::mem::zeroed() };
+                addr.sin6_family = libc::AF_INET6 as _;
+                addr.sin6_port = port.to_be();
+                addr.sin6_addr.s6_addr = ip.octets();
+                addr.sin6_scope_id = self.scope_id;
+                SockAddr::V6(addr)
+            }
+        }
+    }
+}
+
+impl From<Ipv4Addr> for FourierAddr {
+    fn from(ip: Ipv4Addr) -> Self {
+        Self {
+            ip: IpAddr::V4(ip),
+            scope_id: 0,
+        }
+    }
+}
+
+// accepts `192.168.0.10`, `fd00::10`, and link-local addresses scoped by interface
+// name or index, e.g. `fe80::10%eth0` or `fe80::10%2`.
+impl core::str::FromStr for FourierAddr {
+    type Err = FourierAddrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ip, scope) = match s.split_once('%') {
+            Some((ip, scope)) => (ip, Some(scope)),
+            None => (s, None),
+        };
+
+        let ip = ip.parse::<IpAddr>().map_err(FourierAddrError::Ip)?;
+
+        let scope_id = match (&ip, scope) {
+            (_, None) => 0,
+            (IpAddr::V4(_), Some(_)) => return Err(FourierAddrError::ScopeOnIpv4),
+            (IpAddr::V6(_), Some(scope)) => match scope.parse::<u32>() {
+                Ok(idx) => idx,
+                Err(_) => interface_index(scope)
+                    .ok_or_else(|| FourierAddrError::UnknownInterface(scope.to_owned()))?,
+            },
+        };
+
+        Ok(Self { ip, scope_id })
+    }
+}
+
+impl core::fmt::Display for FourierAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.ip {
+            IpAddr::V6(ip) if self.scope_id != 0 => write!(f, "{ip}%{}", self.scope_id),
+            ip => write!(f, "{ip}"),
+        }
+    }
+}
+
+fn interface_index(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    let idx = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    (idx != 0).then_some(idx)
+}
+
+enum SockAddr {
+    V4(libc::sockaddr_in),
+    V6(libc::sockaddr_in6),
+}
+
+impl SockAddr {
+    fn as_ptr(&self) -> *const libc::sockaddr {
+        match self {
+            Self::V4(addr) => addr as *const _ as *const libc::sockaddr,
+            Self::V6(addr) => addr as *const _ as *const libc::sockaddr,
+        }
+    }
+
+    fn len(&self) -> libc::socklen_t {
+        match self {
+            Self::V4(_) => core::mem::size_of::<libc::sockaddr_in>() as _,
+            Self::V6(_) => core::mem::size_of::<libc::sockaddr_in6>() as _,
+        }
+    }
+}
 
 pub struct MotorUiConfig {
     pub(crate) ip_buf_storage: String,
-    pub(crate) ip: Option<Ipv4Addr>,
+    pub(crate) ip: Option<FourierAddr>,
     pub(crate) encoding: FourierEncodeKind,
     pub(crate) added: bool,
+    pub(crate) discovery: Discovery,
+    pub(crate) params: ParamPanel,
+    pub(crate) errors: Option<AxisErrors>,
+    pub(crate) procedures: ProcedurePanel,
+    pub(crate) add_error: Option<String>,
 }
 
 impl MotorUiConfig {
@@ -28,20 +176,35 @@ impl MotorUiConfig {
             ip_buf_storage,
             ip,
             encoding,
-            added: _,
+            added,
+            discovery,
+            params: _,
+            errors: _,
+            procedures: _,
+            add_error,
         } = self;
 
+        // replies are matched to the motor by its address, so it stays fixed until the motor is removed
+        let found = ui.add_enabled_ui(!*added, |ui| discovery.display(ui)).inner;
+        if let Some(found) = found {
+            *ip_buf_storage = found.to_string();
+            if *ip != Some(found) {
+                *ip = Some(found);
+                changed = true;
+            }
+        }
+
         ui.horizontal(|ui| {
             ui.label("motor ip addr");
-            if ui.text_edit_singleline(ip_buf_storage).changed() {
-                match (&ip, ip_buf_storage.parse::<IpAddr>()) {
-                    (_, Ok(IpAddr::V6(_))) => todo!(),
-                    (Some(old_ip), Ok(IpAddr::V4(new_ip))) if new_ip != *old_ip => {
+            let edit = egui::TextEdit::singleline(ip_buf_storage);
+            if ui.add_enabled(!*added, edit).changed() {
+                match (&ip, ip_buf_storage.parse::<FourierAddr>()) {
+                    (Some(old_ip), Ok(new_ip)) if new_ip != *old_ip => {
                         // signal to the driver to remove the old ip and create the new ip.
                         *ip = Some(new_ip);
                         changed = true;
                     }
-                    (None, Ok(IpAddr::V4(new_ip))) => {
+                    (None, Ok(new_ip)) => {
                         // create a new ip
                         *ip = Some(new_ip);
                         changed = true;
@@ -57,6 +220,12 @@ impl MotorUiConfig {
             if let Some(ip) = &ip {
                 ui.label(format!("current ip: {ip}"));
             }
+
+            if !ip_buf_storage.is_empty() {
+                if let Err(e) = ip_buf_storage.parse::<FourierAddr>() {
+                    ui.label(format!("invalid address: {e}"));
+                }
+            }
         });
 
         ui.horizontal(|ui| {
@@ -66,10 +235,14 @@ impl MotorUiConfig {
             ui.radio_value(encoding, FourierEncodeKind::Binary, "binary");
         });
 
+        if let Some(e) = add_error {
+            ui.colored_label(egui::Color32::RED, format!("could not add the motor: {e}"));
+        }
+
         changed
     }
 
-    pub fn ip_addr(&self) -> Option<Ipv4Addr> {
+    pub fn ip_addr(&self) -> Option<FourierAddr> {
         self.ip
     }
 }
@@ -81,11 +254,18 @@ impl Default for MotorUiConfig {
             ip: None,
             encoding: FourierEncodeKind::Binary,
             added: false,
+            discovery: Discovery::default(),
+            params: ParamPanel::default(),
+            errors: None,
+            procedures: ProcedurePanel::default(),
+            add_error: None,
         }
     }
 }
 
 const DEFAULT_FOURIER_READ_TIMEOUT: Timespec = Timespec::new().nsec(1000000000);
+// the generation sits above the motor's id and the send bit in the user data.
+const GENERATION_SHIFT: u32 = 33;
 
 pub(crate) struct FourierSendRecv {
     pub send: SQEntry,
@@ -112,20 +292,49 @@ impl FourierSendRecv {
 
 pub(crate) struct FourierBackend<const R: usize, const W: usize> {
     motor: amber_aios::AiosMotor<R, W>,
+    // amber_aios only opens ipv4 sockets, so requests go out on a socket of the motor's own
+    // address family and the motor is only used for its buffers.
+    socket: UdpSocket,
+    addr: FourierAddr,
+    // identifies the motor's io_uring entries, since an ipv6 address does not fit in the user data.
+    id: u32,
     config: FourierConfig,
     enabled: bool,
     remove_next_recv: bool,
+    disabling: bool,
+    // configuration requests are sent in between setpoints, one at a time.
+    params: std::collections::VecDeque<ParamRequest>,
+    param_in_flight: Option<ParamRequest>,
+    param_buf: Vec<u8>,
+    // the error registers are read once each time the motor refuses to enable.
+    errors_queried: bool,
+    homing: Option<Homing>,
+    // a limit with the fault action tripped, the motor is kept idle until it is reset.
+    faulted: bool,
+    // drops the follow up of the calibration request that was in flight when it got cancelled.
+    procedure_cancelled: bool,
+    // bumped when the reply to the request in flight is given up on, the entries carry it so
+    // the completions of the abandoned request are dropped.
+    generation: u32,
 }
 
-use crate::motor_ctx::{ControlState, MotorConfig};
+use crate::motor_ctx::{ControlState, LimitViolation, MotorConfig, SafetyLimits};
 
 impl<const R: usize, const W: usize> FourierBackend<R, W> {
     pub fn new(
-        ip_addr: Ipv4Addr,
+        addr: FourierAddr,
+        id: u32,
         encode: FourierEncodeKind,
         read_timeout: Option<Timespec>,
-    ) -> Result<Self, amber_aios::Err> {
-        let motor = amber_aios::AiosMotor::from_addr(ip_addr)?;
+    ) -> std::io::Result<Self> {
+        let (motor_ip, bind_ip) = match addr.ip() {
+            IpAddr::V4(ip) => (ip, IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            IpAddr::V6(_) => (Ipv4Addr::UNSPECIFIED, IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        };
+
+        let motor = amber_aios::AiosMotor::from_addr(motor_ip)
+            .map_err(|_| std::io::Error::other("could not create the motor's buffers"))?;
+        let socket = UdpSocket::bind((bind_ip, 0))?;
 
         let config = FourierConfig {
             encode: encode,
@@ -134,17 +343,55 @@ impl<const R: usize, const W: usize> FourierBackend<R, W> {
 
         Ok(Self {
             motor,
+            socket,
+            addr,
+            id,
             config,
             enabled: false,
             remove_next_recv: false,
+            disabling: false,
+            params: std::collections::VecDeque::new(),
+            param_in_flight: None,
+            param_buf: Vec::new(),
+            errors_queried: false,
+            homing: None,
+            faulted: false,
+            procedure_cancelled: false,
+            generation: 0,
         })
     }
 
+    // the motor's id and the current generation, sends add `1 << 32`.
+    fn user_data(&self) -> u64 {
+        self.id as u64 | ((self.generation as u64) << GENERATION_SHIFT)
+    }
+
     pub fn prepare_input_msg(
         &mut self,
         cvp: crate::motor_ctx::CVP,
         mode: &ControlState,
         f: impl FnOnce(FourierSendRecv) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let enable_cmd = amber_aios::cmds::set_requested_state::<1>(amber_aios::AxisState::Enable);
+        let disable_cmd = amber_aios::cmds::set_requested_state::<1>(amber_aios::AxisState::Idle);
+
+        if self.disabling || self.faulted {
+            self.prepare_msg_from_cmd(&disable_cmd, f)
+        } else if let Some(request) = self.params.pop_front() {
+            self.prepare_param_msg(request, f)
+        } else if !self.enabled {
+            self.prepare_msg_from_cmd(&enable_cmd, f)
+        } else {
+            self.prepare_setpoint_msg(cvp, mode, f)
+        }
+    }
+
+    // the setpoint alone, whatever else is waiting to be sent.
+    fn prepare_setpoint_msg(
+        &mut self,
+        cvp: crate::motor_ctx::CVP,
+        mode: &ControlState,
+        f: impl FnOnce(FourierSendRecv) -> std::io::Result<()>,
     ) -> std::io::Result<()> {
         let set_pos = amber_aios::cmds::binary::set_input_position(
             cvp.position as _,
@@ -161,30 +408,24 @@ impl<const R: usize, const W: usize> FourierBackend<R, W> {
         let set_i = amber_aios::cmds::binary::set_input_torque(cvp.current as _);
         let set_i_json = amber_aios::cmds::set_current::<1>(cvp.current);
 
-        let enable_cmd = amber_aios::cmds::set_requested_state::<1>(amber_aios::AxisState::Enable);
-
-        if !self.enabled {
-            self.prepare_msg_from_cmd(&enable_cmd, f)
-        } else {
-            match (mode, &self.config.encode) {
-                (ControlState::Position { .. }, FourierEncodeKind::Json) => {
-                    self.prepare_msg_from_cmd(&set_pos_json, f)
-                }
-                (ControlState::Position { .. }, FourierEncodeKind::Binary) => {
-                    self.prepare_msg_from_cmd(&set_pos, f)
-                }
-                (ControlState::Velocity { .. }, FourierEncodeKind::Json) => {
-                    self.prepare_msg_from_cmd(&set_vel_json, f)
-                }
-                (ControlState::Velocity { .. }, FourierEncodeKind::Binary) => {
-                    self.prepare_msg_from_cmd(&set_vel, f)
-                }
-                (ControlState::Torque { .. }, FourierEncodeKind::Json) => {
-                    self.prepare_msg_from_cmd(&set_i_json, f)
-                }
-                (ControlState::Torque { .. }, FourierEncodeKind::Binary) => {
-                    self.prepare_msg_from_cmd(&set_i, f)
-                }
+        match (mode, &self.config.encode) {
+            (ControlState::Position { .. }, FourierEncodeKind::Json) => {
+                self.prepare_msg_from_cmd(&set_pos_json, f)
+            }
+            (ControlState::Position { .. }, FourierEncodeKind::Binary) => {
+                self.prepare_msg_from_cmd(&set_pos, f)
+            }
+            (ControlState::Velocity { .. }, FourierEncodeKind::Json) => {
+                self.prepare_msg_from_cmd(&set_vel_json, f)
+            }
+            (ControlState::Velocity { .. }, FourierEncodeKind::Binary) => {
+                self.prepare_msg_from_cmd(&set_vel, f)
+            }
+            (ControlState::Torque { .. }, FourierEncodeKind::Json) => {
+                self.prepare_msg_from_cmd(&set_i_json, f)
+            }
+            (ControlState::Torque { .. }, FourierEncodeKind::Binary) => {
+                self.prepare_msg_from_cmd(&set_i, f)
             }
         }
     }
@@ -194,9 +435,10 @@ impl<const R: usize, const W: usize> FourierBackend<R, W> {
         cmd: &'b C,
         f: impl FnOnce(FourierSendRecv) -> std::io::Result<()>,
     ) -> std::io::Result<()> {
+        let fd = self.socket.as_raw_fd();
         let motor = &mut self.motor;
-        let fd = motor.as_raw_fd();
-        let addr = motor.sock_addr(C::PORT);
+        let addr = self.addr.sock_addr(C::PORT);
+        let id = self.user_data();
 
         use io_uring::opcode::{LinkTimeout, Recv, Send};
         use io_uring::squeue::Flags;
@@ -208,7 +450,7 @@ impl<const R: usize, const W: usize> FourierBackend<R, W> {
                 .dest_addr(addr.as_ptr())
                 .dest_addr_len(addr.len())
                 .build()
-                .user_data((motor.addr().to_bits() as u64) | 1 << 32)
+                .user_data(id | 1 << 32)
                 .flags(io_uring::squeue::Flags::SKIP_SUCCESS)
         };
 
@@ -218,7 +460,7 @@ impl<const R: usize, const W: usize> FourierBackend<R, W> {
             Recv::new(Fd(fd), recv_buf.as_mut_ptr(), recv_buf.len() as _)
                 .build()
                 .flags(Flags::IO_LINK)
-                .user_data(motor.addr().to_bits() as u64)
+                .user_data(id)
         };
 
         let read_timeout = self
@@ -228,7 +470,50 @@ impl<const R: usize, const W: usize> FourierBackend<R, W> {
 
         let recv_timeout = LinkTimeout::new(&read_timeout)
             .build()
-            .user_data(motor.addr().to_bits() as u64);
+            .user_data(id);
+
+        f(FourierSendRecv {
+            send,
+            recv,
+            recv_timeout,
+        })
+    }
+
+    fn prepare_param_msg(
+        &mut self,
+        request: ParamRequest,
+        f: impl FnOnce(FourierSendRecv) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let fd = self.socket.as_raw_fd();
+        let addr = self.addr.sock_addr(service::SERVICE_PORT);
+        let id = self.user_data();
+
+        use io_uring::opcode::{LinkTimeout, Recv, Send};
+        use io_uring::squeue::Flags;
+        use io_uring::types::Fd;
+
+        request.serialize(&mut self.param_buf);
+        self.param_in_flight = Some(request);
+
+        let send = Send::new(Fd(fd), self.param_buf.as_ptr(), self.param_buf.len() as _)
+            .dest_addr(addr.as_ptr())
+            .dest_addr_len(addr.len())
+            .build()
+            .user_data(id | 1 << 32)
+            .flags(Flags::SKIP_SUCCESS);
+
+        let recv_buf = self.motor.read_buf_mut();
+        let recv = Recv::new(Fd(fd), recv_buf.as_mut_ptr(), recv_buf.len() as _)
+            .build()
+            .flags(Flags::IO_LINK)
+            .user_data(id);
+
+        let read_timeout = self
+            .config
+            .read_timeout
+            .unwrap_or(DEFAULT_FOURIER_READ_TIMEOUT);
+
+        let recv_timeout = LinkTimeout::new(&read_timeout).build().user_data(id);
 
         f(FourierSendRecv {
             send,
@@ -237,25 +522,125 @@ impl<const R: usize, const W: usize> FourierBackend<R, W> {
         })
     }
 
+    // handles the reply to a configuration request, if one was in flight.
+    fn complete_params(&mut self, len: usize) -> Option<Option<FourierResponse>> {
+        let request = self.param_in_flight.take()?;
+        if matches!(request, ParamRequest::AbortCalibration(_)) {
+            self.procedure_cancelled = false;
+        }
+
+        let (response, next) = request.complete(&self.motor.read_buf()[..len]);
+        match next {
+            Some(next) if self.procedure_cancelled && next.is_calibration() => (),
+            Some(next) => self.params.push_front(next),
+            None => (),
+        }
+        Some(response)
+    }
+
+    fn calibrating(&self) -> bool {
+        self.param_in_flight
+            .iter()
+            .chain(self.params.iter())
+            .any(ParamRequest::is_calibration)
+    }
+
+    fn start_procedure(&mut self, procedure: Procedure) -> ProcedureStatus {
+        if self.homing.is_some() || self.calibrating() {
+            return ProcedureStatus::Failed("another procedure is running".into());
+        }
+
+        match procedure {
+            Procedure::Calibrate => {
+                // the motor is enabled again once the calibration is over
+                self.enabled = false;
+                self.params.push_back(ParamRequest::Calibrate);
+                ProcedureStatus::Running("requesting calibration".into())
+            }
+            Procedure::SetZero => {
+                self.params.push_back(ParamRequest::SetZero);
+                ProcedureStatus::Running("zeroing".into())
+            }
+            Procedure::Home(config) => {
+                self.homing = Some(Homing::new(config));
+                ProcedureStatus::Running("moving towards the hard stop".into())
+            }
+        }
+    }
+
+    fn cancel_procedure(&mut self) -> Option<ProcedureStatus> {
+        if self.homing.take().is_some() {
+            return Some(ProcedureStatus::Failed("homing cancelled".into()));
+        }
+
+        if self.calibrating() {
+            self.params.retain(|request| !request.is_calibration());
+            self.procedure_cancelled = self
+                .param_in_flight
+                .as_ref()
+                .is_some_and(ParamRequest::is_calibration);
+            self.params.push_back(ParamRequest::AbortCalibration(
+                "calibration cancelled".into(),
+            ));
+        }
+        None
+    }
+
+    // ends a running procedure without waiting for the motor, which is going away or stopped replying.
+    fn abandon_procedure(&mut self, reason: &str) -> Option<ProcedureStatus> {
+        if self.homing.take().is_none() && !self.calibrating() {
+            return None;
+        }
+
+        self.params.retain(|request| !request.is_calibration());
+        self.procedure_cancelled = self
+            .param_in_flight
+            .as_ref()
+            .is_some_and(ParamRequest::is_calibration);
+        Some(ProcedureStatus::Failed(reason.into()))
+    }
+
+    fn queue_params(&mut self, request: ParamRequest) {
+        if matches!(request, ParamRequest::Reboot) {
+            // the motor comes back up disabled
+            self.enabled = false;
+        }
+        self.params.push_back(request);
+    }
+
+    // the measurement at the output, in the same frame as the commands before
+    // `Backend::prepare_input_msg` applies the gear reduction.
     pub fn parse_cvp(
         &mut self,
         len: usize,
-        _config: &MotorConfig,
+        config: &MotorConfig,
     ) -> Result<Option<crate::motor_ctx::CVP>, amber_aios::Err> {
         use amber_aios::cmds::Command;
         use amber_aios::cmds::binary::BinaryCommand;
         let bytes = &self.motor.read_buf()[..len];
 
         if !self.enabled {
-            if let Ok(amber_aios::Request {
-                data: amber_aios::RequestedState { current_state: 8 },
-                ..
-            }) = amber_aios::cmds::GetRequestedState::parse_return(bytes)
-            {
-                self.enabled = true;
-                return Ok(None);
-            } else {
-                return Err(amber_aios::Err::UnexpectedReturn);
+            match amber_aios::cmds::GetRequestedState::parse_return(bytes) {
+                Ok(amber_aios::Request {
+                    data: amber_aios::RequestedState { current_state: 8 },
+                    ..
+                }) => {
+                    self.enabled = true;
+                    self.errors_queried = false;
+                    return Ok(None);
+                }
+                Ok(amber_aios::Request {
+                    data: amber_aios::RequestedState { current_state },
+                    ..
+                }) => {
+                    if !self.errors_queried {
+                        self.errors_queried = true;
+                        self.params
+                            .push_back(ParamRequest::ReadErrors(Some(current_state.into())));
+                    }
+                    return Err(amber_aios::Err::UnexpectedReturn);
+                }
+                Err(_) => return Err(amber_aios::Err::UnexpectedReturn),
             }
         }
 
@@ -271,39 +656,125 @@ impl<const R: usize, const W: usize> FourierBackend<R, W> {
         };
 
         Ok(Some(crate::motor_ctx::CVP {
-            position: position,
-            velocity: velocity,
+            position: position / config.gear_reduction,
+            velocity: velocity / config.gear_reduction,
             current,
         }))
     }
 }
 
+// homing drives the motor towards its hard stop at a constant velocity.
+const HOMING_STATE: ControlState = ControlState::Velocity {
+    show_position: false,
+    show_torque: false,
+};
+
 impl<const R: usize, const W: usize> Backend<FourierBackend<R, W>> {
     pub fn prepare_input_msg(
         &mut self,
         f: impl FnOnce(FourierSendRecv) -> std::io::Result<()>,
     ) -> std::io::Result<()> {
-        let cvp = if let Some(crate::motor_ctx::CVP {
+        if let Some(homing) = &self.backend_specific.homing {
+            self.input_cvp = Some(homing.command());
+        }
+
+        // the replies to the idle request do not parse as a measurement, and the motor is
+        // known to be refusing to enable, so no errors are read for them
+        let faulted = self.safety.faulted();
+        if faulted && !self.backend_specific.faulted {
+            self.backend_specific.enabled = false;
+            self.backend_specific.errors_queried = true;
+        }
+        self.backend_specific.faulted = faulted;
+
+        let cvp = self.motor_input();
+        let mode = self.mode();
+        self.backend_specific.prepare_input_msg(cvp, &mode, f)
+    }
+
+    // the limited command at the motor, before the gear reduction.
+    fn motor_input(&mut self) -> crate::motor_ctx::CVP {
+        let crate::motor_ctx::CVP {
             position,
             velocity,
             current,
-        }) = &self.input_cvp
-        {
-            let gear_reduction = self.motor_config.gear_reduction;
-            crate::motor_ctx::CVP {
-                position: position * gear_reduction,
-                velocity: velocity * gear_reduction,
-                current: *current,
-            }
-        } else {
-            crate::motor_ctx::CVP {
-                position: 0.,
-                velocity: 0.,
-                current: 0.,
-            }
-        };
+        } = self.limited_input();
+
+        let gear_reduction = self.motor_config.gear_reduction;
+        crate::motor_ctx::CVP {
+            position: position * gear_reduction,
+            velocity: velocity * gear_reduction,
+            current,
+        }
+    }
+
+    fn mode(&self) -> ControlState {
+        match self.backend_specific.homing {
+            Some(_) => HOMING_STATE,
+            None => self.motor_config.state,
+        }
+    }
+
+    // sends the stopped setpoint right away, rather than once the request in flight has been
+    // answered, which can take up to the read timeout. the setpoint in flight is given up on,
+    // its recv is cancelled and the stop goes out with a recv of its own.
+    fn send_stop(&mut self, ring: &mut io_uring::IoUring) -> std::io::Result<()> {
+        use io_uring::opcode::AsyncCancel;
+
+        let motor = &self.backend_specific;
+        if !motor.enabled || motor.disabling || motor.faulted || motor.param_in_flight.is_some() {
+            // the motor is not following setpoints, or a configuration request is waiting for
+            // its reply. either way the stop goes out with the next request.
+            return Ok(());
+        }
+
+        let abandoned = motor.user_data();
+        let cancel = AsyncCancel::new(abandoned).build().user_data(abandoned);
+        while unsafe { ring.submission().push(&cancel).is_err() } {
+            ring.submit()?;
+        }
+        // leaves room for the whole request
+        ring.submit()?;
+
+        let motor = &mut self.backend_specific;
+        motor.generation = (motor.generation + 1) & (u32::MAX >> 1);
+
+        let cvp = self.motor_input();
+        let mode = self.mode();
         self.backend_specific
-            .prepare_input_msg(cvp, &self.motor_config.state, f)
+            .prepare_setpoint_msg(cvp, &mode, |prep| {
+                prep.queue(&mut ring.submission())
+                    .map_err(std::io::Error::other)?;
+                ring.submit()?;
+                Ok(())
+            })
+    }
+
+    // `Some` when the homing routine has made progress worth reporting.
+    fn update_homing(&mut self, measured: &crate::motor_ctx::CVP) -> Option<ProcedureStatus> {
+        let homing = self.backend_specific.homing.as_mut()?;
+
+        if self.safety.stopped() || self.safety.tripped().is_some() {
+            self.backend_specific.homing = None;
+            return Some(ProcedureStatus::Failed(
+                "homing interrupted by a stop".into(),
+            ));
+        }
+
+        let result = homing.update(measured)?;
+        self.backend_specific.homing = None;
+        self.input_cvp = None;
+
+        Some(match result {
+            Ok(()) => {
+                // zeroing goes out before the next setpoint
+                self.backend_specific
+                    .params
+                    .push_back(ParamRequest::SetZero);
+                ProcedureStatus::Running("hard stop found, zeroing".into())
+            }
+            Err(e) => ProcedureStatus::Failed(e),
+        })
     }
 
     pub fn parse_cvp(
@@ -325,7 +796,14 @@ impl<const R: usize, const W: usize> Backend<FourierBackend<R, W>> {
         }
     }
 
-    fn update_input(&mut self, end_tx: &mpsc::Sender<(Ipv4Addr, FourierResponse)>) {
+    fn update_input(&mut self, end_tx: &mpsc::Sender<(FourierAddr, FourierResponse)>) {
+        if self.waiting_to_start() {
+            // keep holding until a synchronized start. setpoints only go out once the previous
+            // request was answered, so the start lands on the first reply after it, up to a round
+            // trip later than on the other backends.
+            return;
+        }
+
         let next_input = match &self.request_input {
             None => None,
             Some((_, RequestedMotorInput::Cvp(c))) => Some(*c),
@@ -335,17 +813,14 @@ impl<const R: usize, const W: usize> Backend<FourierBackend<R, W>> {
 
                 if elapsed > s.delay + s.on_dur {
                     let _ = end_tx.send((
-                        self.backend_specific.motor.addr(),
+                        self.backend_specific.addr,
                         FourierResponse::EndWaveform,
                     ));
                     None
                 } else if elapsed > s.delay {
-                    Some(crate::motor_ctx::CVP {
-                        velocity: s.magnitude,
-                        ..Default::default()
-                    })
+                    Some(self.setpoint(Some(s.magnitude)))
                 } else {
-                    Some(Default::default())
+                    Some(self.setpoint(None))
                 }
             }
             Some((then, RequestedMotorInput::Impulse(i))) => {
@@ -356,17 +831,14 @@ impl<const R: usize, const W: usize> Backend<FourierBackend<R, W>> {
 
                 if elapsed > i.delay + i.delay {
                     let _ = end_tx.send((
-                        self.backend_specific.motor.addr(),
+                        self.backend_specific.addr,
                         FourierResponse::EndWaveform,
                     ));
                     None
                 } else if elapsed < i.delay + EPSILON && elapsed > i.delay - EPSILON {
-                    Some(crate::motor_ctx::CVP {
-                        velocity: i.magnitude,
-                        ..Default::default()
-                    })
+                    Some(self.setpoint(Some(i.magnitude)))
                 } else {
-                    Some(Default::default())
+                    Some(self.setpoint(None))
                 }
             }
             Some((then, RequestedMotorInput::Custom(c))) => {
@@ -386,23 +858,37 @@ impl<const R: usize, const W: usize> Backend<FourierBackend<R, W>> {
 
                 if cvp.is_none() {
                     let _ = end_tx.send((
-                        self.backend_specific.motor.addr(),
+                        self.backend_specific.addr,
                         FourierResponse::EndWaveform,
                     ));
                 }
                 cvp
             }
+            Some((then, RequestedMotorInput::Trajectory(profile))) => {
+                let now = time::Instant::now();
+                let elapsed = now.duration_since(*then);
+                let cvp = profile.sample(elapsed);
+
+                if elapsed > profile.duration() {
+                    let _ = end_tx.send((
+                        self.backend_specific.addr,
+                        FourierResponse::EndWaveform,
+                    ));
+                    // hold the target rather than falling back to a zero setpoint
+                    self.request_input = Some((now, RequestedMotorInput::Cvp(cvp)));
+                }
+                Some(cvp)
+            }
+            Some((_, RequestedMotorInput::Follow(c))) => Some(*c),
         };
 
         self.input_cvp = next_input;
     }
 }
 
-use std::net::IpAddr;
-
 impl<const R: usize, const W: usize> AsRawFd for FourierBackend<R, W> {
     fn as_raw_fd(&self) -> RawFd {
-        self.motor.as_raw_fd()
+        self.socket.as_raw_fd()
     }
 }
 
@@ -418,9 +904,28 @@ use std::sync::mpsc;
 pub enum FourierCmd {
     Add(FourierEncodeKind, Option<Timespec>, MotorConfig),
     Remove,
-    SetWaveForm(crate::MotorInput),
+    // the waveform begins at the given instant, which may be in the future.
+    SetWaveForm(crate::MotorInput, time::Instant),
+    // the next setpoint of a motor whose waveform follows another motor.
+    Follow(crate::motor_ctx::CVP),
     StopWaveform,
     Shutdown,
+    // stops every connected motor, the address is ignored.
+    EStop,
+    Rearm,
+    Heartbeat,
+    SetWatchdog(Option<time::Duration>),
+    // replaces the safety limits of an added motor.
+    SetLimits(SafetyLimits),
+    ReadParams(ParamGroup),
+    // the written parameters are read back to verify them.
+    WriteParams(ParamGroup, ParamValues),
+    SaveConfig,
+    Reboot,
+    ReadErrors,
+    ClearErrors,
+    StartProcedure(Procedure),
+    CancelProcedure,
 }
 
 use std::time;
@@ -428,20 +933,43 @@ use std::time;
 #[derive(Debug)]
 pub enum FourierResponse {
     OutputCVP(crate::motor_ctx::CVP, time::Instant),
+    // the command after the controller, the measurement is still sent as `OutputCVP`.
     ControllerAdjustedCVP(crate::motor_ctx::CVP, time::Instant),
     Error(std::io::Error),
+    // the motor's socket could not be opened, it is not added.
+    AddFailed(std::io::Error),
     Timeout,
     DuplicateConnections,
     EndWaveform,
+    LimitTripped(LimitViolation),
+    EStopActive,
+    // sent once for the whole backend, with an unspecified address.
+    WatchdogExpired,
+    Removed,
+    ShutdownComplete,
+    Params(ParamGroup, ParamValues),
+    ParamsWritten {
+        group: ParamGroup,
+        values: ParamValues,
+        // parameters that did not read back as written
+        mismatched: Vec<String>,
+    },
+    ParamError(String),
+    ConfigSaved,
+    Rebooting,
+    AxisError(AxisErrors),
+    Procedure(ProcedureStatus),
 }
 
 pub fn event_loop(
-    cmd_rx: mpsc::Receiver<(Ipv4Addr, FourierCmd)>,
-    err_tx: mpsc::Sender<(Ipv4Addr, FourierResponse)>,
+    cmd_rx: mpsc::Receiver<(FourierAddr, FourierCmd)>,
+    err_tx: mpsc::Sender<(FourierAddr, FourierResponse)>,
 ) -> std::io::Result<()> {
     let mut ring = io_uring::IoUring::new(16)?;
     use std::collections::HashMap;
     let mut connections = HashMap::new();
+    let mut connection_ids = HashMap::new();
+    let mut next_id: u32 = 0;
 
     let mut probe = io_uring::register::Probe::new();
     ring.submitter().register_probe(&mut probe)?;
@@ -456,18 +984,35 @@ pub fn event_loop(
     }
 
     let mut request_shutdown = false;
+    let mut estopped = false;
+    let mut watchdog = Watchdog::new(Some(DEFAULT_WATCHDOG_TIMEOUT));
 
     loop {
         while let Some((ip, cmd)) = match cmd_rx.try_recv() {
             Ok(cmd) => Some(cmd),
             Err(mpsc::TryRecvError::Disconnected) => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "disconnected",
-                ));
+                // the ui is gone, stop the motors rather than leave them at their last setpoint,
+                // then disable and remove them. the loop returns once they are removed.
+                if !request_shutdown {
+                    estopped = true;
+                    request_shutdown = true;
+                    for motor in connections.values_mut() {
+                        motor.stop();
+                        motor.send_stop(&mut ring)?;
+                        motor.backend_specific.remove_next_recv = true;
+                        motor.backend_specific.abandon_procedure("shutting down");
+                    }
+
+                    if connections.is_empty() {
+                        return Ok(());
+                    }
+                }
+                None
             }
             _ => None,
         } {
+            watchdog.feed();
+
             match cmd {
                 FourierCmd::Add(encode, timeout, motor_config) => {
                     if connections.contains_key(&ip) {
@@ -475,12 +1020,22 @@ pub fn event_loop(
                         continue;
                     }
 
-                    let mut motor = Backend::new(
-                        motor_config,
-                        FourierBackend::<2048, 2048>::new(ip, encode, timeout)
-                            .map_err(|_| std::io::Error::other("a"))?,
-                        None,
-                    );
+                    let id = next_id;
+                    next_id = next_id.wrapping_add(1);
+
+                    let backend = match FourierBackend::<2048, 2048>::new(ip, id, encode, timeout) {
+                        Ok(backend) => backend,
+                        Err(e) => {
+                            let _ = err_tx.send((ip, FourierResponse::AddFailed(e)));
+                            continue;
+                        }
+                    };
+
+                    let mut motor = Backend::new(motor_config, backend, None);
+
+                    if estopped {
+                        motor.safety.stop();
+                    }
 
                     motor
                         .prepare_input_msg(|prep| {
@@ -491,135 +1046,240 @@ pub fn event_loop(
                         .unwrap();
 
                     connections.insert(ip, motor);
+                    connection_ids.insert(id, ip);
+                }
+                FourierCmd::Remove => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        motor.stop();
+                        motor.backend_specific.remove_next_recv = true;
+                        // disabling the motor on removal also ends a calibration
+                        if let Some(status) =
+                            motor.backend_specific.abandon_procedure("motor removed")
+                        {
+                            let _ = err_tx.send((ip, status.into()));
+                        }
+                    } else {
+                        let _ = err_tx.send((ip, FourierResponse::Removed));
+                    }
                 }
                 FourierCmd::Shutdown => {
                     request_shutdown = true;
+                    for (motor_ip, motor) in connections.iter_mut() {
+                        motor.stop();
+                        motor.backend_specific.remove_next_recv = true;
+                        if let Some(status) =
+                            motor.backend_specific.abandon_procedure("shutting down")
+                        {
+                            let _ = err_tx.send((*motor_ip, status.into()));
+                        }
+                    }
+
+                    if connections.is_empty() {
+                        let _ = err_tx.send((ip, FourierResponse::ShutdownComplete));
+                        return Ok(());
+                    }
+                }
+                FourierCmd::SetWaveForm(..) if estopped => {
+                    let _ = err_tx.send((ip, FourierResponse::EStopActive));
                 }
-                FourierCmd::SetWaveForm(waveform) => {
+                FourierCmd::SetWaveForm(waveform, start) => {
                     if let Some(motor) = connections.get_mut(&ip) {
-                        use crate::MotorInput;
-                        match waveform {
-                            MotorInput::Constant(c) => {
-                                if !motor.backend_specific.remove_next_recv {
-                                    let cvp = crate::motor_ctx::CVP {
-                                        velocity: c,
-                                        position: 0.,
-                                        current: 0.,
-                                    };
-
-                                    motor.input_cvp = Some(cvp);
-                                    motor.request_input =
-                                        Some((time::Instant::now(), RequestedMotorInput::Cvp(cvp)))
-                                }
-                            }
-                            MotorInput::Idle => {
-                                let cvp = crate::motor_ctx::CVP::default();
-                                motor.input_cvp = Some(cvp);
-                                motor.request_input =
-                                    Some((time::Instant::now(), RequestedMotorInput::Cvp(cvp)));
-                            }
-                            MotorInput::Step(s) => {
-                                let cvp = if s.delay <= time::Duration::ZERO {
-                                    crate::motor_ctx::CVP {
-                                        velocity: s.magnitude,
-                                        position: 0.,
-                                        current: 0.,
-                                    }
-                                } else {
-                                    crate::motor_ctx::CVP::default()
-                                };
-
-                                motor.input_cvp = Some(cvp);
-                                motor.request_input =
-                                    Some((time::Instant::now(), RequestedMotorInput::Step(s)))
-                            }
-                            MotorInput::Impulse(i) => {
-                                let cvp = if i.delay < time::Duration::ZERO {
-                                    crate::motor_ctx::CVP {
-                                        velocity: i.magnitude,
-                                        position: 0.,
-                                        current: 0.,
-                                    }
-                                } else {
-                                    crate::motor_ctx::CVP::default()
-                                };
-
-                                motor.input_cvp = Some(cvp);
-                                motor.request_input =
-                                    Some((time::Instant::now(), RequestedMotorInput::Impulse(i)))
-                            }
-                            MotorInput::Custom(c) => {
-                                let cvps = c
-                                    .into_iter()
-                                    .map(|(mag, time)| {
-                                        let cvp = crate::motor_ctx::CVP {
-                                            velocity: mag,
-                                            position: 0.,
-                                            current: 0.,
-                                        };
-                                        (cvp, time)
-                                    })
-                                    .collect::<Vec<_>>();
-
-                                let cvp =
-                                    cvps.iter().next().map(|(cvp, _)| *cvp).unwrap_or_default();
-
-                                motor.input_cvp = Some(cvp);
-                                motor.request_input =
-                                    Some((time::Instant::now(), RequestedMotorInput::Custom(cvps)))
-                            }
+                        motor.safety.reset();
+                        if !motor.backend_specific.remove_next_recv {
+                            motor.set_waveform(waveform, start);
                         }
                     }
                 }
+                FourierCmd::Follow(cvp) => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        motor.follow(cvp);
+                    }
+                }
                 FourierCmd::StopWaveform => {
                     if let Some(motor) = connections.get_mut(&ip) {
-                        motor.input_cvp = Some(crate::motor_ctx::CVP {
-                            velocity: 0.,
-                            position: 0.,
-                            current: 0.,
-                        });
+                        motor.hold();
                         let _ = err_tx.send((ip, FourierResponse::EndWaveform));
                     }
                 }
-                _ => (),
+                FourierCmd::EStop => {
+                    estopped = true;
+                    for (motor_ip, motor) in connections.iter_mut() {
+                        motor.stop();
+                        motor.send_stop(&mut ring)?;
+                        let _ = err_tx.send((*motor_ip, FourierResponse::EndWaveform));
+                    }
+                }
+                FourierCmd::Rearm => {
+                    estopped = false;
+                    for motor in connections.values_mut() {
+                        motor.safety.release();
+                    }
+                }
+                FourierCmd::Heartbeat => (),
+                FourierCmd::SetWatchdog(timeout) => watchdog.set_timeout(timeout),
+                FourierCmd::SetLimits(limits) => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        motor.set_limits(limits);
+                    }
+                }
+                FourierCmd::ReadParams(group) => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        motor
+                            .backend_specific
+                            .queue_params(ParamRequest::Read(group));
+                    }
+                }
+                FourierCmd::WriteParams(group, values) => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        motor
+                            .backend_specific
+                            .queue_params(ParamRequest::Write(group, values));
+                    }
+                }
+                FourierCmd::SaveConfig => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        motor
+                            .backend_specific
+                            .queue_params(ParamRequest::SaveConfig);
+                    }
+                }
+                FourierCmd::Reboot => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        motor.backend_specific.queue_params(ParamRequest::Reboot);
+                    }
+                }
+                FourierCmd::ReadErrors => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        motor
+                            .backend_specific
+                            .queue_params(ParamRequest::ReadErrors(None));
+                    }
+                }
+                FourierCmd::ClearErrors => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        motor
+                            .backend_specific
+                            .queue_params(ParamRequest::ClearErrors);
+                    }
+                }
+                FourierCmd::StartProcedure(_) if estopped => {
+                    let _ = err_tx.send((ip, FourierResponse::EStopActive));
+                }
+                FourierCmd::StartProcedure(procedure) => {
+                    if let Some(motor) = connections.get_mut(&ip) {
+                        // procedures take over the motor from any running waveform
+                        motor.request_input = None;
+                        motor.safety.reset();
+                        let status = motor.backend_specific.start_procedure(procedure);
+                        let _ = err_tx.send((ip, status.into()));
+                    }
+                }
+                FourierCmd::CancelProcedure => {
+                    let status = connections
+                        .get_mut(&ip)
+                        .and_then(|motor| motor.backend_specific.cancel_procedure());
+                    if let Some(status) = status {
+                        let _ = err_tx.send((ip, status.into()));
+                    }
+                }
             }
         }
 
+        if watchdog.check() {
+            estopped = true;
+            for motor in connections.values_mut() {
+                motor.stop();
+                motor.send_stop(&mut ring)?;
+            }
+            // sent even without motors, the ui latches its emergency stop either way
+            let _ = err_tx.send((FourierAddr::UNSPECIFIED, FourierResponse::WatchdogExpired));
+        }
+
         let mut completed = ring.completion().next();
         while let Some(entry) = completed {
             completed = ring.completion().next();
 
-            let ip = Ipv4Addr::from_bits(entry.user_data() as u32);
+            let Some(ip) = connection_ids.get(&(entry.user_data() as u32)).copied() else {
+                continue;
+            };
 
             let Some(motor) = connections.get_mut(&ip) else {
                 continue;
             };
 
+            if (entry.user_data() >> GENERATION_SHIFT) as u32 != motor.backend_specific.generation {
+                // the request was given up on for a stop
+                continue;
+            }
+
             let result = entry.result();
+
+            // the request that was in flight when the motor was removed has finished,
+            // either by receiving a reply or timing out.
+            if motor.backend_specific.remove_next_recv
+                && entry.user_data() & (1 << 32) == 0
+                && result != -libc::ECANCELED
+            {
+                if !motor.backend_specific.disabling {
+                    motor.backend_specific.disabling = true;
+                    motor
+                        .prepare_input_msg(|prep| {
+                            prep.queue(&mut ring.submission()).unwrap();
+                            ring.submit()?;
+                            Ok(())
+                        })
+                        .unwrap();
+                    continue;
+                }
+
+                // dropping the backend closes its socket
+                connection_ids.remove(&motor.backend_specific.id);
+                connections.remove(&ip);
+                let _ = err_tx.send((ip, FourierResponse::Removed));
+
+                if request_shutdown && connections.is_empty() {
+                    // no more in flight requests and all motors have been shut down
+                    let _ = err_tx.send((ip, FourierResponse::ShutdownComplete));
+                    return Ok(());
+                }
+                continue;
+            }
+
             if result >= 0 {
                 let len = result as usize;
 
+                if let Some(response) = motor.backend_specific.complete_params(len) {
+                    if let Some(response) = response {
+                        let _ = err_tx.send((ip, response));
+                    }
+
+                    motor
+                        .prepare_input_msg(|prep| {
+                            prep.queue(&mut ring.submission()).unwrap();
+                            ring.submit()?;
+                            Ok(())
+                        })
+                        .unwrap();
+                    continue;
+                }
+
                 let Some(cvp) = motor.parse_cvp(len, &mut ring) else {
                     // motor does not have data available
                     // an entry has already been submitted.
                     continue;
                 };
 
-                let should_drop_motor = motor.backend_specific.remove_next_recv;
+                motor.check_output(&cvp);
 
-                if should_drop_motor {
-                    connections.remove(&ip);
-                    if request_shutdown && connections.is_empty() {
-                        // no more in flight requests and all motors have been shut down
-                        return Ok(());
-                    }
-                    // motor has been idled
-                    continue;
+                if let Some(status) = motor.update_homing(&cvp) {
+                    let _ = err_tx.send((ip, status.into()));
                 }
 
                 // updates `input_cvp` from `request_input`
                 motor.update_input(&err_tx);
 
+                let _ = err_tx.send((ip, FourierResponse::OutputCVP(cvp, time::Instant::now())));
+
                 if let Some(controller) = &mut motor.motor_config.controller {
                     let input = motor.input_cvp.unwrap_or_default();
 
@@ -629,9 +1289,6 @@ pub fn event_loop(
                         FourierResponse::ControllerAdjustedCVP(new_input, time::Instant::now()),
                     ));
                     motor.input_cvp = Some(new_input);
-                } else {
-                    let _ =
-                        err_tx.send((ip, FourierResponse::OutputCVP(cvp, time::Instant::now())));
                 }
 
                 motor
@@ -641,6 +1298,10 @@ pub fn event_loop(
                         Ok(())
                     })
                     .unwrap();
+
+                if let Some(violation) = motor.safety.take_violation() {
+                    let _ = err_tx.send((ip, FourierResponse::LimitTripped(violation)));
+                }
             } else {
                 let errno = -result;
 
@@ -653,6 +1314,37 @@ pub fn event_loop(
                     libc::ECANCELED => (),
                     libc::ETIME => {
                         motor.backend_specific.enabled = false;
+                        match motor.backend_specific.param_in_flight.take() {
+                            // the motor may go down before it replies
+                            Some(ParamRequest::Reboot) => {
+                                let _ = err_tx.send((ip, FourierResponse::Rebooting));
+                            }
+                            Some(request @ ParamRequest::CalibrationPoll { started, .. })
+                                if !motor.backend_specific.procedure_cancelled
+                                    && started.elapsed() < procedure::CALIBRATION_TIMEOUT =>
+                            {
+                                motor.backend_specific.params.push_front(request);
+                            }
+                            Some(request) if request.is_calibration() => {
+                                let status = ProcedureStatus::Failed(
+                                    "calibration failed: the motor stopped replying".into(),
+                                );
+                                let _ = err_tx.send((ip, status.into()));
+                            }
+                            Some(request) => {
+                                let _ = err_tx.send((
+                                    ip,
+                                    FourierResponse::ParamError(format!("no reply to {request:?}")),
+                                ));
+                            }
+                            None => (),
+                        }
+                        if motor.backend_specific.homing.take().is_some() {
+                            let status = ProcedureStatus::Failed(
+                                "homing failed: the motor stopped replying".into(),
+                            );
+                            let _ = err_tx.send((ip, status.into()));
+                        }
                         let _ = err_tx.send((ip, FourierResponse::Timeout));
                         motor
                             .prepare_input_msg(|prep| {
@@ -663,6 +1355,7 @@ pub fn event_loop(
                             .unwrap();
                     }
                     _ => {
+                        motor.backend_specific.param_in_flight = None;
                         let _ = err_tx.send((
                             ip,
                             FourierResponse::Error(std::io::Error::from_raw_os_error(errno)),
@@ -0,0 +1,202 @@
+use super::FourierAddr;
+
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+// aios motors listen for this broadcast on their service port and answer with a json description.
+const DISCOVERY_PORT: u16 = 2334;
+const DISCOVERY_REQUEST: &[u8] = b"Is any fourier smart server here?";
+const DISCOVERY_WINDOW: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct DiscoveredMotor {
+    pub addr: FourierAddr,
+    pub serial_number: Option<String>,
+    pub firmware_version: Option<String>,
+    pub reply: String,
+}
+
+impl DiscoveredMotor {
+    fn parse(ip: Ipv4Addr, reply: &[u8]) -> Self {
+        let reply = String::from_utf8_lossy(reply).into_owned();
+        let json = serde_json::from_str::<serde_json::Value>(&reply).ok();
+
+        // the key names differ between firmware releases.
+        let field = |keys: &[&str]| {
+            let json = json.as_ref()?;
+            keys.iter().find_map(|key| match json.get(key)? {
+                serde_json::Value::String(s) => Some(s.clone()),
+                serde_json::Value::Null => None,
+                other => Some(other.to_string()),
+            })
+        };
+
+        Self {
+            addr: ip.into(),
+            serial_number: field(&["serial_number", "SN", "sn"]),
+            firmware_version: field(&["fw_version", "firmware_version", "version"]),
+            reply,
+        }
+    }
+}
+
+// broadcasts the discovery request from `local` and collects every reply that arrives within `window`.
+pub fn discover(
+    local: Ipv4Addr,
+    prefix: u8,
+    window: Duration,
+) -> std::io::Result<Vec<DiscoveredMotor>> {
+    let mask = u32::MAX
+        .checked_shl(32 - prefix.min(32) as u32)
+        .unwrap_or(0);
+    let broadcast = Ipv4Addr::from(local.to_bits() | !mask);
+
+    let socket = UdpSocket::bind((local, 0))?;
+    socket.set_broadcast(true)?;
+    socket.send_to(DISCOVERY_REQUEST, (broadcast, DISCOVERY_PORT))?;
+
+    let deadline = Instant::now() + window;
+    let mut found: Vec<DiscoveredMotor> = Vec::new();
+    let mut buf = [0; 1024];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        match socket.recv_from(&mut buf) {
+            Ok((len, from)) => {
+                let IpAddr::V4(ip) = from.ip() else {
+                    continue;
+                };
+
+                if found.iter().any(|m| m.addr == ip.into()) {
+                    continue;
+                }
+                found.push(DiscoveredMotor::parse(ip, &buf[..len]));
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    found.sort_by_key(|m| m.addr.ip());
+    Ok(found)
+}
+
+#[derive(Default)]
+pub struct Discovery {
+    networks: sysinfo::Networks,
+    scan: Option<mpsc::Receiver<std::io::Result<Vec<DiscoveredMotor>>>>,
+    found: Vec<DiscoveredMotor>,
+    error: Option<String>,
+}
+
+impl Discovery {
+    fn start(&mut self, local: Ipv4Addr, prefix: u8) {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(discover(local, prefix, DISCOVERY_WINDOW));
+        });
+        self.scan = Some(rx);
+        self.error = None;
+    }
+
+    // returns the address of the motor the user picked from the scan results.
+    pub(crate) fn display(&mut self, ui: &mut egui::Ui) -> Option<FourierAddr> {
+        if let Some(scan) = &self.scan {
+            match scan.try_recv() {
+                Ok(Ok(found)) => {
+                    self.found = found;
+                    self.scan = None;
+                }
+                Ok(Err(e)) => {
+                    self.error = Some(e.to_string());
+                    self.scan = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    ui.ctx().request_repaint_after(Duration::from_millis(50));
+                }
+                Err(mpsc::TryRecvError::Disconnected) => self.scan = None,
+            }
+        }
+
+        let mut picked = None;
+
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(self.scan.is_none(), |ui| {
+                ui.menu_button("scan", |ui| {
+                    self.networks.refresh(true);
+
+                    let mut start = None;
+                    for (interface_name, network) in &self.networks {
+                        for ip_network in network.ip_networks() {
+                            let IpAddr::V4(local) = ip_network.addr else {
+                                continue;
+                            };
+
+                            if local.is_loopback() {
+                                continue;
+                            }
+
+                            if ui
+                                .button(format!("[{interface_name}] {local}/{}", ip_network.prefix))
+                                .clicked()
+                            {
+                                start = Some((local, ip_network.prefix));
+                                ui.close_menu();
+                            }
+                        }
+                    }
+
+                    if let Some((local, prefix)) = start {
+                        self.start(local, prefix);
+                    }
+                });
+            });
+
+            if self.scan.is_some() {
+                ui.spinner();
+                ui.label("scanning...");
+            } else if let Some(e) = &self.error {
+                ui.label(format!("scan failed: {e}"));
+            } else if !self.found.is_empty() {
+                ui.label(format!("found {} motor(s)", self.found.len()));
+            }
+        });
+
+        for motor in &self.found {
+            let DiscoveredMotor {
+                addr,
+                serial_number,
+                firmware_version,
+                reply,
+            } = motor;
+
+            let serial_number = serial_number.as_deref().unwrap_or("unknown");
+            let firmware_version = firmware_version.as_deref().unwrap_or("unknown");
+
+            if ui
+                .button(format!(
+                    "{addr}  serial: {serial_number}  firmware: {firmware_version}"
+                ))
+                .on_hover_text(reply)
+                .clicked()
+            {
+                picked = Some(*addr);
+            }
+        }
+
+        picked
+    }
+}
@@ -0,0 +1,142 @@
+// the aios firmware is derived from odrive, and shares its axis states and error flags.
+
+const AXIS_STATES: [&str; 9] = [
+    "undefined",
+    "idle",
+    "startup sequence",
+    "full calibration sequence",
+    "motor calibration",
+    "sensorless control",
+    "encoder index search",
+    "encoder offset calibration",
+    "closed loop control",
+];
+
+pub fn axis_state_name(state: u64) -> &'static str {
+    AXIS_STATES
+        .get(state as usize)
+        .copied()
+        .unwrap_or("unknown")
+}
+
+const AXIS_ERRORS: &[(u32, &str)] = &[
+    (0x1, "invalid state"),
+    (0x2, "dc bus under voltage"),
+    (0x4, "dc bus over voltage"),
+    (0x8, "current measurement timeout"),
+    (0x10, "brake resistor disarmed"),
+    (0x20, "motor disarmed"),
+    (0x40, "motor failed"),
+    (0x80, "sensorless estimator failed"),
+    (0x100, "encoder failed"),
+    (0x200, "controller failed"),
+    (0x400, "position control during sensorless"),
+    (0x800, "watchdog timer expired"),
+    (0x1000, "min endstop pressed"),
+    (0x2000, "max endstop pressed"),
+    (0x4000, "estop requested"),
+    (0x20000, "homing without endstop"),
+    (0x40000, "over temperature"),
+];
+
+const MOTOR_ERRORS: &[(u32, &str)] = &[
+    (0x1, "phase resistance out of range"),
+    (0x2, "phase inductance out of range"),
+    (0x4, "adc failed"),
+    (0x8, "drv fault"),
+    (0x10, "control deadline missed"),
+    (0x20, "not implemented motor type"),
+    (0x40, "brake current out of range"),
+    (0x80, "modulation magnitude"),
+    (0x100, "brake deadtime violation"),
+    (0x200, "unexpected timer callback"),
+    (0x400, "current sense saturation"),
+    (0x1000, "current limit violation"),
+];
+
+const ENCODER_ERRORS: &[(u32, &str)] = &[
+    (0x1, "unstable gain"),
+    (0x2, "cpr and pole pairs mismatch"),
+    (0x4, "no response"),
+    (0x8, "unsupported encoder mode"),
+    (0x10, "illegal hall state"),
+    (0x20, "index not found yet"),
+    (0x40, "absolute spi timeout"),
+    (0x80, "absolute spi communication failure"),
+    (0x100, "absolute spi not ready"),
+];
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct AxisErrors {
+    // the axis state reported when the motor refused to enable, if that is what prompted the query.
+    pub state: Option<u64>,
+    pub axis: u32,
+    pub motor: u32,
+    pub encoder: u32,
+}
+
+impl AxisErrors {
+    pub fn any(&self) -> bool {
+        self.axis != 0 || self.motor != 0 || self.encoder != 0
+    }
+
+    pub(crate) fn parse(
+        state: Option<u64>,
+        reply: &serde_json::Map<String, serde_json::Value>,
+    ) -> Option<Self> {
+        // newer firmware nests each register in an object along with its name.
+        let register = |name: &str| {
+            let value = reply.get(name)?;
+            let value = value.get("error").unwrap_or(value);
+            value.as_u64().map(|v| v as u32)
+        };
+
+        Some(Self {
+            state,
+            axis: register("axis")?,
+            motor: register("motor").unwrap_or(0),
+            encoder: register("encoder").unwrap_or(0),
+        })
+    }
+}
+
+fn write_flags(
+    f: &mut core::fmt::Formatter<'_>,
+    register: &str,
+    bits: u32,
+    names: &[(u32, &str)],
+) -> core::fmt::Result {
+    if bits == 0 {
+        return Ok(());
+    }
+
+    write!(f, "{register}:")?;
+    let mut unknown = bits;
+    for (flag, name) in names {
+        if bits & flag != 0 {
+            write!(f, " {name},")?;
+            unknown &= !flag;
+        }
+    }
+
+    if unknown != 0 {
+        write!(f, " unknown ({unknown:#x}),")?;
+    }
+    write!(f, " ")
+}
+
+impl core::fmt::Display for AxisErrors {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if let Some(state) = self.state {
+            write!(f, "[{}] ", axis_state_name(state))?;
+        }
+
+        if !self.any() {
+            return write!(f, "no errors");
+        }
+
+        write_flags(f, "axis", self.axis, AXIS_ERRORS)?;
+        write_flags(f, "motor", self.motor, MOTOR_ERRORS)?;
+        write_flags(f, "encoder", self.encoder, ENCODER_ERRORS)
+    }
+}
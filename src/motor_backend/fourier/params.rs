@@ -0,0 +1,336 @@
+use super::errors::axis_state_name;
+use super::procedure::{
+    CALIBRATION_GRACE, CALIBRATION_STATE, CALIBRATION_TIMEOUT, IDLE_STATE, ProcedureStatus,
+};
+use super::service;
+use super::{AxisErrors, FourierAddr, FourierCmd, FourierResponse};
+
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+
+pub type ParamValues = BTreeMap<String, f64>;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum ParamGroup {
+    // pid gains and the velocity limit
+    Controller,
+    // current limits and current loop bandwidth
+    Motor,
+    // limits used by the on-board trapezoidal trajectory planner
+    Trajectory,
+    // the lower and upper position limit, at the endstops' offsets
+    MinPosition,
+    MaxPosition,
+}
+
+impl ParamGroup {
+    pub const ALL: [Self; 5] = [
+        Self::Controller,
+        Self::Motor,
+        Self::Trajectory,
+        Self::MinPosition,
+        Self::MaxPosition,
+    ];
+
+    fn target(&self) -> &'static str {
+        match self {
+            Self::Controller => service::CONTROLLER_CONFIG,
+            Self::Motor => service::MOTOR_CONFIG,
+            Self::Trajectory => service::TRAP_TRAJ,
+            Self::MinPosition => service::MIN_ENDSTOP_CONFIG,
+            Self::MaxPosition => service::MAX_ENDSTOP_CONFIG,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Controller => "controller",
+            Self::Motor => "motor",
+            Self::Trajectory => "trajectory",
+            Self::MinPosition => "min position",
+            Self::MaxPosition => "max position",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum ParamRequest {
+    Read(ParamGroup),
+    Write(ParamGroup, ParamValues),
+    // reads the group back after a write and compares it against what was written.
+    Verify(ParamGroup, ParamValues),
+    SaveConfig,
+    Reboot,
+    // carries the axis state that prompted the query, if any.
+    ReadErrors(Option<u64>),
+    ClearErrors,
+    SetZero,
+    Calibrate,
+    // polls the axis state until the calibration has finished.
+    CalibrationPoll {
+        started: std::time::Instant,
+        last_state: Option<u64>,
+    },
+    // returns the axis to idle, failing the calibration with the given reason.
+    AbortCalibration(String),
+}
+
+impl ParamRequest {
+    pub(crate) fn is_calibration(&self) -> bool {
+        matches!(self, Self::Calibrate | Self::CalibrationPoll { .. })
+    }
+
+    pub(crate) fn serialize(&self, buf: &mut Vec<u8>) {
+        let msg = match self {
+            Self::Read(group) | Self::Verify(group, _) => service::get(group.target()),
+            Self::Write(group, values) => service::set(group.target(), values),
+            Self::SaveConfig => service::save_config(),
+            Self::Reboot => service::reboot(),
+            Self::ReadErrors(_) => service::read_errors(),
+            Self::ClearErrors => service::clear_errors(),
+            Self::SetZero => service::set_linear_count(0),
+            Self::Calibrate => service::request_state(CALIBRATION_STATE),
+            Self::CalibrationPoll { .. } => service::read_state(),
+            Self::AbortCalibration(_) => service::request_state(IDLE_STATE),
+        };
+
+        buf.clear();
+        serde_json::to_writer(&mut *buf, &msg).expect("serialization error");
+    }
+
+    // what to report once the motor replied, along with a request that has to follow it.
+    pub(crate) fn complete(self, reply: &[u8]) -> (Option<FourierResponse>, Option<Self>) {
+        let reply = match parse_reply(reply) {
+            Ok(reply) => reply,
+            Err(e) if self.is_calibration() => {
+                let status = ProcedureStatus::Failed(format!("calibration failed: {e}"));
+                return (Some(status.into()), None);
+            }
+            Err(e) => return (Some(FourierResponse::ParamError(e)), None),
+        };
+
+        match self {
+            Self::Read(group) => (
+                Some(FourierResponse::Params(group, numeric_fields(&reply))),
+                None,
+            ),
+            Self::Write(group, written) => (None, Some(Self::Verify(group, written))),
+            Self::Verify(group, written) => {
+                let values = numeric_fields(&reply);
+                let mismatched = written
+                    .iter()
+                    .filter(|(name, expected)| match values.get(*name) {
+                        Some(actual) => (actual - *expected).abs() > 1e-4 * expected.abs().max(1.),
+                        None => true,
+                    })
+                    .map(|(name, _)| name.clone())
+                    .collect();
+
+                let response = FourierResponse::ParamsWritten {
+                    group,
+                    values,
+                    mismatched,
+                };
+                (Some(response), None)
+            }
+            Self::SaveConfig => (Some(FourierResponse::ConfigSaved), None),
+            Self::Reboot => (Some(FourierResponse::Rebooting), None),
+            // read the registers back so the ui sees them cleared
+            Self::ClearErrors => (None, Some(Self::ReadErrors(None))),
+            Self::ReadErrors(state) => {
+                let response = match AxisErrors::parse(state, &reply) {
+                    Some(errors) => FourierResponse::AxisError(errors),
+                    None => {
+                        FourierResponse::ParamError(format!("unexpected error reply: {reply:?}"))
+                    }
+                };
+                (Some(response), None)
+            }
+            Self::SetZero => {
+                let status = ProcedureStatus::Done("position zeroed".into());
+                (Some(status.into()), None)
+            }
+            Self::Calibrate => {
+                let status = ProcedureStatus::Running("calibration requested".into());
+                let poll = Self::CalibrationPoll {
+                    started: std::time::Instant::now(),
+                    last_state: None,
+                };
+                (Some(status.into()), Some(poll))
+            }
+            Self::CalibrationPoll {
+                started,
+                last_state,
+            } => {
+                let Some(state) = reply.get("current_state").and_then(|s| s.as_u64()) else {
+                    let status =
+                        ProcedureStatus::Failed(format!("unexpected state reply: {reply:?}"));
+                    return (Some(status.into()), None);
+                };
+
+                let elapsed = started.elapsed();
+                if state == IDLE_STATE && elapsed > CALIBRATION_GRACE {
+                    // a failed calibration also ends in idle, the error registers tell them apart
+                    let status = ProcedureStatus::Done(format!(
+                        "calibration finished after {:.1}s",
+                        elapsed.as_secs_f64()
+                    ));
+                    (Some(status.into()), Some(Self::ReadErrors(None)))
+                } else if elapsed > CALIBRATION_TIMEOUT {
+                    let reason = format!(
+                        "calibration still in {} after {CALIBRATION_TIMEOUT:?}",
+                        axis_state_name(state)
+                    );
+                    (None, Some(Self::AbortCalibration(reason)))
+                } else {
+                    let status: Option<FourierResponse> = (last_state != Some(state))
+                        .then(|| ProcedureStatus::Running(axis_state_name(state).into()).into());
+                    let poll = Self::CalibrationPoll {
+                        started,
+                        last_state: Some(state),
+                    };
+                    (status, Some(poll))
+                }
+            }
+            Self::AbortCalibration(reason) => (Some(ProcedureStatus::Failed(reason).into()), None),
+        }
+    }
+}
+
+fn parse_reply(reply: &[u8]) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let reply = serde_json::from_slice::<serde_json::Value>(reply)
+        .map_err(|e| format!("unexpected reply: {e}"))?;
+
+    let serde_json::Value::Object(fields) = reply else {
+        return Err(format!("unexpected reply: {reply}"));
+    };
+
+    let status = fields.get("status").and_then(|s| s.as_str());
+    if let Some(status) = status.filter(|status| *status != "OK") {
+        return Err(format!("motor rejected the request: {status}"));
+    }
+
+    Ok(fields)
+}
+
+fn numeric_fields(reply: &serde_json::Map<String, serde_json::Value>) -> ParamValues {
+    reply
+        .iter()
+        .filter_map(|(name, value)| Some((name.clone(), value.as_f64()?)))
+        .collect()
+}
+
+#[derive(Default)]
+struct GroupCache {
+    // values as last read from the motor
+    values: ParamValues,
+    storage: BTreeMap<String, String>,
+}
+
+#[derive(Default)]
+pub struct ParamPanel {
+    groups: BTreeMap<ParamGroup, GroupCache>,
+    status: Option<String>,
+}
+
+impl ParamPanel {
+    pub(crate) fn update(&mut self, response: &FourierResponse) {
+        match response {
+            FourierResponse::Params(group, values) => {
+                self.set_values(*group, values);
+                self.status = Some(format!("read {} parameters", group.name()));
+            }
+            FourierResponse::ParamsWritten {
+                group,
+                values,
+                mismatched,
+            } => {
+                self.set_values(*group, values);
+                self.status = Some(if mismatched.is_empty() {
+                    format!("wrote and verified {} parameters", group.name())
+                } else {
+                    format!(
+                        "{} parameters did not read back as written: {}",
+                        group.name(),
+                        mismatched.join(", ")
+                    )
+                });
+            }
+            FourierResponse::ParamError(e) => self.status = Some(e.clone()),
+            FourierResponse::ConfigSaved => self.status = Some("saved to flash".into()),
+            FourierResponse::Rebooting => self.status = Some("rebooting".into()),
+            _ => (),
+        }
+    }
+
+    fn set_values(&mut self, group: ParamGroup, values: &ParamValues) {
+        let cache = self.groups.entry(group).or_default();
+        cache.storage = values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.to_string()))
+            .collect();
+        cache.values = values.clone();
+    }
+
+    pub(crate) fn display(
+        &mut self,
+        addr: FourierAddr,
+        fourier_tx: &mpsc::Sender<(FourierAddr, FourierCmd)>,
+        ui: &mut egui::Ui,
+    ) {
+        ui.collapsing("parameters", |ui| {
+            for group in ParamGroup::ALL {
+                let cache = self.groups.entry(group).or_default();
+
+                ui.horizontal(|ui| {
+                    ui.label(group.name());
+                    if ui.button("read").clicked() {
+                        let _ = fourier_tx.send((addr, FourierCmd::ReadParams(group)));
+                    }
+
+                    // only the parameters that were edited are sent
+                    let edited = cache
+                        .storage
+                        .iter()
+                        .filter_map(|(name, value)| {
+                            let value = value.parse::<f64>().ok()?;
+                            (cache.values.get(name) != Some(&value)).then(|| (name.clone(), value))
+                        })
+                        .collect::<ParamValues>();
+
+                    if ui
+                        .add_enabled(!edited.is_empty(), egui::Button::new("write"))
+                        .clicked()
+                    {
+                        let _ = fourier_tx.send((addr, FourierCmd::WriteParams(group, edited)));
+                    }
+                });
+
+                ui.indent(group.name(), |ui| {
+                    for (name, value) in cache.storage.iter_mut() {
+                        ui.horizontal(|ui| {
+                            ui.label(name);
+                            ui.text_edit_singleline(value);
+                            if value.parse::<f64>().is_err() {
+                                ui.label("invalid value");
+                            }
+                        });
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("save to flash").clicked() {
+                    let _ = fourier_tx.send((addr, FourierCmd::SaveConfig));
+                }
+                if ui.button("reboot").clicked() {
+                    let _ = fourier_tx.send((addr, FourierCmd::Reboot));
+                }
+            });
+
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+    }
+}
@@ -0,0 +1,195 @@
+use super::{FourierAddr, FourierCmd, FourierResponse};
+use crate::motor_ctx::CVP;
+
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Procedure {
+    // motor resistance/inductance measurement followed by the encoder offset calibration
+    Calibrate,
+    // makes the current position the motor's zero
+    SetZero,
+    Home(HomingConfig),
+}
+
+// drives the motor at `velocity` until the measured current stays above `current_threshold`,
+// which happens once it pushes against a hard stop. that position then becomes the zero.
+#[derive(Clone, Copy, Debug)]
+pub struct HomingConfig {
+    pub velocity: f64,
+    pub current_threshold: f64,
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub enum ProcedureStatus {
+    Running(String),
+    Done(String),
+    Failed(String),
+}
+
+// calibration leaves the axis idle once it is done, or earlier on failure.
+pub(crate) const CALIBRATION_STATE: u64 = 3;
+pub(crate) const IDLE_STATE: u64 = 1;
+pub(crate) const CALIBRATION_TIMEOUT: Duration = Duration::from_secs(60);
+// the axis may still report idle right after the calibration was requested.
+pub(crate) const CALIBRATION_GRACE: Duration = Duration::from_secs(1);
+
+// the current spikes while the motor accelerates, so the first samples are ignored.
+const HOMING_SETTLE: Duration = Duration::from_millis(300);
+const HOMING_CONFIRM_SAMPLES: u32 = 5;
+
+pub(crate) struct Homing {
+    pub config: HomingConfig,
+    started: Instant,
+    over_threshold: u32,
+}
+
+impl Homing {
+    pub fn new(config: HomingConfig) -> Self {
+        Self {
+            config,
+            started: Instant::now(),
+            over_threshold: 0,
+        }
+    }
+
+    pub fn command(&self) -> CVP {
+        CVP {
+            velocity: self.config.velocity,
+            ..Default::default()
+        }
+    }
+
+    // `Some` once homing has finished, either at the hard stop or with an error.
+    pub fn update(&mut self, measured: &CVP) -> Option<Result<(), String>> {
+        let elapsed = self.started.elapsed();
+        if elapsed > self.config.timeout {
+            return Some(Err(format!(
+                "no hard stop found within {:?}",
+                self.config.timeout
+            )));
+        }
+
+        if elapsed < HOMING_SETTLE {
+            return None;
+        }
+
+        if measured.current.abs() >= self.config.current_threshold {
+            self.over_threshold += 1;
+        } else {
+            self.over_threshold = 0;
+        }
+
+        (self.over_threshold >= HOMING_CONFIRM_SAMPLES).then_some(Ok(()))
+    }
+}
+
+pub struct ProcedurePanel {
+    velocity_storage: String,
+    current_storage: String,
+    timeout_storage: String,
+    status: Option<ProcedureStatus>,
+}
+
+impl Default for ProcedurePanel {
+    fn default() -> Self {
+        Self {
+            velocity_storage: "1".into(),
+            current_storage: "2".into(),
+            timeout_storage: "10s".into(),
+            status: None,
+        }
+    }
+}
+
+impl ProcedurePanel {
+    pub(crate) fn update(&mut self, status: ProcedureStatus) {
+        self.status = Some(status);
+    }
+
+    fn homing_config(&self) -> Option<HomingConfig> {
+        let timeout = self
+            .timeout_storage
+            .parse::<duration_string::DurationString>()
+            .ok()?;
+
+        Some(HomingConfig {
+            velocity: self.velocity_storage.parse().ok()?,
+            current_threshold: self.current_storage.parse::<f64>().ok()?.abs(),
+            timeout: timeout.into(),
+        })
+    }
+
+    pub(crate) fn display(
+        &mut self,
+        addr: FourierAddr,
+        fourier_tx: &mpsc::Sender<(FourierAddr, FourierCmd)>,
+        estopped: bool,
+        ui: &mut egui::Ui,
+    ) {
+        ui.collapsing("procedures", |ui| {
+            let running = matches!(self.status, Some(ProcedureStatus::Running(_)));
+            let start = |procedure| {
+                let _ = fourier_tx.send((addr, FourierCmd::StartProcedure(procedure)));
+            };
+
+            ui.add_enabled_ui(!running && !estopped, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("calibrate motor and encoder").clicked() {
+                        start(Procedure::Calibrate);
+                    }
+                    if ui.button("set current position as zero").clicked() {
+                        start(Procedure::SetZero);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("homing velocity");
+                    ui.text_edit_singleline(&mut self.velocity_storage);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("hard stop current");
+                    ui.text_edit_singleline(&mut self.current_storage);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("homing timeout");
+                    ui.text_edit_singleline(&mut self.timeout_storage);
+                });
+
+                let config = self.homing_config();
+                let home = ui.add_enabled(config.is_some(), egui::Button::new("home"));
+                if let (true, Some(config)) = (home.clicked(), config) {
+                    start(Procedure::Home(config));
+                }
+            });
+
+            if running && ui.button("cancel").clicked() {
+                let _ = fourier_tx.send((addr, FourierCmd::CancelProcedure));
+            }
+
+            match &self.status {
+                Some(ProcedureStatus::Running(step)) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(step);
+                    });
+                }
+                Some(ProcedureStatus::Done(result)) => {
+                    ui.label(result);
+                }
+                Some(ProcedureStatus::Failed(e)) => {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+                None => (),
+            }
+        });
+    }
+}
+
+impl From<ProcedureStatus> for FourierResponse {
+    fn from(status: ProcedureStatus) -> Self {
+        FourierResponse::Procedure(status)
+    }
+}
@@ -0,0 +1,64 @@
+// the json requests understood by the motor's service port. the aios firmware mirrors odrive's object
+// tree under `/m1`, so the targets and property names are odrive's.
+use super::ParamValues;
+
+use serde_json::{Map, Value, json};
+
+// configuration requests go to the motor's service port rather than the realtime port.
+pub(crate) const SERVICE_PORT: u16 = 2334;
+
+pub(crate) const CONTROLLER_CONFIG: &str = "/m1/controller/config";
+pub(crate) const MOTOR_CONFIG: &str = "/m1/motor/config";
+pub(crate) const TRAP_TRAJ: &str = "/m1/trap_traj";
+// the position limits are the endstops, which trip the axis once the position passes their offset.
+pub(crate) const MIN_ENDSTOP_CONFIG: &str = "/m1/min_endstop/config";
+pub(crate) const MAX_ENDSTOP_CONFIG: &str = "/m1/max_endstop/config";
+
+const ROOT: &str = "/";
+const ERROR: &str = "/m1/error";
+const ENCODER: &str = "/m1/encoder";
+const REQUESTED_STATE: &str = "/m1/requested_state";
+
+pub(crate) fn get(target: &str) -> Value {
+    json!({ "method": "GET", "reqTarget": target })
+}
+
+pub(crate) fn set(target: &str, values: &ParamValues) -> Value {
+    let mut msg = Map::new();
+    msg.insert("method".into(), "SET".into());
+    msg.insert("reqTarget".into(), target.into());
+    for (name, value) in values {
+        msg.insert(name.clone(), (*value).into());
+    }
+    Value::Object(msg)
+}
+
+pub(crate) fn save_config() -> Value {
+    json!({ "method": "SET", "reqTarget": ROOT, "property": "save_config" })
+}
+
+pub(crate) fn reboot() -> Value {
+    json!({ "method": "SET", "reqTarget": ROOT, "property": "reboot" })
+}
+
+pub(crate) fn read_errors() -> Value {
+    get(ERROR)
+}
+
+pub(crate) fn clear_errors() -> Value {
+    json!({ "method": "SET", "reqTarget": ERROR, "clear_error": true })
+}
+
+// makes `count` the encoder's current position.
+pub(crate) fn set_linear_count(count: i64) -> Value {
+    json!({ "method": "SET", "reqTarget": ENCODER, "set_linear_count": count })
+}
+
+pub(crate) fn request_state(state: u64) -> Value {
+    json!({ "method": "SET", "reqTarget": REQUESTED_STATE, "property": state })
+}
+
+// the reply carries the axis' `current_state`.
+pub(crate) fn read_state() -> Value {
+    get(REQUESTED_STATE)
+}
@@ -4,7 +4,7 @@ use io_uring::squeue::Entry as SQEntry;
 use io_uring::squeue::PushError as SQPushError;
 use io_uring::types::Timespec;
 
-use super::{Backend, RequestedMotorInput};
+use super::{Backend, DEFAULT_WATCHDOG_TIMEOUT, RequestedMotorInput, Watchdog};
 
 use std::os::fd::{AsRawFd, RawFd};
 
@@ -20,7 +20,7 @@ pub struct MotorUiConfig {
     kd_buf: String,
 }
 
-use crate::motor_ctx::{ControlState, MotorConfig};
+use crate::motor_ctx::{ControlState, LimitViolation, MotorConfig, SafetyLimits};
 use std::sync::mpsc;
 use std::time;
 
@@ -171,22 +171,46 @@ impl Default for MotorUiConfig {
 pub enum ProtobufCmd {
     Add(MotorConfig, RawDevice),
     Remove,
-    SetWaveForm(crate::MotorInput),
+    // the waveform begins at the given instant, which may be in the future.
+    SetWaveForm(crate::MotorInput, time::Instant),
+    // the next setpoint of a motor whose waveform follows another motor.
+    Follow(crate::motor_ctx::CVP),
     SetController(ControllerState),
     StopWaveform,
     Shutdown,
+    // stops every connected motor, the path is ignored.
+    EStop,
+    Rearm,
+    Heartbeat,
+    SetWatchdog(Option<time::Duration>),
+    // replaces the safety limits of an added motor. the motor only takes velocities, so a
+    // limit that faults commands it to zero.
+    SetLimits(SafetyLimits),
 }
 
 #[derive(Debug)]
 pub enum ProtobufResponse {
     OutputCVP(crate::motor_ctx::CVP, time::Instant),
+    // the command after the controller, the measurement is still sent as `OutputCVP`.
     ControllerAdjustedCVP(crate::motor_ctx::CVP, time::Instant),
     Error(std::io::Error),
     Timeout,
     DuplicateConnections,
     EndWaveform,
+    LimitTripped(LimitViolation),
+    EStopActive,
+    // sent once for the whole backend, with an empty path.
+    WatchdogExpired,
+    Removed,
+    ShutdownComplete,
 }
 
+// high bits of the user data, the low 32 bits hold the device fd.
+const WRITE_MASK: u64 = 1 << 32;
+const CANCEL_MASK: u64 = 1 << 33;
+// writes carry an id above the masks, to find their buffer once they complete
+const WRITE_ID_SHIFT: u32 = 34;
+
 pub fn event_loop(
     cmd_rx: mpsc::Receiver<(String, ProtobufCmd)>,
     err_tx: mpsc::Sender<(String, ProtobufResponse)>,
@@ -213,18 +237,33 @@ pub fn event_loop(
     const SIZE: u32 = 64;
 
     let mut idx = 10;
+    let mut estopped = false;
+    let mut request_shutdown = false;
+    let mut watchdog = Watchdog::new(Some(DEFAULT_WATCHDOG_TIMEOUT));
 
     loop {
         while let Some((path, cmd)) = match cmd_rx.try_recv() {
             Ok(cmd) => Some(cmd),
             Err(mpsc::TryRecvError::Disconnected) => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "disconnected",
-                ));
+                // the ui is gone, stop the motors rather than leave them at their last setpoint
+                // and close them. the loop returns once they are closed.
+                if !request_shutdown {
+                    estopped = true;
+                    request_shutdown = true;
+                    for motor in connections.values_mut() {
+                        motor.start_removal(&mut ring)?;
+                    }
+
+                    if connections.is_empty() {
+                        return Ok(());
+                    }
+                }
+                None
             }
             _ => None,
         } {
+            watchdog.feed();
+
             match cmd {
                 ProtobufCmd::Add(config, dev) => {
                     if connections.contains_key(&dev.as_raw_fd()) {
@@ -255,118 +294,70 @@ pub fn event_loop(
                             .build()
                             .user_data(dev_id as _);
 
-                    use motor::{MotorDriver, motor_driver::Motor_cmd};
-                    use protobuf::Message;
-
-                    let mut cmd = MotorDriver::new();
-                    cmd.motor_cmd = Some(Motor_cmd::Velocity(0));
-
-                    let buf = cmd.write_to_bytes().unwrap();
-
-                    let write_entry =
-                        Write::new(types::Fd(dev.as_raw_fd()), buf.as_ptr(), buf.len() as _)
-                            .build()
-                            .user_data(dev_id as _)
-                            .flags(io_uring::squeue::Flags::SKIP_SUCCESS);
-
                     while unsafe { ring.submission().push(&rx_multi_entry).is_err() } {
                         ring.submit().expect("could not submit ops");
                     }
 
-                    while unsafe { ring.submission().push(&write_entry).is_err() } {
-                        ring.submit().expect("could not submit ops");
-                    }
+                    let mut backend = ProtobufBackend {
+                        dev,
+                        buf: rx_bufs,
+                        removing: false,
+                        read_done: false,
+                        writes: HashMap::new(),
+                        next_write: 0,
+                    };
+                    backend.queue_velocity(&mut ring, 0)?;
 
-                    ring.submit().expect("could not submit ops");
+                    let mut motor = Backend::new(config, backend, None);
 
-                    connections.insert(
-                        dev_id,
-                        Backend::new(config, ProtobufBackend { dev, buf: rx_bufs }, None),
-                    );
+                    if estopped {
+                        motor.safety.stop();
+                    }
+
+                    connections.insert(dev_id, motor);
                     path_to_connection.insert(path.clone(), dev_id);
                     connection_to_path.insert(dev_id, path.clone());
                 }
                 ProtobufCmd::Remove => {
-                    if let Some(id) = path_to_connection.remove(&path) {
-                        connections.remove(&id);
-                        connection_to_path.remove(&id);
+                    // the connection is dropped once its read has been cancelled
+                    if let Some(motor) = path_to_connection
+                        .get(&path)
+                        .and_then(|id| connections.get_mut(id))
+                    {
+                        motor.start_removal(&mut ring)?;
+                    } else {
+                        let _ = err_tx.send((path, ProtobufResponse::Removed));
+                    }
+                }
+                ProtobufCmd::Shutdown => {
+                    request_shutdown = true;
+                    for motor in connections.values_mut() {
+                        motor.start_removal(&mut ring)?;
+                    }
+
+                    if connections.is_empty() {
+                        let _ = err_tx.send((path, ProtobufResponse::ShutdownComplete));
+                        return Ok(());
                     }
                 }
-                ProtobufCmd::SetWaveForm(waveform) => {
+                ProtobufCmd::SetWaveForm(..) if estopped => {
+                    let _ = err_tx.send((path, ProtobufResponse::EStopActive));
+                }
+                ProtobufCmd::SetWaveForm(waveform, start) => {
                     let Some(id) = path_to_connection.get(&path) else {
                         continue;
                     };
                     if let Some(motor) = connections.get_mut(id) {
-                        use crate::MotorInput;
-                        match waveform {
-                            MotorInput::Constant(c) => {
-                                let cvp = crate::motor_ctx::CVP {
-                                    velocity: c,
-                                    position: 0.,
-                                    current: 0.,
-                                };
-
-                                motor.input_cvp = Some(cvp);
-                                motor.request_input =
-                                    Some((time::Instant::now(), RequestedMotorInput::Cvp(cvp)))
-                            }
-                            MotorInput::Idle => {
-                                let cvp = crate::motor_ctx::CVP::default();
-                                motor.input_cvp = Some(cvp);
-                                motor.request_input =
-                                    Some((time::Instant::now(), RequestedMotorInput::Cvp(cvp)));
-                            }
-                            MotorInput::Step(s) => {
-                                let cvp = if s.delay <= time::Duration::ZERO {
-                                    crate::motor_ctx::CVP {
-                                        velocity: s.magnitude,
-                                        position: 0.,
-                                        current: 0.,
-                                    }
-                                } else {
-                                    crate::motor_ctx::CVP::default()
-                                };
-
-                                motor.input_cvp = Some(cvp);
-                                motor.request_input =
-                                    Some((time::Instant::now(), RequestedMotorInput::Step(s)))
-                            }
-                            MotorInput::Impulse(i) => {
-                                let cvp = if i.delay < time::Duration::ZERO {
-                                    crate::motor_ctx::CVP {
-                                        velocity: i.magnitude,
-                                        position: 0.,
-                                        current: 0.,
-                                    }
-                                } else {
-                                    crate::motor_ctx::CVP::default()
-                                };
-
-                                motor.input_cvp = Some(cvp);
-                                motor.request_input =
-                                    Some((time::Instant::now(), RequestedMotorInput::Impulse(i)))
-                            }
-                            MotorInput::Custom(c) => {
-                                let cvps = c
-                                    .into_iter()
-                                    .map(|(mag, time)| {
-                                        let cvp = crate::motor_ctx::CVP {
-                                            velocity: mag,
-                                            position: 0.,
-                                            current: 0.,
-                                        };
-                                        (cvp, time)
-                                    })
-                                    .collect::<Vec<_>>();
-
-                                let cvp =
-                                    cvps.iter().next().map(|(cvp, _)| *cvp).unwrap_or_default();
-
-                                motor.input_cvp = Some(cvp);
-                                motor.request_input =
-                                    Some((time::Instant::now(), RequestedMotorInput::Custom(cvps)))
-                            }
-                        }
+                        motor.safety.reset();
+                        motor.set_waveform(waveform, start);
+                    }
+                }
+                ProtobufCmd::Follow(cvp) => {
+                    let Some(id) = path_to_connection.get(&path) else {
+                        continue;
+                    };
+                    if let Some(motor) = connections.get_mut(id) {
+                        motor.follow(cvp);
                     }
                 }
                 ProtobufCmd::StopWaveform => {
@@ -374,16 +365,37 @@ pub fn event_loop(
                         continue;
                     };
                     if let Some(motor) = connections.get_mut(id) {
-                        motor.input_cvp = Some(crate::motor_ctx::CVP {
-                            velocity: 0.,
-                            position: 0.,
-                            current: 0.,
-                        });
+                        motor.hold();
                         let _ = err_tx.send((path, ProtobufResponse::EndWaveform));
                     }
                 }
                 ProtobufCmd::SetController(c) => change_controller = Some(c),
-                _ => (),
+                ProtobufCmd::EStop => {
+                    estopped = true;
+                    for (id, motor) in connections.iter_mut() {
+                        motor.stop();
+                        motor.backend_specific.queue_velocity(&mut ring, 0)?;
+                        if let Some(path) = connection_to_path.get(id) {
+                            let _ = err_tx.send((path.clone(), ProtobufResponse::EndWaveform));
+                        }
+                    }
+                }
+                ProtobufCmd::Rearm => {
+                    estopped = false;
+                    for motor in connections.values_mut() {
+                        motor.safety.release();
+                    }
+                }
+                ProtobufCmd::Heartbeat => (),
+                ProtobufCmd::SetWatchdog(timeout) => watchdog.set_timeout(timeout),
+                ProtobufCmd::SetLimits(limits) => {
+                    let Some(id) = path_to_connection.get(&path) else {
+                        continue;
+                    };
+                    if let Some(motor) = connections.get_mut(id) {
+                        motor.set_limits(limits);
+                    }
+                }
             }
             /*
             match cmd {
@@ -395,11 +407,25 @@ pub fn event_loop(
             */
         }
 
+        if watchdog.check() {
+            estopped = true;
+            for motor in connections.values_mut() {
+                motor.stop();
+                motor.backend_specific.queue_velocity(&mut ring, 0)?;
+            }
+            // sent even without motors, the ui latches its emergency stop either way
+            let _ = err_tx.send((String::new(), ProtobufResponse::WatchdogExpired));
+        }
+
         let mut completed = ring.completion().next();
 
         while let Some(entry) = completed {
             completed = ring.completion().next();
 
+            if entry.user_data() & CANCEL_MASK != 0 {
+                continue;
+            }
+
             let id = entry.user_data() as i32;
 
             let Some(motor) = connections.get_mut(&id) else {
@@ -412,6 +438,48 @@ pub fn event_loop(
 
             let result = entry.result();
 
+            if entry.user_data() & WRITE_MASK != 0 {
+                // the kernel is done with the command, its buffer can go
+                motor
+                    .backend_specific
+                    .writes
+                    .remove(&(entry.user_data() >> WRITE_ID_SHIFT));
+                if result < 0 && !motor.backend_specific.removing {
+                    let error = std::io::Error::from_raw_os_error(-result);
+                    let _ = err_tx.send((name.clone(), ProtobufResponse::Error(error)));
+                }
+            } else if motor.backend_specific.removing && !io_uring::cqueue::more(entry.flags()) {
+                motor.backend_specific.read_done = true;
+            }
+
+            if motor.backend_specific.removing
+                && motor.backend_specific.read_done
+                && motor.backend_specific.writes.is_empty()
+            {
+                // the multishot read has terminated and every write completed, so the kernel
+                // is done with the buffers.
+                let _ = ring
+                    .submitter()
+                    .unregister_buf_ring(motor.backend_specific.buf.bgid());
+
+                // dropping the backend closes the device
+                let path = name.clone();
+                connections.remove(&id);
+                connection_to_path.remove(&id);
+                path_to_connection.remove(&path);
+                let _ = err_tx.send((path.clone(), ProtobufResponse::Removed));
+
+                if request_shutdown && connections.is_empty() {
+                    let _ = err_tx.send((path, ProtobufResponse::ShutdownComplete));
+                    return Ok(());
+                }
+                continue;
+            }
+
+            if motor.backend_specific.removing || entry.user_data() & WRITE_MASK != 0 {
+                continue;
+            }
+
             if result >= 0 {
                 let Ok(Some(id)) = motor.backend_specific.buf.buffer_id_from_cqe(&entry) else {
                     continue;
@@ -433,51 +501,15 @@ pub fn event_loop(
                         }),
                     });
 
-                    let buf = cmd.write_to_bytes().unwrap();
-
-                    let write_entry = Write::new(
-                        types::Fd(motor.backend_specific.dev.as_raw_fd()),
-                        buf.as_ptr(),
-                        buf.len() as _,
-                    )
-                    .build()
-                    .user_data(motor.backend_specific.dev.as_raw_fd() as _)
-                    .flags(io_uring::squeue::Flags::SKIP_SUCCESS);
-
-                    while unsafe { ring.submission().push(&write_entry).is_err() } {
-                        ring.submit().expect("could not submit ops");
-                    }
-
-                    ring.submit().expect("could not submit ops");
+                    motor.backend_specific.queue(&mut ring, &cmd)?;
                     continue;
                 }
 
 
                 let Ok(response) = motor::MotorDriverResponse::parse_from_bytes(buf) else {
-                    use motor::{MotorDriver, motor_driver::Motor_cmd};
-                    use protobuf::Message;
-
-                    let mut cmd = MotorDriver::new();
-                    cmd.motor_cmd = Some(Motor_cmd::Velocity(
-                        motor.input_cvp.unwrap_or_default().velocity.round() as _,
-                    ));
-
-                    let buf = cmd.write_to_bytes().unwrap();
-
-                    let write_entry = Write::new(
-                        types::Fd(motor.backend_specific.dev.as_raw_fd()),
-                        buf.as_ptr(),
-                        buf.len() as _,
-                    )
-                    .build()
-                    .user_data(motor.backend_specific.dev.as_raw_fd() as _)
-                    .flags(io_uring::squeue::Flags::SKIP_SUCCESS);
-
-                    while unsafe { ring.submission().push(&write_entry).is_err() } {
-                        ring.submit().expect("could not submit ops");
-                    }
-
-                    ring.submit().expect("could not submit ops");
+                    drop(id);
+                    let velocity = motor.limited_input().velocity.round() as _;
+                    motor.backend_specific.queue_velocity(&mut ring, velocity)?;
                     continue;
                 };
                 drop(id);
@@ -488,8 +520,14 @@ pub fn event_loop(
                     current: response.current.unwrap_or_default() as _,
                 };
 
+                motor.check_output(&cvp);
                 motor.update_input(&err_tx, name);
 
+                let _ = err_tx.send((
+                    name.clone(),
+                    ProtobufResponse::OutputCVP(cvp, time::Instant::now()),
+                ));
+
                 if let Some(controller) = &mut motor.motor_config.controller {
                     println!("sending controller to: {controller:?}");
                     let input = motor.input_cvp.unwrap_or_default();
@@ -500,64 +538,18 @@ pub fn event_loop(
                         ProtobufResponse::ControllerAdjustedCVP(new_input, time::Instant::now()),
                     ));
                     motor.input_cvp = Some(new_input);
-                } else {
-                    let _ = err_tx.send((
-                        name.clone(),
-                        ProtobufResponse::OutputCVP(cvp, time::Instant::now()),
-                    ));
                 }
 
-                use motor::{MotorDriver, motor_driver::Motor_cmd};
-                use protobuf::Message;
-
-                let mut cmd = MotorDriver::new();
-                cmd.motor_cmd = Some(Motor_cmd::Velocity(
-                    motor.input_cvp.unwrap_or_default().velocity.round() as _,
-                ));
-
-                let buf = cmd.write_to_bytes().unwrap();
-
-                let write_entry = Write::new(
-                    types::Fd(motor.backend_specific.dev.as_raw_fd()),
-                    buf.as_ptr(),
-                    buf.len() as _,
-                )
-                .build()
-                .user_data(motor.backend_specific.dev.as_raw_fd() as _)
-                .flags(io_uring::squeue::Flags::SKIP_SUCCESS);
+                let velocity = motor.limited_input().velocity.round() as _;
+                motor.backend_specific.queue_velocity(&mut ring, velocity)?;
 
-                while unsafe { ring.submission().push(&write_entry).is_err() } {
-                    ring.submit().expect("could not submit ops");
+                if let Some(violation) = motor.safety.take_violation() {
+                    let _ = err_tx.send((name.clone(), ProtobufResponse::LimitTripped(violation)));
                 }
-
-                ring.submit().expect("could not submit ops");
             } else {
                 if matches!(-result, libc::EINTR) {
-                    use crate::motor_backend::protobuf::motor::motor_driver::Motor_cmd;
-                    use crate::motor_backend::protobuf::motor::MotorDriver;
-                    use protobuf::Message;
-
-                    let mut cmd = MotorDriver::new();
-                    cmd.motor_cmd = Some(Motor_cmd::Velocity(
-                        motor.input_cvp.unwrap_or_default().velocity.round() as _,
-                    ));
-
-                    let buf = cmd.write_to_bytes().unwrap();
-
-                    let write_entry = Write::new(
-                        types::Fd(motor.backend_specific.dev.as_raw_fd()),
-                        buf.as_ptr(),
-                        buf.len() as _,
-                    )
-                    .build()
-                    .user_data(motor.backend_specific.dev.as_raw_fd() as _)
-                    .flags(io_uring::squeue::Flags::SKIP_SUCCESS);
-
-                    while unsafe { ring.submission().push(&write_entry).is_err() } {
-                        ring.submit().expect("could not submit ops");
-                    }
-
-                    ring.submit().expect("could not submit ops");
+                    let velocity = motor.limited_input().velocity.round() as _;
+                    motor.backend_specific.queue_velocity(&mut ring, velocity)?;
                 } else {
                     println!("err: {result}");
                 }
@@ -568,6 +560,11 @@ pub fn event_loop(
 
 impl Backend<ProtobufBackend> {
     fn update_input(&mut self, end_tx: &mpsc::Sender<(String, ProtobufResponse)>, path: &str) {
+        if self.waiting_to_start() {
+            // keep holding until a synchronized start
+            return;
+        }
+
         let next_input = match &self.request_input {
             None => None,
             Some((_, RequestedMotorInput::Cvp(c))) => Some(*c),
@@ -579,12 +576,9 @@ impl Backend<ProtobufBackend> {
                     let _ = end_tx.send((path.to_string(), ProtobufResponse::EndWaveform));
                     None
                 } else if elapsed > s.delay {
-                    Some(crate::motor_ctx::CVP {
-                        velocity: s.magnitude,
-                        ..Default::default()
-                    })
+                    Some(self.setpoint(Some(s.magnitude)))
                 } else {
-                    Some(Default::default())
+                    Some(self.setpoint(None))
                 }
             }
             Some((then, RequestedMotorInput::Impulse(i))) => {
@@ -597,12 +591,9 @@ impl Backend<ProtobufBackend> {
                     let _ = end_tx.send((path.to_string(), ProtobufResponse::EndWaveform));
                     None
                 } else if elapsed < i.delay + EPSILON && elapsed > i.delay - EPSILON {
-                    Some(crate::motor_ctx::CVP {
-                        velocity: i.magnitude,
-                        ..Default::default()
-                    })
+                    Some(self.setpoint(Some(i.magnitude)))
                 } else {
-                    Some(Default::default())
+                    Some(self.setpoint(None))
                 }
             }
             Some((then, RequestedMotorInput::Custom(c))) => {
@@ -625,6 +616,19 @@ impl Backend<ProtobufBackend> {
                 }
                 cvp
             }
+            Some((then, RequestedMotorInput::Trajectory(profile))) => {
+                let now = time::Instant::now();
+                let elapsed = now.duration_since(*then);
+                let cvp = profile.sample(elapsed);
+
+                if elapsed > profile.duration() {
+                    let _ = end_tx.send((path.to_string(), ProtobufResponse::EndWaveform));
+                    // hold the target rather than falling back to a zero setpoint
+                    self.request_input = Some((now, RequestedMotorInput::Cvp(cvp)));
+                }
+                Some(cvp)
+            }
+            Some((_, RequestedMotorInput::Follow(c))) => Some(*c),
         };
 
         self.input_cvp = next_input;
@@ -768,4 +772,87 @@ baud_rate! {
 struct ProtobufBackend {
     dev: RawDevice,
     buf: io_uring_buf_ring::BufRing<io_uring_buf_ring::buf_ring_state::Init>,
+    removing: bool,
+    // set once the read has terminated while removing
+    read_done: bool,
+    // the commands being written, keyed by the id in the write's user data. the kernel reads
+    // them until the write completes.
+    writes: std::collections::HashMap<u64, Vec<u8>>,
+    next_write: u64,
+}
+
+impl Backend<ProtobufBackend> {
+    // stops the motor and cancels its read, the connection is dropped when the cancellation completes.
+    fn start_removal(&mut self, ring: &mut io_uring::IoUring) -> std::io::Result<()> {
+        use io_uring::opcode::AsyncCancel;
+
+        if self.backend_specific.removing {
+            return Ok(());
+        }
+
+        self.stop();
+        self.backend_specific.removing = true;
+        self.backend_specific.queue_velocity(ring, 0)?;
+
+        let dev_id = self.backend_specific.dev.as_raw_fd() as u64;
+        let cancel = AsyncCancel::new(dev_id)
+            .build()
+            .user_data(dev_id | CANCEL_MASK);
+
+        while unsafe { ring.submission().push(&cancel).is_err() } {
+            ring.submit()?;
+        }
+
+        ring.submit()?;
+        Ok(())
+    }
+}
+
+impl ProtobufBackend {
+    // writes a velocity command without waiting for the next response from the motor.
+    fn queue_velocity(
+        &mut self,
+        ring: &mut io_uring::IoUring,
+        velocity: i32,
+    ) -> std::io::Result<()> {
+        use motor::{MotorDriver, motor_driver::Motor_cmd};
+
+        let mut cmd = MotorDriver::new();
+        cmd.motor_cmd = Some(Motor_cmd::Velocity(velocity));
+
+        self.queue(ring, &cmd)
+    }
+
+    fn queue(
+        &mut self,
+        ring: &mut io_uring::IoUring,
+        cmd: &motor::MotorDriver,
+    ) -> std::io::Result<()> {
+        use io_uring::opcode::Write;
+        use io_uring::types;
+        use protobuf::Message;
+
+        let buf = cmd.write_to_bytes().unwrap();
+
+        let id = self.next_write;
+        self.next_write = (self.next_write + 1) & (u64::MAX >> WRITE_ID_SHIFT);
+
+        let write_entry = Write::new(
+            types::Fd(self.dev.as_raw_fd()),
+            buf.as_ptr(),
+            buf.len() as _,
+        )
+        .build()
+        .user_data(self.dev.as_raw_fd() as u64 | WRITE_MASK | (id << WRITE_ID_SHIFT));
+
+        // moving the vec keeps its heap buffer where the kernel expects it
+        self.writes.insert(id, buf);
+
+        while unsafe { ring.submission().push(&write_entry).is_err() } {
+            ring.submit()?;
+        }
+
+        ring.submit()?;
+        Ok(())
+    }
 }
@@ -21,6 +21,7 @@ pub struct MotorConfig {
     pub gear_reduction: f64,
     pub controller: Option<Controller>,
     pub state: ControlState,
+    pub limits: SafetyLimits,
 }
 
 #[derive(Clone, Copy, Default, Debug)]
@@ -29,3 +30,448 @@ pub struct CVP {
     pub position: f64,
     pub velocity: f64,
 }
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum LimitAction {
+    #[default]
+    Clamp,
+    RampToZero,
+    // disables the motor, it is enabled again once the limit is reset by a new waveform.
+    Fault,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum LimitKind {
+    Position,
+    Velocity,
+    Current,
+    Acceleration,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Limit {
+    pub min: f64,
+    pub max: f64,
+    pub action: LimitAction,
+}
+
+impl Limit {
+    fn contains(&self, value: f64) -> bool {
+        value >= self.min && value <= self.max
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SafetyLimits {
+    pub position: Option<Limit>,
+    pub velocity: Option<Limit>,
+    pub current: Option<Limit>,
+    // only `max` is used, as a magnitude in units/s^2
+    pub acceleration: Option<Limit>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LimitViolation {
+    pub kind: LimitKind,
+    pub action: LimitAction,
+    pub value: f64,
+    // the violation came from the motor's telemetry rather than from a command
+    pub measured: bool,
+}
+
+impl core::fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let source = if self.measured { "measured" } else { "commanded" };
+        write!(
+            f,
+            "{source} {:?} of {} exceeded its limit ({:?})",
+            self.kind, self.value, self.action
+        )
+    }
+}
+
+// per motor runtime state for enforcing `SafetyLimits`.
+#[derive(Default, Debug)]
+pub struct SafetyState {
+    last: Option<(CVP, std::time::Instant)>,
+    tripped: Option<LimitViolation>,
+    unreported: Option<LimitViolation>,
+    stopped: bool,
+}
+
+impl SafetyState {
+    pub fn tripped(&self) -> Option<&LimitViolation> {
+        self.tripped.as_ref()
+    }
+
+    pub fn reset(&mut self) {
+        self.tripped = None;
+        self.unreported = None;
+    }
+
+    // latches a stop (zero velocity and current, holding the last position) until `release` is called.
+    // unlike a tripped limit, this is not cleared by `reset`.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    pub fn release(&mut self) {
+        self.stopped = false;
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    // a limit with `LimitAction::Fault` tripped, the backend has to disable the motor.
+    pub fn faulted(&self) -> bool {
+        self.tripped.is_some_and(|violation| violation.action == LimitAction::Fault)
+    }
+
+    // the violation that tripped the limits, if it has not been sent to the ui yet.
+    pub fn take_violation(&mut self) -> Option<LimitViolation> {
+        self.unreported.take()
+    }
+
+    // returns the command that is safe to send in place of `cvp`.
+    pub fn limit(&mut self, limits: &SafetyLimits, cvp: CVP, now: std::time::Instant) -> CVP {
+        if self.stopped || self.tripped.is_some() {
+            let out = self.safe_command(limits, now);
+            self.last = Some((out, now));
+            return out;
+        }
+
+        let mut out = cvp;
+        let mut violation = None;
+
+        for (kind, limit, value) in [
+            (LimitKind::Position, &limits.position, &mut out.position),
+            (LimitKind::Velocity, &limits.velocity, &mut out.velocity),
+            (LimitKind::Current, &limits.current, &mut out.current),
+        ] {
+            let Some(limit) = limit else {
+                continue;
+            };
+
+            if limit.contains(*value) {
+                continue;
+            }
+
+            match limit.action {
+                LimitAction::Clamp => *value = (*value).clamp(limit.min, limit.max),
+                action => {
+                    violation.get_or_insert(LimitViolation {
+                        kind,
+                        action,
+                        value: *value,
+                        measured: false,
+                    });
+                }
+            }
+        }
+
+        if let (Some(limit), Some((last, then))) = (&limits.acceleration, &self.last) {
+            let dt = now.duration_since(*then).as_secs_f64();
+            let max_dv = limit.max.abs() * dt;
+            let dv = out.velocity - last.velocity;
+
+            if dt > 0. && dv.abs() > max_dv {
+                match limit.action {
+                    LimitAction::Clamp => out.velocity = last.velocity + dv.signum() * max_dv,
+                    action => {
+                        violation.get_or_insert(LimitViolation {
+                            kind: LimitKind::Acceleration,
+                            action,
+                            value: dv / dt,
+                            measured: false,
+                        });
+                    }
+                }
+            }
+        }
+
+        if violation.is_some() {
+            self.tripped = violation;
+            self.unreported = violation;
+            out = self.safe_command(limits, now);
+        }
+
+        self.last = Some((out, now));
+        out
+    }
+
+    // trips the limits when the motor reports values outside of them.
+    // measurements cannot be clamped, so a clamping limit ramps to zero instead.
+    pub fn check_measured(&mut self, limits: &SafetyLimits, cvp: &CVP) {
+        if self.tripped.is_some() {
+            return;
+        }
+
+        let violation = [
+            (LimitKind::Position, &limits.position, cvp.position),
+            (LimitKind::Velocity, &limits.velocity, cvp.velocity),
+            (LimitKind::Current, &limits.current, cvp.current),
+        ]
+        .into_iter()
+        .find_map(|(kind, limit, value)| {
+            let limit = limit.as_ref()?;
+            if limit.contains(value) {
+                return None;
+            }
+
+            let action = match limit.action {
+                LimitAction::Clamp => LimitAction::RampToZero,
+                action => action,
+            };
+
+            Some(LimitViolation {
+                kind,
+                action,
+                value,
+                measured: true,
+            })
+        });
+
+        if violation.is_some() {
+            self.tripped = violation;
+            self.unreported = violation;
+        }
+    }
+
+    fn safe_command(&self, limits: &SafetyLimits, now: std::time::Instant) -> CVP {
+        let Some((last, then)) = self.last else {
+            return CVP::default();
+        };
+
+        match self.tripped.map(|v| v.action) {
+            Some(LimitAction::RampToZero) if !self.stopped => {
+                let dt = now.duration_since(then).as_secs_f64();
+
+                // without an acceleration limit there is nothing to ramp with, so stop immediately.
+                let velocity = match limits.acceleration.map(|limit| limit.max.abs() * dt) {
+                    Some(dv) if last.velocity.abs() > dv => last.velocity - last.velocity.signum() * dv,
+                    _ => 0.,
+                };
+
+                CVP {
+                    position: last.position + velocity * dt,
+                    velocity,
+                    current: 0.,
+                }
+            }
+            _ => CVP {
+                position: last.position,
+                velocity: 0.,
+                current: 0.,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    const EPSILON: f64 = 1e-9;
+
+    fn limit(min: f64, max: f64, action: LimitAction) -> Option<Limit> {
+        Some(Limit { min, max, action })
+    }
+
+    fn cvp(position: f64, velocity: f64, current: f64) -> CVP {
+        CVP {
+            position,
+            velocity,
+            current,
+        }
+    }
+
+    #[test]
+    fn clamps_commands_into_their_limits() {
+        let limits = SafetyLimits {
+            position: limit(-1., 1., LimitAction::Clamp),
+            current: limit(-2., 2., LimitAction::Clamp),
+            ..Default::default()
+        };
+        let mut safety = SafetyState::default();
+
+        let out = safety.limit(&limits, cvp(3., 5., -4.), Instant::now());
+        assert_eq!(out.position, 1.);
+        assert_eq!(out.velocity, 5.);
+        assert_eq!(out.current, -2.);
+        assert!(safety.tripped().is_none());
+        assert!(safety.take_violation().is_none());
+    }
+
+    #[test]
+    fn ramps_to_zero_at_the_acceleration_limit() {
+        let limits = SafetyLimits {
+            velocity: limit(-1., 1., LimitAction::RampToZero),
+            acceleration: limit(0., 10., LimitAction::Clamp),
+            ..Default::default()
+        };
+        let mut safety = SafetyState::default();
+        let start = Instant::now();
+
+        safety.limit(&limits, cvp(0., 0.5, 0.), start);
+        let then = start + Duration::from_millis(10);
+        let out = safety.limit(&limits, cvp(0., 5., 1.), then);
+
+        let violation = safety.take_violation().expect("the velocity limit tripped");
+        assert_eq!(violation.kind, LimitKind::Velocity);
+        assert_eq!(violation.action, LimitAction::RampToZero);
+        assert!(!violation.measured);
+        assert!((out.velocity - 0.4).abs() < EPSILON);
+        assert_eq!(out.current, 0.);
+
+        // keeps slowing down whatever is commanded, down to standstill
+        let mut now = then;
+        let mut velocity = out.velocity;
+        for _ in 0..100 {
+            now += Duration::from_millis(10);
+            let out = safety.limit(&limits, cvp(0., 0.5, 0.), now);
+            assert!(out.velocity <= velocity);
+            velocity = out.velocity;
+        }
+        assert_eq!(velocity, 0.);
+        // reported once
+        assert!(safety.take_violation().is_none());
+    }
+
+    #[test]
+    fn ramps_to_zero_immediately_without_an_acceleration_limit() {
+        let limits = SafetyLimits {
+            position: limit(-1., 1., LimitAction::RampToZero),
+            ..Default::default()
+        };
+        let mut safety = SafetyState::default();
+        let start = Instant::now();
+
+        safety.limit(&limits, cvp(0.5, 2., 1.), start);
+        let out = safety.limit(&limits, cvp(1.5, 2., 1.), start + Duration::from_millis(10));
+        assert_eq!(out.velocity, 0.);
+        assert_eq!(out.current, 0.);
+        assert!((out.position - 0.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn fault_latches_until_reset() {
+        let limits = SafetyLimits {
+            current: limit(-1., 1., LimitAction::Fault),
+            ..Default::default()
+        };
+        let mut safety = SafetyState::default();
+        let start = Instant::now();
+
+        safety.limit(&limits, cvp(0.25, 0., 0.), start);
+        safety.limit(&limits, cvp(0.25, 0., 3.), start + Duration::from_millis(1));
+        assert!(safety.faulted());
+
+        // a command within the limits does not clear it, the position is held
+        let out = safety.limit(
+            &limits,
+            cvp(0.75, 1., 0.5),
+            start + Duration::from_millis(2),
+        );
+        assert!(safety.faulted());
+        assert_eq!(out.position, 0.25);
+        assert_eq!(out.velocity, 0.);
+        assert_eq!(out.current, 0.);
+
+        safety.reset();
+        assert!(!safety.faulted());
+        let out = safety.limit(
+            &limits,
+            cvp(0.75, 1., 0.5),
+            start + Duration::from_millis(3),
+        );
+        assert_eq!(out.position, 0.75);
+        assert_eq!(out.current, 0.5);
+    }
+
+    #[test]
+    fn clamps_the_acceleration() {
+        let limits = SafetyLimits {
+            acceleration: limit(0., 100., LimitAction::Clamp),
+            ..Default::default()
+        };
+        let mut safety = SafetyState::default();
+        let start = Instant::now();
+
+        safety.limit(&limits, cvp(0., 0., 0.), start);
+        let out = safety.limit(&limits, cvp(0., 10., 0.), start + Duration::from_millis(10));
+        assert!((out.velocity - 1.).abs() < EPSILON);
+
+        let out = safety.limit(
+            &limits,
+            cvp(0., -10., 0.),
+            start + Duration::from_millis(20),
+        );
+        assert!(out.velocity.abs() < EPSILON);
+        assert!(safety.tripped().is_none());
+    }
+
+    #[test]
+    fn trips_on_excessive_acceleration() {
+        let limits = SafetyLimits {
+            acceleration: limit(0., 100., LimitAction::Fault),
+            ..Default::default()
+        };
+        let mut safety = SafetyState::default();
+        let start = Instant::now();
+
+        safety.limit(&limits, cvp(0., 0., 0.), start);
+        safety.limit(&limits, cvp(0., 10., 0.), start + Duration::from_millis(10));
+
+        let violation = safety
+            .take_violation()
+            .expect("the acceleration limit tripped");
+        assert_eq!(violation.kind, LimitKind::Acceleration);
+        assert!((violation.value - 1000.).abs() < EPSILON);
+        assert!(safety.faulted());
+    }
+
+    #[test]
+    fn measured_values_cannot_be_clamped() {
+        let limits = SafetyLimits {
+            position: limit(-1., 1., LimitAction::Clamp),
+            current: limit(-1., 1., LimitAction::Fault),
+            ..Default::default()
+        };
+
+        let mut safety = SafetyState::default();
+        safety.check_measured(&limits, &cvp(0.5, 0., 0.5));
+        assert!(safety.tripped().is_none());
+
+        safety.check_measured(&limits, &cvp(2., 0., 0.));
+        let violation = safety.take_violation().expect("the position limit tripped");
+        assert_eq!(violation.kind, LimitKind::Position);
+        assert_eq!(violation.action, LimitAction::RampToZero);
+        assert!(violation.measured);
+        assert!(!safety.faulted());
+
+        // a fault limit keeps its action
+        let mut safety = SafetyState::default();
+        safety.check_measured(&limits, &cvp(0., 0., -2.));
+        assert!(safety.faulted());
+    }
+
+    #[test]
+    fn stop_outlasts_a_reset() {
+        let limits = SafetyLimits::default();
+        let mut safety = SafetyState::default();
+        let start = Instant::now();
+
+        safety.limit(&limits, cvp(0.5, 1., 1.), start);
+        safety.stop();
+        safety.reset();
+        let out = safety.limit(&limits, cvp(2., 1., 1.), start + Duration::from_millis(1));
+        assert_eq!(out.position, 0.5);
+        assert_eq!(out.velocity, 0.);
+
+        safety.release();
+        let out = safety.limit(&limits, cvp(2., 1., 1.), start + Duration::from_millis(2));
+        assert_eq!(out.position, 2.);
+    }
+}
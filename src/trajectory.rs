@@ -0,0 +1,344 @@
+use crate::motor_ctx::CVP;
+use std::time::Duration;
+
+// a rest to rest move from wherever the motor is to `target`.
+#[derive(Clone, Debug)]
+pub struct TrajectoryInput {
+    pub target: f64,
+    pub max_velocity: f64,
+    pub max_acceleration: f64,
+    // `None` gives a trapezoidal velocity profile, otherwise an s-curve.
+    pub jerk: Option<f64>,
+    // current fed forward per unit of acceleration.
+    pub current_per_acceleration: f64,
+}
+
+impl Default for TrajectoryInput {
+    fn default() -> Self {
+        Self {
+            target: 1.,
+            max_velocity: 1.,
+            max_acceleration: 1.,
+            jerk: None,
+            current_per_acceleration: 0.,
+        }
+    }
+}
+
+impl TrajectoryInput {
+    pub fn is_valid(&self) -> bool {
+        self.target.is_finite()
+            && self.max_velocity > 0.
+            && self.max_acceleration > 0.
+            && self.jerk.is_none_or(|jerk| jerk > 0.)
+            && self.current_per_acceleration.is_finite()
+    }
+
+    pub fn plan(&self, start: f64) -> Profile {
+        let distance = self.target - start;
+        let peak = Ramp::peak_velocity(distance.abs(), self);
+        let ramp = Ramp::new(peak, self);
+
+        let cruise = if peak > 0. {
+            (distance.abs() - peak * ramp.duration) / peak
+        } else {
+            0.
+        };
+
+        Profile {
+            start,
+            distance: distance.abs(),
+            direction: distance.signum(),
+            ramp,
+            cruise: cruise.max(0.),
+            current_per_acceleration: self.current_per_acceleration,
+        }
+    }
+}
+
+// the acceleration phase from rest to `peak` velocity, the deceleration mirrors it.
+#[derive(Clone, Copy, Debug)]
+struct Ramp {
+    peak: f64,
+    jerk: f64,
+    acceleration: f64,
+    // time spent changing the acceleration, zero for a trapezoidal profile
+    jerk_time: f64,
+    duration: f64,
+}
+
+impl Ramp {
+    fn new(peak: f64, input: &TrajectoryInput) -> Self {
+        let max_acceleration = input.max_acceleration;
+
+        let (jerk_time, duration) = match input.jerk {
+            Some(jerk) if peak * jerk >= max_acceleration * max_acceleration => {
+                let jerk_time = max_acceleration / jerk;
+                (jerk_time, jerk_time + peak / max_acceleration)
+            }
+            // the acceleration limit is never reached
+            Some(jerk) => {
+                let jerk_time = (peak / jerk).sqrt();
+                (jerk_time, 2. * jerk_time)
+            }
+            None => (0., peak / max_acceleration),
+        };
+
+        let acceleration = if jerk_time > 0. {
+            peak / (duration - jerk_time)
+        } else {
+            max_acceleration
+        };
+
+        Self {
+            peak,
+            jerk: if jerk_time > 0. {
+                acceleration / jerk_time
+            } else {
+                0.
+            },
+            acceleration,
+            jerk_time,
+            duration,
+        }
+    }
+
+    fn distance(&self) -> f64 {
+        self.peak * self.duration / 2.
+    }
+
+    // the highest velocity that still allows stopping within `distance`.
+    fn peak_velocity(distance: f64, input: &TrajectoryInput) -> f64 {
+        let covered = |peak: f64| peak * Self::new(peak, input).duration;
+
+        if covered(input.max_velocity) <= distance {
+            return input.max_velocity;
+        }
+
+        let (mut low, mut high) = (0., input.max_velocity);
+        for _ in 0..64 {
+            let mid = (low + high) / 2.;
+            if covered(mid) > distance {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        low
+    }
+
+    // position, velocity and acceleration `t` seconds into the ramp.
+    fn sample(&self, t: f64) -> (f64, f64, f64) {
+        let Self {
+            peak,
+            jerk,
+            acceleration,
+            jerk_time,
+            duration,
+        } = *self;
+
+        let t = t.clamp(0., duration);
+
+        if t < jerk_time {
+            (jerk * t.powi(3) / 6., jerk * t * t / 2., jerk * t)
+        } else if t <= duration - jerk_time {
+            let v0 = jerk * jerk_time * jerk_time / 2.;
+            let p0 = jerk * jerk_time.powi(3) / 6.;
+            let dt = t - jerk_time;
+            (
+                p0 + v0 * dt + acceleration * dt * dt / 2.,
+                v0 + acceleration * dt,
+                acceleration,
+            )
+        } else {
+            let tau = duration - t;
+            (
+                self.distance() - peak * tau + jerk * tau.powi(3) / 6.,
+                peak - jerk * tau * tau / 2.,
+                jerk * tau,
+            )
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Profile {
+    start: f64,
+    distance: f64,
+    direction: f64,
+    ramp: Ramp,
+    cruise: f64,
+    current_per_acceleration: f64,
+}
+
+impl Profile {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(2. * self.ramp.duration + self.cruise)
+    }
+
+    pub fn sample(&self, elapsed: Duration) -> CVP {
+        let t = elapsed.as_secs_f64();
+        let ramp = self.ramp.duration;
+
+        let (position, velocity, acceleration) = if t < ramp {
+            self.ramp.sample(t)
+        } else if t < ramp + self.cruise {
+            (
+                self.ramp.distance() + self.ramp.peak * (t - ramp),
+                self.ramp.peak,
+                0.,
+            )
+        } else {
+            let (p, v, a) = self.ramp.sample(2. * ramp + self.cruise - t);
+            (self.distance - p, v, -a)
+        };
+
+        CVP {
+            position: self.start + self.direction * position,
+            velocity: self.direction * velocity,
+            current: self.direction * acceleration * self.current_per_acceleration,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-6;
+
+    fn input(max_velocity: f64, max_acceleration: f64, jerk: Option<f64>) -> TrajectoryInput {
+        TrajectoryInput {
+            target: 0.,
+            max_velocity,
+            max_acceleration,
+            jerk,
+            current_per_acceleration: 0.,
+        }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < EPSILON, "{a} != {b}");
+    }
+
+    #[test]
+    fn trapezoidal_ramp() {
+        let ramp = Ramp::new(2., &input(2., 1., None));
+        assert_close(ramp.jerk_time, 0.);
+        assert_close(ramp.duration, 2.);
+        assert_close(ramp.distance(), 2.);
+
+        let (p, v, a) = ramp.sample(1.);
+        assert_close(p, 0.5);
+        assert_close(v, 1.);
+        assert_close(a, 1.);
+
+        let (p, v, _) = ramp.sample(ramp.duration);
+        assert_close(p, ramp.distance());
+        assert_close(v, 2.);
+    }
+
+    #[test]
+    fn s_curve_reaching_the_acceleration_limit() {
+        let ramp = Ramp::new(2., &input(2., 1., Some(2.)));
+        assert_close(ramp.jerk_time, 0.5);
+        assert_close(ramp.duration, 2.5);
+        assert_close(ramp.acceleration, 1.);
+        assert_close(ramp.jerk, 2.);
+
+        let (_, v, a) = ramp.sample(0.);
+        assert_close(v, 0.);
+        assert_close(a, 0.);
+
+        let (p, v, a) = ramp.sample(ramp.duration);
+        assert_close(p, ramp.distance());
+        assert_close(v, 2.);
+        assert_close(a, 0.);
+    }
+
+    #[test]
+    fn s_curve_below_the_acceleration_limit() {
+        let ramp = Ramp::new(0.5, &input(0.5, 1., Some(1.)));
+        assert_close(ramp.jerk_time, 0.5f64.sqrt());
+        assert_close(ramp.duration, 2. * 0.5f64.sqrt());
+        assert!(ramp.acceleration <= 1.);
+
+        let (_, v, a) = ramp.sample(ramp.jerk_time);
+        assert_close(v, 0.25);
+        assert_close(a, ramp.acceleration);
+    }
+
+    #[test]
+    fn ramp_is_continuous_between_phases() {
+        let ramp = Ramp::new(2., &input(2., 1., Some(2.)));
+        for t in [ramp.jerk_time, ramp.duration - ramp.jerk_time] {
+            let before = ramp.sample(t - 1e-9);
+            let after = ramp.sample(t + 1e-9);
+            assert!((before.0 - after.0).abs() < 1e-6);
+            assert!((before.1 - after.1).abs() < 1e-6);
+            assert!((before.2 - after.2).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn peak_velocity_is_capped_on_long_moves() {
+        let input = input(1., 1., None);
+        assert_close(Ramp::peak_velocity(10., &input), 1.);
+    }
+
+    #[test]
+    fn peak_velocity_stops_within_short_moves() {
+        // a triangular profile covers peak^2 / acceleration
+        let trapezoidal = input(1., 1., None);
+        assert_close(Ramp::peak_velocity(0.25, &trapezoidal), 0.5);
+
+        let s_curve = input(1., 1., Some(1.));
+        let peak = Ramp::peak_velocity(0.25, &s_curve);
+        let ramp = Ramp::new(peak, &s_curve);
+        assert!(peak < 1.);
+        assert_close(2. * ramp.distance(), 0.25);
+    }
+
+    #[test]
+    fn profile_runs_from_start_to_target() {
+        let input = TrajectoryInput {
+            target: -3.,
+            jerk: Some(4.),
+            current_per_acceleration: 0.5,
+            ..input(1., 2., None)
+        };
+        let profile = input.plan(1.);
+
+        let start = profile.sample(Duration::ZERO);
+        assert_close(start.position, 1.);
+        assert_close(start.velocity, 0.);
+
+        let middle = profile.sample(profile.duration() / 2);
+        assert_close(middle.position, -1.);
+        assert_close(middle.velocity, -1.);
+        assert_close(middle.current, 0.);
+
+        let end = profile.sample(profile.duration());
+        assert_close(end.position, -3.);
+        assert_close(end.velocity, 0.);
+
+        let after = profile.sample(profile.duration() * 2);
+        assert_close(after.position, -3.);
+    }
+
+    #[test]
+    fn profile_feeds_current_forward() {
+        let input = TrajectoryInput {
+            target: 4.,
+            current_per_acceleration: 0.5,
+            ..input(1., 2., None)
+        };
+        let profile = input.plan(0.);
+
+        let accelerating = profile.sample(Duration::from_millis(100));
+        assert_close(accelerating.current, 1.);
+
+        let decelerating = profile.sample(profile.duration() - Duration::from_millis(100));
+        assert_close(decelerating.current, -1.);
+    }
+}